@@ -0,0 +1,27 @@
+use crate::ir::lower_source;
+use crate::read_source;
+use std::process;
+use tiny_ir::opt::optimise;
+use tiny_ir::x86_64;
+use tiny_parser::Dialect;
+
+const USAGE: &str = "Usage: tiny asm [--textbook] [--optimise] <FILE | ->";
+
+/// Prints the x86-64 assembly of the file (or stdin, for `-`), to be built with
+/// `cc program.s -o program`. With `--optimise`, of the optimised program.
+pub fn run(args: &[String], dialect: &Dialect) {
+    let (optimised, path) = match args {
+        [path] => (false, path),
+        [flag, path] if flag == "--optimise" => (true, path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    let program = lower_source(&read_source(path), dialect);
+    if optimised {
+        print!("{}", x86_64::emit(&optimise(&program)));
+    } else {
+        print!("{}", x86_64::emit(&program));
+    }
+}
@@ -0,0 +1,43 @@
+use crate::ir::lower_source;
+use crate::read_source;
+use std::process;
+use tiny_ir::cfg::{self, Cfg};
+use tiny_parser::Dialect;
+
+const USAGE: &str = "Usage: tiny cfg [--textbook] [--dot [--dominators] | --json] <FILE | ->";
+
+enum Format {
+    Text,
+    Dot { dominators: bool },
+    Json,
+}
+
+/// Prints the control-flow graphs of the procedures and the main program of the
+/// file (or stdin, for `-`), as text, Graphviz or JSON.
+pub fn run(args: &[String], dialect: &Dialect) {
+    let (format, path) = match args {
+        [path] => (Format::Text, path),
+        [flag, path] if flag == "--dot" => (Format::Dot { dominators: false }, path),
+        [flag, dom, path] if flag == "--dot" && dom == "--dominators" => {
+            (Format::Dot { dominators: true }, path)
+        }
+        [flag, path] if flag == "--json" => (Format::Json, path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let cfgs: Vec<Cfg> = lower_source(&read_source(path), dialect)
+        .functions()
+        .map(Cfg::new)
+        .collect();
+    match format {
+        Format::Text => {
+            let dumps: Vec<String> = cfgs.iter().map(ToString::to_string).collect();
+            print!("{}", dumps.join("\n"));
+        }
+        Format::Dot { dominators } => print!("{}", cfg::to_dot(&cfgs, dominators)),
+        Format::Json => println!("{}", cfg::to_json(&cfgs).pretty(2)),
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{exit_with_errors, read_source};
+use std::process;
+use tiny_parser::ast::lower_with;
+use tiny_parser::fold::fold;
+use tiny_parser::jsonify::treantify_node_with;
+use tiny_parser::pretty::to_source;
+use tiny_parser::{parse_with, semantic, Dialect};
+
+const USAGE: &str = "Usage: tiny fold [--textbook] [--treant] <FILE | ->";
+
+/// Prints the file (or stdin, for `-`) with its constants folded, as TINY source or
+/// as the tree of that source for the Treant viewer.
+pub fn run(args: &[String], dialect: &Dialect) {
+    let (treant, path) = match args {
+        [path] => (false, path),
+        [flag, path] if flag == "--treant" => (true, path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let src = read_source(path);
+    let program = lower_with(&parse_with(&src, true, dialect), &src, dialect).and_then(|program| {
+        semantic::check(&program)
+            .and_then(|_| fold(&program))
+            .map_err(|errors| errors.iter().map(ToString::to_string).collect())
+    });
+    let folded = match program {
+        Ok(program) => to_source(&program),
+        Err(errors) => exit_with_errors(&src, dialect, errors),
+    };
+
+    if treant {
+        let root = parse_with(&folded, true, dialect);
+        println!("{}", treantify_node_with(&root, &folded, dialect).pretty(2));
+    } else {
+        print!("{}", folded);
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{exit_with_errors, read_source};
+use std::process;
+use tiny_ir::opt::optimise;
+use tiny_ir::{lower, Program};
+use tiny_parser::ast::lower_with;
+use tiny_parser::{parse_with, semantic, Dialect};
+
+const USAGE: &str = "Usage: tiny ir [--textbook] [--optimise] <FILE | ->";
+
+/// Prints the three-address code of the file (or stdin, for `-`), optimised
+/// with `--optimise`.
+pub fn run(args: &[String], dialect: &Dialect) {
+    let (optimised, path) = match args {
+        [path] => (false, path),
+        [flag, path] if flag == "--optimise" => (true, path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    let program = lower_source(&read_source(path), dialect);
+    if optimised {
+        print!("{}", optimise(&program));
+    } else {
+        print!("{}", program);
+    }
+}
+
+/// The three-address code of `src`, exiting with its errors (and the lexer's
+/// warnings) if it has any.
+pub fn lower_source(src: &str, dialect: &Dialect) -> Program {
+    let program = lower_with(&parse_with(src, true, dialect), src, dialect).and_then(|program| {
+        semantic::check(&program)
+            .map(|_| program)
+            .map_err(|errors| errors.iter().map(ToString::to_string).collect())
+    });
+    match program {
+        Ok(program) => lower(&program),
+        Err(errors) => exit_with_errors(src, dialect, errors),
+    }
+}
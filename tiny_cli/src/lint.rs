@@ -0,0 +1,31 @@
+use crate::ir::lower_source;
+use crate::read_source;
+use std::process;
+use tiny_ir::lint;
+use tiny_lexer::lexer::{tokenize_with, warnings};
+use tiny_parser::Dialect;
+
+const USAGE: &str = "Usage: tiny lint [--textbook] <FILE | ->";
+
+/// Prints the warnings for the file (or stdin, for `-`): the lexer's, then those
+/// of the data-flow analyses about assignments that are never used and variables
+/// read before they're assigned.
+pub fn run(args: &[String], dialect: &Dialect) {
+    let path = match args {
+        [path] => path,
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let src = read_source(path);
+    // A program with errors exits here, with the lexer's warnings before them.
+    let program = lower_source(&src, dialect);
+    for warning in warnings(&src, &tokenize_with(&src, false, dialect), dialect) {
+        println!("{}", warning);
+    }
+    for warning in lint::warnings(&program) {
+        println!("{}", warning);
+    }
+}
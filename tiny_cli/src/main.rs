@@ -0,0 +1,83 @@
+mod asm;
+mod cfg;
+mod fold;
+mod ir;
+mod lint;
+mod repl;
+mod ssa;
+mod trace;
+
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+use tiny_lexer::lexer::{tokenize_with, warnings};
+use tiny_parser::Dialect;
+
+const USAGE: &str = "Usage: tiny <command> [--textbook]
+
+Commands:
+    repl    Read, parse and run TINY statements interactively
+    trace   Show how the parser derives a file: tiny trace [--json | --derivation] <FILE | ->
+    fold    Fold the constants in a file and print it again: tiny fold [--treant] <FILE | ->
+    ir      Print the three-address code of a file: tiny ir [--optimise] <FILE | ->
+    cfg     Print the control-flow graphs of a file: tiny cfg [--dot [--dominators] | --json] <FILE | ->
+    lint    Warn about unused assignments and reads of unassigned variables: tiny lint <FILE | ->
+    ssa     Print the SSA form of a file: tiny ssa [--optimise] <FILE | ->
+    asm     Print the x86-64 assembly of a file, for cc: tiny asm [--optimise] <FILE | ->
+
+Options:
+    --textbook  Accept only the TINY of the textbook, without the course extensions";
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let dialect = if args.iter().any(|arg| arg == "--textbook") {
+        args.retain(|arg| arg != "--textbook");
+        Dialect::textbook()
+    } else {
+        Dialect::extended()
+    };
+    match args.first().map(String::as_str) {
+        Some("repl") if args.len() == 1 => repl::run(dialect),
+        Some("trace") => trace::run(&args[1..], &dialect),
+        Some("fold") => fold::run(&args[1..], &dialect),
+        Some("ir") => ir::run(&args[1..], &dialect),
+        Some("cfg") => cfg::run(&args[1..], &dialect),
+        Some("lint") => lint::run(&args[1..], &dialect),
+        Some("ssa") => ssa::run(&args[1..], &dialect),
+        Some("asm") => asm::run(&args[1..], &dialect),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+/// Prints the lexer's warnings about `src`, which often explain what went wrong,
+/// then `errors`, and exits.
+fn exit_with_errors(src: &str, dialect: &Dialect, errors: Vec<String>) -> ! {
+    for warning in warnings(src, &tokenize_with(src, false, dialect), dialect) {
+        eprintln!("{}", warning);
+    }
+    for err in errors {
+        eprintln!("{}", err);
+    }
+    process::exit(1);
+}
+
+/// The contents of the file at `path`, or of stdin for `-`. Exits if it can't be read.
+fn read_source(path: &str) -> String {
+    let src = if path == "-" {
+        let mut src = String::new();
+        io::stdin().read_to_string(&mut src).map(|_| src)
+    } else {
+        fs::read_to_string(path)
+    };
+    match src {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", path, err);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::io::{self, Write};
+use tiny_interpreter::Interpreter;
+use tiny_lexer::lexer::{tokenize_with, unterminated_comment, warnings};
+use tiny_parser::ast::lower_with;
+use tiny_parser::semantic::check_with;
+use tiny_parser::{parse_with, Dialect, ErrorType, Node, NodeType};
+
+const HELP: &str =
+    "Enter TINY statements; input is run once it forms a complete statement sequence.
+A trailing ';', an open '{' comment or an open 'if'/'repeat'/'while'/'procedure' keeps
+reading on the next line; ':cancel' there throws away what was entered so far.
+
+Commands:
+    :tokens     Show the tokens of the last input
+    :tree       Show the syntax tree of the last input
+    :tree full  Show the full parse tree of the last input
+    :vars       Show the variables and arrays with their values
+    :procs      Show the declared procedures
+    :reset      Forget all variables, arrays and procedures
+    :help       Show this message
+    :quit       Leave the REPL";
+
+struct Repl {
+    interpreter: Interpreter,
+    dialect: Dialect,
+    last: Option<String>,
+}
+
+pub fn run(dialect: Dialect) {
+    let stdin = io::stdin();
+    let mut repl = Repl {
+        interpreter: Interpreter::new(),
+        dialect,
+        last: None,
+    };
+    let mut buffer = String::new();
+
+    println!("TINY REPL, type :help for help.");
+    loop {
+        print!(
+            "{}",
+            if buffer.is_empty() {
+                "tiny> "
+            } else {
+                "  ... "
+            }
+        );
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line).unwrap_or(0) == 0 {
+            // Run whatever is left so its errors get reported.
+            if !buffer.trim().is_empty() {
+                repl.execute(&buffer);
+            }
+            println!();
+            break;
+        }
+
+        if !buffer.is_empty() && line.trim() == ":cancel" {
+            buffer.clear();
+            continue;
+        }
+        if buffer.is_empty() && line.trim_start().starts_with(':') {
+            if !repl.command(line.trim()) {
+                break;
+            }
+            continue;
+        }
+
+        buffer.push_str(&line);
+        if buffer.trim().is_empty() {
+            buffer.clear();
+        } else if !is_incomplete(&buffer, &repl.dialect) {
+            repl.execute(&buffer);
+            buffer.clear();
+        }
+    }
+}
+
+impl Repl {
+    fn execute(&mut self, src: &str) {
+        self.last = Some(src.to_string());
+        let tokens = tokenize_with(src, false, &self.dialect);
+        for warning in warnings(src, &tokens, &self.dialect) {
+            println!("{}", warning);
+        }
+
+        let root = parse_with(src, true, &self.dialect);
+        // Procedures declared by earlier inputs can be called as well.
+        let checked = lower_with(&root, src, &self.dialect).and_then(|program| {
+            match check_with(&program, &self.interpreter.declarations()) {
+                Ok(_) => Ok(program),
+                Err(errors) => Err(errors.iter().map(ToString::to_string).collect()),
+            }
+        });
+        let program = match checked {
+            Ok(program) => program,
+            Err(errors) => {
+                for err in errors {
+                    println!("{}", err);
+                }
+                return;
+            }
+        };
+
+        let mut input = StdinNumbers;
+        if let Err(err) = self
+            .interpreter
+            .run(&program, &mut input, &mut io::stdout())
+        {
+            println!("{}\n\tat: '{}'", err, &src[err.span.0..err.span.1]);
+        }
+    }
+
+    /// Runs a meta-command, returning false once the REPL should stop.
+    fn command(&mut self, command: &str) -> bool {
+        let mut words = command.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some(":tokens"), None) => self.with_last(|src| {
+                for tok in tokenize_with(src, true, &self.dialect) {
+                    let (i0, i1) = tok.get_span();
+                    println!("{:<12}{}", tok.type_name(), &src[i0..i1]);
+                }
+            }),
+            (Some(":tree"), None) => self.with_last(|src| {
+                print_tree(&parse_with(src, true, &self.dialect), src, &self.dialect, 0)
+            }),
+            (Some(":tree"), Some("full")) => self.with_last(|src| {
+                print_tree(
+                    &parse_with(src, false, &self.dialect),
+                    src,
+                    &self.dialect,
+                    0,
+                )
+            }),
+            (Some(":vars"), None) => {
+                for (name, value) in self.interpreter.vars() {
+                    println!("{} = {}", name, value);
+                }
+                for (name, values) in self.interpreter.arrays() {
+                    println!("{} = {:?}", name, values);
+                }
+            }
+            (Some(":procs"), None) => {
+                for (name, arity) in self.interpreter.declarations().procedures {
+                    println!("{}/{}", name, arity);
+                }
+            }
+            (Some(":reset"), None) => {
+                self.interpreter.reset();
+                self.last = None;
+            }
+            (Some(":help"), None) => println!("{}", HELP),
+            (Some(":quit"), None) | (Some(":q"), None) => return false,
+            _ => println!("Unknown command '{}', type :help for help.", command),
+        }
+        true
+    }
+
+    fn with_last<F: FnOnce(&str)>(&self, f: F) {
+        match &self.last {
+            Some(src) => f(src),
+            None => println!("Nothing entered yet."),
+        }
+    }
+}
+
+/// Whether `src` stops in the middle of a statement sequence: inside a comment,
+/// right after a ';', or before an `if`/`repeat`/`while`/expression got its closing part.
+fn is_incomplete(src: &str, dialect: &Dialect) -> bool {
+    let tokens = tokenize_with(src, false, dialect);
+    if unterminated_comment(src, &tokens, dialect).is_some() {
+        return true;
+    }
+    if let Some(last) = tokens.last() {
+        let (i0, i1) = last.get_span();
+        if &src[i0..i1] == ";" {
+            return true;
+        }
+    }
+    has_eof_error(&parse_with(src, true, dialect))
+}
+
+fn has_eof_error(node: &Node) -> bool {
+    if let NodeType::Error(ErrorType::UnexpectedEOF, _) = node.n_type {
+        return true;
+    }
+    node.children
+        .iter()
+        .chain(node.nextstmt.iter())
+        .any(has_eof_error)
+}
+
+fn print_tree(node: &Node, src: &str, dialect: &Dialect, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match &node.n_type {
+        NodeType::Number(_) => println!("{}Number {}", indent, &src[node.span.0..node.span.1]),
+        NodeType::Identifier | NodeType::String | NodeType::Keyword | NodeType::Symbol => {
+            println!(
+                "{}{:?} {}",
+                indent,
+                node.n_type,
+                &src[node.span.0..node.span.1]
+            )
+        }
+        NodeType::Op(op) => println!("{}{:?} {}", indent, op, node.op_symbol(src, dialect)),
+        NodeType::Error(error_type, _) => println!("{}Error({:?})", indent, error_type),
+        n_type => println!("{}{:?}", indent, n_type),
+    }
+    for child in &node.children {
+        let mut curr = Some(child);
+        while let Some(stmt) = curr {
+            print_tree(stmt, src, dialect, depth + 1);
+            curr = stmt.nextstmt.first();
+        }
+    }
+}
+
+/// Values for `read` statements, asked for on stdin one at a time.
+struct StdinNumbers;
+
+impl Iterator for StdinNumbers {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        loop {
+            print!("? ");
+            io::stdout().flush().unwrap();
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+                return None;
+            }
+            match line.trim().parse() {
+                Ok(value) => return Some(value),
+                Err(_) => println!("Expected an integer."),
+            }
+        }
+    }
+}
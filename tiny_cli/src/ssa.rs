@@ -0,0 +1,35 @@
+use crate::ir::lower_source;
+use crate::read_source;
+use std::process;
+use tiny_ir::opt::optimise_ssa;
+use tiny_ir::ssa::{memory_vars, Ssa};
+use tiny_parser::Dialect;
+
+const USAGE: &str = "Usage: tiny ssa [--textbook] [--optimise] <FILE | ->";
+
+/// Prints the SSA form of the procedures and the main program of the file (or
+/// stdin, for `-`), optimised with `--optimise`.
+pub fn run(args: &[String], dialect: &Dialect) {
+    let (optimised, path) = match args {
+        [path] => (false, path),
+        [flag, path] if flag == "--optimise" => (true, path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let program = lower_source(&read_source(path), dialect);
+    let memory = memory_vars(&program);
+    let dumps: Vec<String> = program
+        .functions()
+        .map(|function| {
+            let mut ssa = Ssa::new(function, &memory);
+            if optimised {
+                optimise_ssa(&mut ssa);
+            }
+            ssa.to_string()
+        })
+        .collect();
+    print!("{}", dumps.join("\n"));
+}
@@ -0,0 +1,31 @@
+use crate::read_source;
+use std::process;
+use tiny_parser::trace::parse_traced;
+use tiny_parser::Dialect;
+
+const USAGE: &str = "Usage: tiny trace [--textbook] [--json | --derivation] <FILE | ->";
+
+/// Prints how the parser went through the file (or stdin, for `-`).
+pub fn run(args: &[String], dialect: &Dialect) {
+    let (format, path) = match args {
+        [path] => ("text", path),
+        [flag, path] if flag == "--json" => ("json", path),
+        [flag, path] if flag == "--derivation" => ("derivation", path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let src = read_source(path);
+    let (_, trace) = parse_traced(&src, true, dialect);
+    match format {
+        "json" => println!("{}", trace.to_json(&src).pretty(2)),
+        "derivation" => {
+            for (i, form) in trace.derivation(&src).iter().enumerate() {
+                println!("{}{}", if i == 0 { "   " } else { "=> " }, form);
+            }
+        }
+        _ => print!("{}", trace.to_text(&src)),
+    }
+}
@@ -0,0 +1,207 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn repl(input: &str) -> String {
+    repl_with(&[], input)
+}
+
+fn repl_with(flags: &[&str], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tiny"))
+        .arg("repl")
+        .args(flags)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap()
+}
+
+#[test]
+fn buffers_until_the_statement_sequence_is_complete() {
+    let output = repl("x := 2;\nrepeat\n  x := x * x\nuntil 100 < x\nwrite x\n");
+    assert!(output.contains("  ... "));
+    assert!(output.ends_with("256\ntiny> \n"));
+}
+
+#[test]
+fn braces_in_strings_and_line_comments_dont_open_comments() {
+    let output = repl("write \"{\"\nwrite 1 // {\nwrite 2 { still\n a comment }\n");
+    assert!(
+        output.contains("tiny> {\ntiny> 1\ntiny>   ... 2\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn cancel_throws_away_the_buffered_input() {
+    let output = repl("if 1 < 2 then\n  write 1\n:cancel\nwrite 2\n");
+    assert!(output.contains("  ... tiny> 2\n"), "{}", output);
+    assert!(!output.contains("1\n"));
+}
+
+#[test]
+fn reports_statements_without_a_semicolon_between_them() {
+    let output = repl("write 1 write 5\nwrite 2\n");
+    assert!(output.contains("Unexpected Token Error:"), "{}", output);
+    assert!(output.contains("Found: 'write'"));
+    assert!(!output.contains("1\n"));
+    assert!(output.contains("tiny> 2\n"));
+}
+
+#[test]
+fn keeps_variables_until_reset() {
+    let output = repl("read n\n7\nwrite n + 1\n:vars\n:reset\n:vars\nwrite n\n");
+    assert!(output.contains("? tiny> 8\n"));
+    assert!(output.contains("tiny> n = 7\ntiny> tiny> tiny> 0\n"));
+}
+
+#[test]
+fn shows_tokens_and_trees() {
+    let output = repl("y := 1 + 2\n:tokens\n:tree\n:tree full\n");
+    assert!(output.contains("IDENTIFIER  y\nSYMBOL      :=\n"));
+    assert!(output.contains("Program\n  Stmt(AssignStmt)\n    Identifier y\n    AddOp +\n"));
+    assert!(output.contains("Program\n  StmtSeq\n    Stmt(AssignStmt)\n"));
+}
+
+#[test]
+fn procedures_outlive_the_input_declaring_them() {
+    let output =
+        repl("procedure twice(n)\n  write n * 2\nend\ncall twice(21)\n:procs\ncall twice()\n");
+    assert!(output.contains("42\n"));
+    assert!(output.contains("twice/1\n"));
+    assert!(output.contains("Procedure 'twice' takes 1 argument(s) but 0 were given."));
+}
+
+#[test]
+fn textbook_dialect_rejects_the_extensions() {
+    let src = "x := 3; WHILE x > 0 DO write x; x := x - 1 END\n";
+    assert!(repl(src).contains("tiny> 3\n2\n1\ntiny> "));
+    let output = repl_with(&["--textbook"], src);
+    assert!(output.contains("Expected AssignmentOperator"));
+    assert!(!output.contains("3\n2\n1\n"));
+}
+
+#[test]
+fn textbook_dialect_points_out_keywords_in_the_wrong_case() {
+    let output = repl_with(&["--textbook"], "x := 1; IF x = 1 THEN write x END\n");
+    assert!(output.contains("Warning at 8..10:\nDid you mean the keyword `if`?"));
+    assert!(output.contains("Warning at 30..33:\nDid you mean the keyword `end`?"));
+}
+
+#[test]
+fn fold_prints_the_folded_program() {
+    let run = |flags: &[&str], input: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_tiny"))
+            .arg("fold")
+            .args(flags)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    let output = run(&[], "x := 2 * 3 + y * 1; write x ");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "x := 6 + y;\nwrite x\n"
+    );
+
+    let output = run(&["--treant"], "write 1 + 1 ");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("\"name\": \"2\""));
+
+    let output = run(&[], "write 1 / 0 ");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Division by zero."));
+}
+
+#[test]
+fn ir_prints_three_address_code() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tiny"))
+        .args(["ir", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"read x; write x * 2 ")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "main:\n  read x\n  %0 := x * 2\n  write %0\n  writeln\n"
+    );
+}
+
+#[test]
+fn compiling_commands_print_the_lexer_warnings_before_the_errors() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tiny"))
+        .args(["ir", "--textbook", "-"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"x := 1; IF x = 1 THEN write x END ")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let warning = stderr.find("Did you mean the keyword `if`?").unwrap();
+    assert!(warning < stderr.find("IllegalAssignmentSyntax").unwrap());
+}
+
+#[test]
+fn ssa_prints_the_optimised_ssa_form() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tiny"))
+        .args(["ssa", "--optimise", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"x := 2; read y; write x * y ")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "main():
+B0 (entry):
+  read y.1
+  %0.1 := 2 * y.1
+  write %0.1
+  writeln
+  goto B1
+B1 (exit): preds B0
+  return
+"
+    );
+}
@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::rc::Rc;
+use tiny_lexer::lexer::Span;
+use tiny_parser::ast::{evaluate, BinOp, Exp, Program, Stmt, UnOp, WriteItem};
+use tiny_parser::semantic::Declarations;
+
+pub mod runtime;
+
+pub use runtime::{RuntimeError, MAX_ARRAY_SIZE, MAX_CALL_DEPTH};
+
+/// Runs TINY programs. Variables, arrays and procedures live as long as the interpreter,
+/// so running several programs one after the other shares their state; variables
+/// that were never assigned read as 0, like the zeroed memory of the TM machine.
+/// Inside a procedure its parameters shadow the globals, see `tiny_parser::semantic`.
+#[derive(Debug, Clone, Default)]
+pub struct Interpreter {
+    vars: BTreeMap<String, i64>,
+    arrays: BTreeMap<String, Vec<i64>>,
+    procedures: BTreeMap<String, Rc<Procedure>>,
+    frames: Vec<BTreeMap<String, i64>>,
+    step_limit: Option<usize>,
+    steps: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Procedure {
+    params: Vec<String>,
+    body: Vec<Stmt>,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::default()
+    }
+
+    /// An interpreter that gives up on a `run` after executing `limit` statements,
+    /// for running untrusted programs that may never terminate.
+    pub fn with_step_limit(limit: usize) -> Interpreter {
+        Interpreter {
+            step_limit: Some(limit),
+            ..Interpreter::default()
+        }
+    }
+
+    pub fn vars(&self) -> &BTreeMap<String, i64> {
+        &self.vars
+    }
+
+    pub fn arrays(&self) -> &BTreeMap<String, Vec<i64>> {
+        &self.arrays
+    }
+
+    /// Every procedure and array declared so far, to check later programs against.
+    pub fn declarations(&self) -> Declarations {
+        Declarations {
+            procedures: self
+                .procedures
+                .iter()
+                .map(|(name, procedure)| (name.clone(), procedure.params.len()))
+                .collect(),
+            arrays: self
+                .arrays
+                .iter()
+                .map(|(name, array)| (name.clone(), array.len()))
+                .collect(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.vars.clear();
+        self.arrays.clear();
+        self.procedures.clear();
+    }
+
+    /// Runs `program`, which `semantic::check` passed, taking the values of `read`
+    /// statements from `input` and writing one line per `write` statement to `output`.
+    pub fn run<I, W>(
+        &mut self,
+        program: &Program,
+        input: &mut I,
+        output: &mut W,
+    ) -> Result<(), RuntimeError>
+    where
+        I: Iterator<Item = i64>,
+        W: Write,
+    {
+        self.steps = 0;
+        self.frames.clear();
+        // Procedures and arrays exist before their declarations are reached.
+        for stmt in &program.body {
+            self.declare(stmt)?;
+        }
+        self.stmt_seq(&program.body, input, output)
+    }
+
+    fn stmt_seq<I, W>(
+        &mut self,
+        stmts: &[Stmt],
+        input: &mut I,
+        output: &mut W,
+    ) -> Result<(), RuntimeError>
+    where
+        I: Iterator<Item = i64>,
+        W: Write,
+    {
+        for stmt in stmts {
+            self.stmt(stmt, input, output)?;
+        }
+        Ok(())
+    }
+
+    fn stmt<I, W>(&mut self, stmt: &Stmt, input: &mut I, output: &mut W) -> Result<(), RuntimeError>
+    where
+        I: Iterator<Item = i64>,
+        W: Write,
+    {
+        self.steps += 1;
+        if let Some(limit) = self.step_limit {
+            if self.steps > limit {
+                return Err(RuntimeError {
+                    span: stmt.span(),
+                    message: format!(
+                        "Step limit of {} statements exceeded.\nSuggested Fix:\tCheck that every 'repeat' and 'while' loop can reach its exit condition.",
+                        limit
+                    ),
+                });
+            }
+        }
+
+        match stmt {
+            Stmt::If {
+                cond,
+                then_part,
+                elsif_parts,
+                else_part,
+                ..
+            } => {
+                if self.exp(cond)? != 0 {
+                    return self.stmt_seq(then_part, input, output);
+                }
+                for part in elsif_parts {
+                    if self.exp(&part.cond)? != 0 {
+                        return self.stmt_seq(&part.body, input, output);
+                    }
+                }
+                self.stmt_seq(else_part, input, output)
+            }
+            Stmt::Repeat { body, cond, .. } => loop {
+                self.stmt_seq(body, input, output)?;
+                if self.exp(cond)? != 0 {
+                    return Ok(());
+                }
+            },
+            Stmt::While { cond, body, .. } => {
+                while self.exp(cond)? != 0 {
+                    self.stmt_seq(body, input, output)?;
+                }
+                Ok(())
+            }
+            // Declared by `run` already.
+            Stmt::Procedure { .. } | Stmt::Array { .. } => Ok(()),
+            Stmt::Call { name, args, span } => {
+                let procedure = match self.procedures.get(name) {
+                    Some(procedure) if procedure.params.len() == args.len() => Rc::clone(procedure),
+                    Some(procedure) => {
+                        return Err(RuntimeError {
+                            span: *span,
+                            message: format!(
+                                "Procedure '{}' takes {} argument(s) but {} were given.",
+                                name,
+                                procedure.params.len(),
+                                args.len()
+                            ),
+                        })
+                    }
+                    None => {
+                        return Err(RuntimeError {
+                            span: *span,
+                            message: runtime::undeclared_procedure(name),
+                        })
+                    }
+                };
+                if self.frames.len() >= MAX_CALL_DEPTH {
+                    return Err(RuntimeError {
+                        span: *span,
+                        message: runtime::calls_too_deep(),
+                    });
+                }
+
+                let mut frame = BTreeMap::new();
+                for (param, arg) in procedure.params.iter().zip(args) {
+                    frame.insert(param.clone(), self.exp(arg)?);
+                }
+                self.frames.push(frame);
+                let result = self.stmt_seq(&procedure.body, input, output);
+                self.frames.pop();
+                result
+            }
+            Stmt::Assign {
+                name, index, value, ..
+            } => {
+                let value = self.exp(value)?;
+                self.store(name, index.as_ref(), value)
+            }
+            Stmt::Read { name, index, span } => match input.next() {
+                Some(value) => self.store(name, index.as_ref(), value),
+                None => Err(RuntimeError {
+                    span: *span,
+                    message: runtime::out_of_input(name),
+                }),
+            },
+            Stmt::Write { items, span } => {
+                let mut line = String::new();
+                for item in items {
+                    match item {
+                        WriteItem::String { value, .. } => line.push_str(value),
+                        WriteItem::Exp(exp) => line.push_str(&self.exp(exp)?.to_string()),
+                    }
+                }
+                writeln!(output, "{}", line).map_err(|err| RuntimeError {
+                    span: *span,
+                    message: runtime::output_failed(err),
+                })
+            }
+        }
+    }
+
+    fn declare(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        match stmt {
+            Stmt::Procedure {
+                name, params, body, ..
+            } => {
+                let procedure = Procedure {
+                    params: params.clone(),
+                    body: body.clone(),
+                };
+                self.procedures.insert(name.clone(), Rc::new(procedure));
+            }
+            Stmt::Array { name, size, span } => {
+                if *size > MAX_ARRAY_SIZE {
+                    return Err(RuntimeError {
+                        span: *span,
+                        message: runtime::array_too_big(name, *size),
+                    });
+                }
+                self.arrays.insert(name.clone(), vec![0; *size]);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Stores `value` in `name`, or in `name[index]`.
+    fn store(&mut self, name: &str, index: Option<&Exp>, value: i64) -> Result<(), RuntimeError> {
+        match index {
+            Some(index) => {
+                let i = self.index(name, index, index.span())?;
+                self.arrays.get_mut(name).unwrap()[i] = value;
+            }
+            None => self.set(name, value),
+        }
+        Ok(())
+    }
+
+    /// Evaluates `index` and checks it against the bounds of array `name`,
+    /// reporting errors at `span`.
+    fn index(&self, name: &str, index: &Exp, span: Span) -> Result<usize, RuntimeError> {
+        let i = self.exp(index)?;
+        let array = self.arrays.get(name).ok_or_else(|| RuntimeError {
+            span,
+            message: format!("'{}' is not an array.", name),
+        })?;
+        if i < 0 || i as u64 >= array.len() as u64 {
+            return Err(RuntimeError {
+                span,
+                message: runtime::index_out_of_bounds(i, name, array.len()),
+            });
+        }
+        Ok(i as usize)
+    }
+
+    fn get(&self, name: &str) -> i64 {
+        self.frames
+            .last()
+            .and_then(|frame| frame.get(name))
+            .or_else(|| self.vars.get(name))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    fn set(&mut self, name: &str, value: i64) {
+        match self.frames.last_mut() {
+            Some(frame) if frame.contains_key(name) => frame.insert(name.to_string(), value),
+            _ => self.vars.insert(name.to_string(), value),
+        };
+    }
+
+    fn exp(&self, exp: &Exp) -> Result<i64, RuntimeError> {
+        match exp {
+            Exp::Number { value, .. } => Ok(*value),
+            Exp::Identifier { name, .. } => Ok(self.get(name)),
+            Exp::Index { name, index, span } => {
+                let i = self.index(name, index, *span)?;
+                Ok(self.arrays[name][i])
+            }
+            Exp::UnOp {
+                op: UnOp::Not,
+                operand,
+                ..
+            } => Ok((self.exp(operand)? == 0) as i64),
+            Exp::UnOp {
+                op: UnOp::Neg,
+                operand,
+                ..
+            } => Ok(self.exp(operand)?.wrapping_neg()),
+            Exp::BinOp { op, lhs, rhs, span } => {
+                let lhs = self.exp(lhs)?;
+                // `and` and `or` short-circuit, leaving the right operand unevaluated.
+                match (op, lhs != 0) {
+                    (BinOp::And, false) => return Ok(0),
+                    (BinOp::Or, true) => return Ok(1),
+                    _ => {}
+                }
+                let rhs = self.exp(rhs)?;
+                evaluate(*op, lhs, rhs).ok_or_else(|| RuntimeError {
+                    span: *span,
+                    message: runtime::DIVISION_BY_ZERO.to_string(),
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! The limits on running programs and the messages of the run-time errors, shared
+//! by the interpreter, `tiny_ir::exec` and the native code of `tiny_ir::x86_64` so
+//! that a program fails the same way whichever of them runs it.
+
+use std::fmt::{self, Display};
+use tiny_lexer::lexer::Span;
+
+/// Calls nested deeper than this fail instead of overflowing the stack.
+pub const MAX_CALL_DEPTH: usize = 200;
+
+/// Arrays that `semantic::check` lets through fit in memory.
+pub use tiny_parser::semantic::MAX_ARRAY_SIZE;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Runtime Error at {}..{}:\n{}",
+            self.span.0, self.span.1, self.message
+        )
+    }
+}
+
+pub const DIVISION_BY_ZERO: &str = "Division by zero.";
+
+pub fn out_of_input(name: &str) -> String {
+    format!("Ran out of input while reading '{}'.", name)
+}
+
+pub fn output_failed(err: impl Display) -> String {
+    format!("Failed to write output: {}", err)
+}
+
+pub fn undeclared_procedure(name: &str) -> String {
+    format!("Call to undeclared procedure '{}'.", name)
+}
+
+pub fn array_too_big(name: &str, size: usize) -> String {
+    format!(
+        "Array '{}' of {} elements is bigger than the limit of {}.",
+        name, size, MAX_ARRAY_SIZE
+    )
+}
+
+pub fn calls_too_deep() -> String {
+    format!(
+        "Calls nested deeper than {} levels.\nSuggested Fix:\tCheck that every recursive procedure can stop calling itself.",
+        MAX_CALL_DEPTH
+    )
+}
+
+/// The native code passes a `printf` conversion as the `index`.
+pub fn index_out_of_bounds(index: impl Display, array: &str, size: usize) -> String {
+    format!(
+        "Index {} is out of bounds for array '{}' of size {}.",
+        index, array, size
+    )
+}
@@ -0,0 +1,249 @@
+use tiny_interpreter::Interpreter;
+use tiny_parser::ast::{lower_with, parse_program, Stmt};
+use tiny_parser::desugar::desugar_elsif;
+use tiny_parser::fold::fold;
+use tiny_parser::{parse_with, Dialect};
+
+fn run(src: &str, input: &[i64]) -> String {
+    let program = parse_program(src).unwrap();
+    let mut output = vec![];
+    Interpreter::new()
+        .run(&program, &mut input.iter().cloned(), &mut output)
+        .unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn computes_factorial() {
+    let src = "{ Sample program in TINY language computes factorial }
+read x; {input an integer }
+if 0 < x then { don't compute if x <= 0 }
+  fact := 1;
+  repeat
+    fact := fact * x;
+    x := x - 1
+  until x = 0;
+  write fact { output factorial of x }
+end ";
+    assert_eq!(run(src, &[5]), "120\n");
+    assert_eq!(run(src, &[0]), "");
+}
+
+#[test]
+fn while_loops_check_before_each_pass() {
+    let src = "read n; i := 0; while i < n do write i; i := i + 1 end ";
+    assert_eq!(run(src, &[3]), "0\n1\n2\n");
+    assert_eq!(run(src, &[0]), "");
+}
+
+#[test]
+fn comparisons_evaluate_to_one_or_zero() {
+    let src = "write 1 <= 1; write 2 >= 3; write 3 > 2; write 1 <> 1; write 1 < 2 = 1 ";
+    assert_eq!(run(src, &[]), "1\n0\n1\n0\n1\n");
+}
+
+#[test]
+fn boolean_operators_short_circuit() {
+    let src = "x := 0; write not x; write x <> 0 and 10 / x; write x = 0 or 10 / x; write 2 and 3 ";
+    assert_eq!(run(src, &[]), "1\n0\n1\n1\n");
+}
+
+#[test]
+fn negation_and_signed_literals() {
+    let src = "x := -5; write x; write -x * 2; write 3 - -1; write +x; write -9223372036854775808 ";
+    assert_eq!(run(src, &[]), "-5\n10\n4\n-5\n-9223372036854775808\n");
+}
+
+#[test]
+fn elsif_chains_take_the_first_true_branch() {
+    let src = "read n;
+if n < 0 then write 0
+elsif n < 10 then write 1
+elsif n < 100 then write 2
+else write 3
+end ";
+    for &(n, expected) in &[(-5, "0\n"), (5, "1\n"), (50, "2\n"), (500, "3\n")] {
+        assert_eq!(run(src, &[n]), expected);
+
+        let dialect = Dialect::extended();
+        let tree = desugar_elsif(&parse_with(src, true, &dialect));
+        let program = lower_with(&tree, src, &dialect).unwrap();
+        match &program.body[1] {
+            Stmt::If { elsif_parts, .. } => assert!(elsif_parts.is_empty()),
+            stmt => panic!("Expected an if statement, found {:?}", stmt),
+        }
+        let mut output = vec![];
+        Interpreter::new()
+            .run(&program, &mut vec![n].into_iter(), &mut output)
+            .unwrap();
+        assert_eq!(output, expected.as_bytes());
+    }
+}
+
+#[test]
+fn procedures_take_parameters_by_value() {
+    let src = "x := 1; n := 10;
+call show(5);
+procedure show(n) n := n * 2; x := x + n; write n end;
+write n; write x ";
+    assert_eq!(run(src, &[]), "10\n10\n11\n");
+}
+
+#[test]
+fn procedures_can_recurse() {
+    let src = "procedure fact(n) if n < 2 then result := 1 else call fact(n - 1); result := result * n end end;
+call fact(10); write result ";
+    assert_eq!(run(src, &[]), "3628800\n");
+
+    let program =
+        parse_program("procedure forever(n) call forever(n + 1) end; call forever(0) ").unwrap();
+    let err = Interpreter::new()
+        .run(&program, &mut std::iter::empty(), &mut vec![])
+        .unwrap_err();
+    assert!(err.message.starts_with("Calls nested deeper than"));
+}
+
+#[test]
+fn arrays_hold_values_by_index() {
+    let src = "array fib[10];
+fib[1] := 1; i := 2;
+while i < 10 do fib[i] := fib[i - 1] + fib[i - 2]; i := i + 1 end;
+read fib[0]; write fib[9]; write fib[0] ";
+    assert_eq!(run(src, &[7]), "34\n7\n");
+}
+
+#[test]
+fn array_accesses_are_bounds_checked() {
+    let src = "array a[3]; read i; write a[i] ";
+    let program = parse_program(src).unwrap();
+    let err = Interpreter::new()
+        .run(&program, &mut vec![3].into_iter(), &mut vec![])
+        .unwrap_err();
+    assert_eq!(
+        err.message,
+        "Index 3 is out of bounds for array 'a' of size 3."
+    );
+    assert_eq!(&src[err.span.0..err.span.1], "a[i]");
+
+    let src = "array a[3]; read i; a[i - 5] := 1 ";
+    let err = Interpreter::new()
+        .run(
+            &parse_program(src).unwrap(),
+            &mut vec![1].into_iter(),
+            &mut vec![],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.message,
+        "Index -4 is out of bounds for array 'a' of size 3."
+    );
+    assert_eq!(&src[err.span.0..err.span.1], "i - 5");
+}
+
+#[test]
+fn write_prints_its_items_on_one_line() {
+    let src = r#"read x; write "x = ", x, ", \"x * 2\" = ", x * 2; write "" "#;
+    assert_eq!(run(src, &[21]), "x = 21, \"x * 2\" = 42\n\n");
+}
+
+#[test]
+fn identifiers_can_be_case_insensitive() {
+    let src = "Total := 1; TOTAL := total + 1; write total ";
+    let dialect = Dialect {
+        case_insensitive_identifiers: true,
+        ..Dialect::extended()
+    };
+    let program = lower_with(&parse_with(src, true, &dialect), src, &dialect).unwrap();
+    let mut output = vec![];
+    Interpreter::new()
+        .run(&program, &mut vec![].into_iter(), &mut output)
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "2\n");
+    assert_eq!(run(src, &[]), "0\n");
+}
+
+#[test]
+fn operators_associate_to_the_left() {
+    assert_eq!(run("write 10 - 3 + 2; write 12 / 2 * 3 ", &[]), "9\n18\n");
+    assert_eq!(run("write 2 * (3 + 4) - 1 ", &[]), "13\n");
+}
+
+#[test]
+fn variables_persist_between_runs() {
+    let mut interpreter = Interpreter::new();
+    let mut output = vec![];
+    for src in &["x := 4 ", "write x * x "] {
+        let program = parse_program(src).unwrap();
+        interpreter
+            .run(&program, &mut std::iter::empty(), &mut output)
+            .unwrap();
+    }
+    assert_eq!(output, b"16\n");
+    assert_eq!(interpreter.vars().get("x"), Some(&4));
+}
+
+#[test]
+fn reports_runtime_errors_with_spans() {
+    let program = parse_program("x := 0; write 1 / x ").unwrap();
+    let err = Interpreter::new()
+        .run(&program, &mut std::iter::empty(), &mut vec![])
+        .unwrap_err();
+    assert_eq!(err.span, (14, 19));
+
+    let program = parse_program("repeat x := 1 until 0 ").unwrap();
+    let err = Interpreter::with_step_limit(100)
+        .run(&program, &mut std::iter::empty(), &mut vec![])
+        .unwrap_err();
+    assert!(err.message.starts_with("Step limit"));
+}
+
+#[test]
+fn refuses_programs_with_syntax_errors() {
+    assert!(parse_program("x 1 ").is_err());
+}
+
+#[test]
+fn hex_binary_and_separated_literals() {
+    assert_eq!(run("write 0xFF + 0b1010 + 1_000 ", &[]), "1265\n");
+}
+
+#[test]
+fn unicode_identifiers_and_comments() {
+    assert_eq!(
+        run("{ don’t panic } größe := 6; write größe * 7 ", &[]),
+        "42\n"
+    );
+}
+
+#[test]
+fn identifiers_with_digits_and_underscores() {
+    assert_eq!(
+        run(
+            "total_sum := 1; x1 := 2; _x := 3; write total_sum + x1 + _x ",
+            &[]
+        ),
+        "6\n"
+    );
+}
+
+#[test]
+fn folding_keeps_what_programs_do() {
+    let src = "read x; y := 2 * 3 + x * 1; z := (x - 0) / (4 - 2);
+if 1 < 2 and x > 0 - 0 then write y, z, - -x, 9223372036854775807 + 1 end ";
+    let program = parse_program(src).unwrap();
+    let folded = fold(&program).unwrap();
+    assert_ne!(folded, program);
+    for input in &[-5, 0, 7] {
+        let outputs: Vec<String> = [&program, &folded]
+            .iter()
+            .map(|program| {
+                let mut output = vec![];
+                Interpreter::new()
+                    .run(program, &mut vec![*input].into_iter(), &mut output)
+                    .unwrap();
+                String::from_utf8(output).unwrap()
+            })
+            .collect();
+        assert_eq!(outputs[0], outputs[1]);
+    }
+}
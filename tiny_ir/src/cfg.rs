@@ -0,0 +1,405 @@
+//! Basic blocks and the control-flow graph of a function. Blocks are cut at the
+//! labels and jumps of the three-address code, which don't appear in them anymore:
+//! a block is straight-line code and the `Terminator` saying where to go next.
+//!
+//! Block 0 is the entry, which nothing jumps back to, and the last block is an
+//! empty exit block every path out of the function goes through, so the analyses
+//! have a single place to start from in both directions.
+
+use crate::dom::Dominators;
+use crate::{Function, Instr, InstrKind, Label, Operand, Var};
+use json::{object, JsonValue};
+use std::collections::BTreeMap;
+use std::fmt;
+use tiny_lexer::lexer::Span;
+
+pub type BlockId = usize;
+
+/// The block every function starts in.
+pub const ENTRY: BlockId = 0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cfg<V = Var> {
+    pub name: String,
+    pub params: Vec<String>,
+    pub blocks: Vec<Block<V>>,
+    /// The block every path out of the function ends in.
+    pub exit: BlockId,
+    /// How many temporaries the function uses, as in `Function::temps`.
+    pub temps: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<V = Var> {
+    /// Never labels or jumps.
+    pub instrs: Vec<Instr<V>>,
+    pub terminator: Terminator<V>,
+    /// The blocks ending with a jump or a fall through to this one, in order.
+    pub preds: Vec<BlockId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator<V = Var> {
+    Goto(BlockId),
+    /// Goes to `nonzero` or `zero` depending on `cond`, with `span` being the
+    /// statement the condition belongs to.
+    Branch {
+        cond: Operand<V>,
+        nonzero: BlockId,
+        zero: BlockId,
+        span: Span,
+    },
+    /// Leaves the function, only ending the exit block.
+    Return,
+}
+
+impl<V> Terminator<V> {
+    /// The blocks that may run next, each once.
+    pub fn succs(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Goto(target) => vec![*target],
+            Terminator::Branch { nonzero, zero, .. } if nonzero == zero => vec![*nonzero],
+            Terminator::Branch { nonzero, zero, .. } => vec![*nonzero, *zero],
+            Terminator::Return => vec![],
+        }
+    }
+
+    /// The condition of a branch.
+    pub fn cond(&self) -> Option<&Operand<V>> {
+        match self {
+            Terminator::Branch { cond, .. } => Some(cond),
+            _ => None,
+        }
+    }
+
+    /// The condition of a branch, to be changed.
+    pub fn cond_mut(&mut self) -> Option<&mut Operand<V>> {
+        match self {
+            Terminator::Branch { cond, .. } => Some(cond),
+            _ => None,
+        }
+    }
+
+    /// The same terminator over other variables, `f` of each of these.
+    pub fn map<W>(&self, f: impl FnOnce(&V) -> W) -> Terminator<W> {
+        match self {
+            Terminator::Goto(target) => Terminator::Goto(*target),
+            Terminator::Branch {
+                cond,
+                nonzero,
+                zero,
+                span,
+            } => Terminator::Branch {
+                cond: cond.map(f),
+                nonzero: *nonzero,
+                zero: *zero,
+                span: *span,
+            },
+            Terminator::Return => Terminator::Return,
+        }
+    }
+}
+
+impl<V: Clone> Cfg<V> {
+    pub fn new(function: &Function<V>) -> Cfg<V> {
+        // The instructions of each block and the jump ending it, if any, still to
+        // a label.
+        let mut instrs: Vec<Vec<Instr<V>>> = vec![vec![]];
+        let mut jumps: Vec<Option<Instr<V>>> = vec![None];
+        let mut block_of: BTreeMap<Label, BlockId> = BTreeMap::new();
+        // Whether the last block ended with a jump, so anything after it is in a new one.
+        let mut closed = false;
+        for instr in &function.body {
+            let current = instrs.len() - 1;
+            match &instr.kind {
+                InstrKind::Label(label) => {
+                    // Labels right after each other name the same block, which is
+                    // never the entry.
+                    if current == ENTRY || closed || !instrs[current].is_empty() {
+                        instrs.push(vec![]);
+                        jumps.push(None);
+                        closed = false;
+                    }
+                    block_of.insert(*label, instrs.len() - 1);
+                }
+                kind => {
+                    if closed {
+                        instrs.push(vec![]);
+                        jumps.push(None);
+                        closed = false;
+                    }
+                    if kind.target().is_some() {
+                        *jumps.last_mut().unwrap() = Some(instr.clone());
+                        closed = true;
+                    } else {
+                        instrs.last_mut().unwrap().push(instr.clone());
+                    }
+                }
+            }
+        }
+
+        let exit = instrs.len();
+        let mut blocks: Vec<Block<V>> = instrs
+            .into_iter()
+            .zip(jumps)
+            .enumerate()
+            .map(|(id, (instrs, jump))| {
+                let next = id + 1;
+                let terminator = match jump {
+                    None => Terminator::Goto(next),
+                    Some(Instr { kind, span }) => match kind {
+                        InstrKind::Jump(target) => Terminator::Goto(block_of[&target]),
+                        InstrKind::JumpIfZero { cond, target } => Terminator::Branch {
+                            cond,
+                            nonzero: next,
+                            zero: block_of[&target],
+                            span,
+                        },
+                        InstrKind::JumpIfNonZero { cond, target } => Terminator::Branch {
+                            cond,
+                            nonzero: block_of[&target],
+                            zero: next,
+                            span,
+                        },
+                        _ => unreachable!("only jumps end blocks"),
+                    },
+                };
+                Block {
+                    instrs,
+                    terminator,
+                    preds: vec![],
+                }
+            })
+            .collect();
+        blocks.push(Block {
+            instrs: vec![],
+            terminator: Terminator::Return,
+            preds: vec![],
+        });
+
+        for id in 0..blocks.len() {
+            for succ in blocks[id].terminator.succs() {
+                blocks[succ].preds.push(id);
+            }
+        }
+
+        Cfg {
+            name: function.name.clone(),
+            params: function.params.clone(),
+            blocks,
+            exit,
+            temps: function.temps,
+        }
+    }
+}
+
+impl<V> Cfg<V> {
+    /// The blocks reachable from the entry, each before all of its successors
+    /// except along the back edges of loops.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = vec![];
+        // Each block with the successors it has yet to visit.
+        let mut stack = vec![(ENTRY, self.blocks[ENTRY].terminator.succs())];
+        visited[ENTRY] = true;
+        while let Some((block, succs)) = stack.last_mut() {
+            match succs.pop() {
+                Some(succ) if !visited[succ] => {
+                    visited[succ] = true;
+                    let succs = self.blocks[succ].terminator.succs();
+                    stack.push((succ, succs));
+                }
+                Some(_) => {}
+                None => {
+                    postorder.push(*block);
+                    stack.pop();
+                }
+            }
+        }
+        postorder.reverse();
+        postorder
+    }
+
+    pub fn dominators(&self) -> Dominators {
+        Dominators::new(self)
+    }
+}
+
+impl<V: fmt::Display> Cfg<V> {
+    /// The blocks with their instructions and edges as a Graphviz cluster, and the
+    /// dominator tree as dashed edges if `dominators` is set. Node names start with
+    /// the function's name so several graphs fit in one `digraph`.
+    pub fn write_dot(&self, out: &mut String, dominators: bool) {
+        let node = |id: BlockId| dot_string(&format!("{}.B{}", self.name, id));
+        out.push_str(&format!(
+            "  subgraph {} {{\n    label = {};\n",
+            dot_string(&format!("cluster_{}", self.name)),
+            dot_string(&self.name)
+        ));
+        for (id, block) in self.blocks.iter().enumerate() {
+            let mut label = self.block_name(id);
+            label.push_str(":\\l");
+            for instr in &block.instrs {
+                label.push_str(&format!("  {}\\l", dot_escape(&instr.to_string())));
+            }
+            if let Terminator::Branch { cond, .. } = &block.terminator {
+                label.push_str(&format!("  if {}\\l", dot_escape(&cond.to_string())));
+            }
+            out.push_str(&format!("    {} [label = \"{}\"];\n", node(id), label));
+        }
+        for (id, block) in self.blocks.iter().enumerate() {
+            match &block.terminator {
+                Terminator::Goto(target) => {
+                    out.push_str(&format!("    {} -> {};\n", node(id), node(*target)))
+                }
+                Terminator::Branch { nonzero, zero, .. } => {
+                    out.push_str(&format!(
+                        "    {} -> {} [label = \"nonzero\"];\n    {} -> {} [label = \"zero\"];\n",
+                        node(id),
+                        node(*nonzero),
+                        node(id),
+                        node(*zero)
+                    ));
+                }
+                Terminator::Return => {}
+            }
+        }
+        if dominators {
+            let dominators = self.dominators();
+            for id in 0..self.blocks.len() {
+                if let Some(idom) = dominators.idom(id) {
+                    out.push_str(&format!(
+                        "    {} -> {} [style = dashed, color = gray, constraint = false];\n",
+                        node(idom),
+                        node(id)
+                    ));
+                }
+            }
+        }
+        out.push_str("  }\n");
+    }
+
+    /// The graph as `{ name, params, entry, exit, blocks }`, each block being
+    /// `{ id, instrs: [{ text, span }], terminator, succs, preds, idom, span }`
+    /// with `span` covering its instructions (`null` if it has none).
+    pub fn to_json(&self) -> JsonValue {
+        let dominators = self.dominators();
+        let blocks: Vec<JsonValue> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(id, block)| {
+                let instrs: Vec<JsonValue> = block
+                    .instrs
+                    .iter()
+                    .map(|instr| {
+                        object! {
+                            "text" => instr.to_string(),
+                            "span" => vec![instr.span.0, instr.span.1],
+                        }
+                    })
+                    .collect();
+                let span = block
+                    .instrs
+                    .iter()
+                    .map(|instr| instr.span)
+                    .chain(match block.terminator {
+                        Terminator::Branch { span, .. } => Some(span),
+                        _ => None,
+                    })
+                    .fold(None, |covered: Option<Span>, span| match covered {
+                        Some((start, end)) => Some((start.min(span.0), end.max(span.1))),
+                        None => Some(span),
+                    });
+                object! {
+                    "id" => id,
+                    "instrs" => instrs,
+                    "terminator" => block.terminator.to_string(),
+                    "succs" => block.terminator.succs(),
+                    "preds" => block.preds.clone(),
+                    "idom" => dominators.idom(id),
+                    "span" => span.map(|(start, end)| vec![start, end]),
+                }
+            })
+            .collect();
+        object! {
+            "name" => self.name.clone(),
+            "params" => self.params.clone(),
+            "entry" => ENTRY,
+            "exit" => self.exit,
+            "blocks" => blocks,
+        }
+    }
+
+    pub(crate) fn block_name(&self, id: BlockId) -> String {
+        if id == ENTRY {
+            format!("B{} (entry)", id)
+        } else if id == self.exit {
+            format!("B{} (exit)", id)
+        } else {
+            format!("B{}", id)
+        }
+    }
+}
+
+/// The graphs of several functions in one Graphviz `digraph`.
+pub fn to_dot<V: fmt::Display>(cfgs: &[Cfg<V>], dominators: bool) -> String {
+    let mut out = String::from("digraph cfg {\n  node [shape = box, fontname = \"monospace\"];\n");
+    for cfg in cfgs {
+        cfg.write_dot(&mut out, dominators);
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// The graphs of several functions as a JSON array of `Cfg::to_json`.
+pub fn to_json<V: fmt::Display>(cfgs: &[Cfg<V>]) -> JsonValue {
+    JsonValue::Array(cfgs.iter().map(Cfg::to_json).collect())
+}
+
+/// The dump: every block with its predecessors, instructions and terminator.
+impl<V: fmt::Display> fmt::Display for Cfg<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dominators = self.dominators();
+        writeln!(f, "{}({}):", self.name, self.params.join(", "))?;
+        for (id, block) in self.blocks.iter().enumerate() {
+            let preds: Vec<String> = block.preds.iter().map(|p| format!("B{}", p)).collect();
+            write!(f, "{}:", self.block_name(id))?;
+            if !preds.is_empty() {
+                write!(f, " preds {}", preds.join(", "))?;
+            }
+            if let Some(idom) = dominators.idom(id) {
+                write!(f, "; idom B{}", idom)?;
+            }
+            writeln!(f)?;
+            for instr in &block.instrs {
+                writeln!(f, "  {}", instr)?;
+            }
+            writeln!(f, "  {}", block.terminator)?;
+        }
+        Ok(())
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for Terminator<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Terminator::Goto(target) => write!(f, "goto B{}", target),
+            Terminator::Branch {
+                cond,
+                nonzero,
+                zero,
+                ..
+            } => write!(f, "if {} goto B{} else B{}", cond, nonzero, zero),
+            Terminator::Return => write!(f, "return"),
+        }
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", dot_escape(text))
+}
@@ -0,0 +1,384 @@
+//! Data-flow analyses over a `Cfg`: a worklist solver for any `Analysis`, and the
+//! classic ones on top of it. Facts are computed for the start and the end of
+//! every block, and `Solution::points` replays a block for the facts in between.
+//!
+//! Procedures can read and assign any global, so a call counts as using every
+//! global and, for reaching definitions, as assigning every one of them.
+
+use crate::cfg::{BlockId, Cfg, Terminator, ENTRY};
+use crate::{Instr, InstrKind, Operand};
+use std::collections::{BTreeSet, VecDeque};
+use tiny_parser::ast::{BinOp, UnOp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// A place in a function: before the `index`th instruction of `block`, or before
+/// its terminator if `index` is the number of instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub block: BlockId,
+    pub index: usize,
+}
+
+pub trait Analysis<V> {
+    type Fact: Clone + PartialEq;
+
+    fn direction(&self) -> Direction;
+    /// The fact at the start of the entry for a forward analysis, at the end of
+    /// the exit for a backward one.
+    fn boundary(&self) -> Self::Fact;
+    /// What every other block starts with, which `join` leaves as it is.
+    fn top(&self) -> Self::Fact;
+    /// Merges the fact of one more predecessor (or successor, going backward).
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact);
+    /// Steps `fact` over the instruction at `point`, in the analysis's direction.
+    fn transfer(&self, instr: &Instr<V>, point: Point, fact: &mut Self::Fact);
+    /// Steps `fact` over a block's terminator, which changes nothing unless the
+    /// analysis cares about the condition of a branch.
+    fn transfer_terminator(
+        &self,
+        _terminator: &Terminator<V>,
+        _point: Point,
+        _fact: &mut Self::Fact,
+    ) {
+    }
+}
+
+/// The facts at the start and at the end of every block. Blocks the entry doesn't
+/// reach keep `Analysis::top`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution<F> {
+    pub before: Vec<F>,
+    pub after: Vec<F>,
+}
+
+/// Iterates `analysis` over `cfg` until no fact changes, visiting the blocks in
+/// reverse postorder (or its reverse, going backward) and then only those whose
+/// neighbours changed.
+pub fn solve<V, A: Analysis<V>>(cfg: &Cfg<V>, analysis: &A) -> Solution<A::Fact> {
+    let forward = analysis.direction() == Direction::Forward;
+    let mut order = cfg.reverse_postorder();
+    if !forward {
+        order.reverse();
+    }
+    let mut solution = Solution {
+        before: vec![analysis.top(); cfg.blocks.len()],
+        after: vec![analysis.top(); cfg.blocks.len()],
+    };
+    let mut queued = vec![false; cfg.blocks.len()];
+    let mut worklist = VecDeque::new();
+    for block in order {
+        queued[block] = true;
+        worklist.push_back(block);
+    }
+
+    while let Some(id) = worklist.pop_front() {
+        queued[id] = false;
+        let block = &cfg.blocks[id];
+        let succs = block.terminator.succs();
+        let (sources, sinks) = if forward {
+            (&block.preds, &succs)
+        } else {
+            (&succs, &block.preds)
+        };
+
+        let mut fact = if (forward && id == ENTRY) || (!forward && id == cfg.exit) {
+            analysis.boundary()
+        } else {
+            analysis.top()
+        };
+        for source in sources {
+            let other = if forward {
+                &solution.after[*source]
+            } else {
+                &solution.before[*source]
+            };
+            analysis.join(&mut fact, other);
+        }
+        let (start, end) = if forward {
+            (&mut solution.before, &mut solution.after)
+        } else {
+            (&mut solution.after, &mut solution.before)
+        };
+        start[id] = fact.clone();
+        step_block(analysis, cfg, id, &mut fact, |_| {});
+        if end[id] != fact {
+            end[id] = fact;
+            for sink in sinks {
+                if !queued[*sink] {
+                    queued[*sink] = true;
+                    worklist.push_back(*sink);
+                }
+            }
+        }
+    }
+    solution
+}
+
+impl<F: Clone> Solution<F> {
+    /// The fact at every point of `block` in program order: before each of its
+    /// instructions, then before its terminator.
+    pub fn points<V, A>(&self, analysis: &A, cfg: &Cfg<V>, block: BlockId) -> Vec<F>
+    where
+        A: Analysis<V, Fact = F>,
+    {
+        let len = cfg.blocks[block].instrs.len();
+        let mut points = vec![];
+        match analysis.direction() {
+            Direction::Forward => {
+                let mut fact = self.before[block].clone();
+                points.push(fact.clone());
+                step_block(analysis, cfg, block, &mut fact, |fact| {
+                    points.push(fact.clone())
+                });
+            }
+            Direction::Backward => {
+                let mut fact = self.after[block].clone();
+                step_block(analysis, cfg, block, &mut fact, |fact| {
+                    points.push(fact.clone())
+                });
+                points.reverse();
+            }
+        }
+        // A forward analysis steps over the terminator last, a backward one first.
+        points.truncate(len + 1);
+        points
+    }
+}
+
+/// Steps `fact` through `block` in the analysis's direction, calling `visited`
+/// after every instruction and terminator with the fact after it.
+fn step_block<V, A, F>(analysis: &A, cfg: &Cfg<V>, id: BlockId, fact: &mut A::Fact, mut visited: F)
+where
+    A: Analysis<V>,
+    F: FnMut(&A::Fact),
+{
+    let block = &cfg.blocks[id];
+    let terminator = Point {
+        block: id,
+        index: block.instrs.len(),
+    };
+    match analysis.direction() {
+        Direction::Forward => {
+            for (index, instr) in block.instrs.iter().enumerate() {
+                let point = Point { block: id, index };
+                analysis.transfer(instr, point, fact);
+                visited(fact);
+            }
+            analysis.transfer_terminator(&block.terminator, terminator, fact);
+            visited(fact);
+        }
+        Direction::Backward => {
+            analysis.transfer_terminator(&block.terminator, terminator, fact);
+            visited(fact);
+            for (index, instr) in block.instrs.iter().enumerate().rev() {
+                let point = Point { block: id, index };
+                analysis.transfer(instr, point, fact);
+                visited(fact);
+            }
+        }
+    }
+}
+
+/// The variables holding a value that may still be read, going backward from
+/// `live_at_exit`.
+pub struct Liveness<V> {
+    pub globals: BTreeSet<V>,
+    pub live_at_exit: BTreeSet<V>,
+}
+
+impl<V: Clone + Ord> Analysis<V> for Liveness<V> {
+    type Fact = BTreeSet<V>;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn boundary(&self) -> BTreeSet<V> {
+        self.live_at_exit.clone()
+    }
+
+    fn top(&self) -> BTreeSet<V> {
+        BTreeSet::new()
+    }
+
+    fn join(&self, fact: &mut BTreeSet<V>, other: &BTreeSet<V>) {
+        fact.extend(other.iter().cloned());
+    }
+
+    fn transfer(&self, instr: &Instr<V>, _point: Point, live: &mut BTreeSet<V>) {
+        if let Some(def) = instr.kind.def() {
+            live.remove(def);
+        }
+        live.extend(vars(instr.kind.uses()).cloned());
+        if let InstrKind::Call { .. } = instr.kind {
+            live.extend(self.globals.iter().cloned());
+        }
+    }
+
+    fn transfer_terminator(
+        &self,
+        terminator: &Terminator<V>,
+        _point: Point,
+        live: &mut BTreeSet<V>,
+    ) {
+        if let Terminator::Branch {
+            cond: Operand::Var(var),
+            ..
+        } = terminator
+        {
+            live.insert(var.clone());
+        }
+    }
+}
+
+/// Where a reaching value of a variable was assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Definition {
+    /// The value the variable had when the function started.
+    Entry,
+    /// An assignment, `read` or call.
+    At(Point),
+}
+
+/// The assignments whose values may still be in their variables, going forward
+/// from the values in `defined_at_entry`.
+pub struct ReachingDefinitions<V> {
+    pub globals: BTreeSet<V>,
+    pub defined_at_entry: BTreeSet<V>,
+}
+
+impl<V: Clone + Ord> Analysis<V> for ReachingDefinitions<V> {
+    type Fact = BTreeSet<(V, Definition)>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> Self::Fact {
+        self.defined_at_entry
+            .iter()
+            .map(|var| (var.clone(), Definition::Entry))
+            .collect()
+    }
+
+    fn top(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        fact.extend(other.iter().cloned());
+    }
+
+    fn transfer(&self, instr: &Instr<V>, point: Point, reaching: &mut Self::Fact) {
+        let mut define = |var: &V| {
+            reaching.retain(|(other, _)| other != var);
+            reaching.insert((var.clone(), Definition::At(point)));
+        };
+        if let Some(def) = instr.kind.def() {
+            define(def);
+        }
+        if let InstrKind::Call { .. } = instr.kind {
+            self.globals.iter().for_each(define);
+        }
+    }
+}
+
+/// An operation `AvailableExpressions` keeps track of.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<V> {
+    BinOp(BinOp, Operand<V>, Operand<V>),
+    UnOp(UnOp, Operand<V>),
+}
+
+impl<V: Clone + PartialEq> Expr<V> {
+    /// The expression `kind` computes, if it's an operation.
+    pub fn of(kind: &InstrKind<V>) -> Option<Expr<V>> {
+        match kind {
+            InstrKind::BinOp { op, lhs, rhs, .. } => {
+                Some(Expr::BinOp(*op, lhs.clone(), rhs.clone()))
+            }
+            InstrKind::UnOp { op, src, .. } => Some(Expr::UnOp(*op, src.clone())),
+            _ => None,
+        }
+    }
+
+    fn reads(&self, var: &V) -> bool {
+        let operands = match self {
+            Expr::BinOp(_, lhs, rhs) => vec![lhs, rhs],
+            Expr::UnOp(_, src) => vec![src],
+        };
+        vars(operands).any(|operand| operand == var)
+    }
+}
+
+/// The operations computed on every path to a point, without any of their
+/// operands changing since. Facts are indices into `exprs`, every operation of
+/// the function.
+pub struct AvailableExpressions<V> {
+    pub globals: BTreeSet<V>,
+    pub exprs: Vec<Expr<V>>,
+}
+
+impl<V: Clone + PartialEq> AvailableExpressions<V> {
+    pub fn new(cfg: &Cfg<V>, globals: BTreeSet<V>) -> AvailableExpressions<V> {
+        let mut exprs = vec![];
+        for block in &cfg.blocks {
+            for instr in &block.instrs {
+                if let Some(expr) = Expr::of(&instr.kind) {
+                    if !exprs.contains(&expr) {
+                        exprs.push(expr);
+                    }
+                }
+            }
+        }
+        AvailableExpressions { globals, exprs }
+    }
+}
+
+impl<V: Clone + PartialEq> Analysis<V> for AvailableExpressions<V> {
+    type Fact = BTreeSet<usize>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> BTreeSet<usize> {
+        BTreeSet::new()
+    }
+
+    fn top(&self) -> BTreeSet<usize> {
+        (0..self.exprs.len()).collect()
+    }
+
+    fn join(&self, fact: &mut BTreeSet<usize>, other: &BTreeSet<usize>) {
+        fact.retain(|expr| other.contains(expr));
+    }
+
+    fn transfer(&self, instr: &Instr<V>, _point: Point, available: &mut BTreeSet<usize>) {
+        let mut kill = |var: &V| available.retain(|expr| !self.exprs[*expr].reads(var));
+        if let Some(def) = instr.kind.def() {
+            kill(def);
+        }
+        if let InstrKind::Call { .. } = instr.kind {
+            self.globals.iter().for_each(kill);
+        }
+        if let Some(expr) = Expr::of(&instr.kind) {
+            // `x := x + 1` changes what it computed.
+            if !matches!(instr.kind.def(), Some(def) if expr.reads(def)) {
+                available.insert(self.exprs.iter().position(|e| *e == expr).unwrap());
+            }
+        }
+    }
+}
+
+fn vars<V>(operands: Vec<&Operand<V>>) -> impl Iterator<Item = &V> {
+    operands.into_iter().filter_map(|operand| match operand {
+        Operand::Var(var) => Some(var),
+        Operand::Const(_) => None,
+    })
+}
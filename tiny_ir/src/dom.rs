@@ -0,0 +1,111 @@
+//! Dominators, with the iterative algorithm of Cooper, Harvey and Kennedy ("A
+//! Simple, Fast Dominance Algorithm"): a block's immediate dominator is where the
+//! dominator tree paths of all its predecessors meet, repeated until nothing
+//! changes, which is after two passes in reverse postorder unless loops nest.
+
+use crate::cfg::{BlockId, Cfg, ENTRY};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dominators {
+    /// The immediate dominator of every block, `None` for the entry and for the
+    /// blocks it doesn't reach.
+    idom: Vec<Option<BlockId>>,
+}
+
+impl Dominators {
+    pub fn new<V>(cfg: &Cfg<V>) -> Dominators {
+        let order = cfg.reverse_postorder();
+        let mut rank = vec![usize::MAX; cfg.blocks.len()];
+        for (i, block) in order.iter().enumerate() {
+            rank[*block] = i;
+        }
+
+        let mut idom = vec![None; cfg.blocks.len()];
+        idom[ENTRY] = Some(ENTRY);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in &order[1..] {
+                let new_idom = cfg.blocks[*block]
+                    .preds
+                    .iter()
+                    .filter(|pred| idom[**pred].is_some())
+                    .fold(None, |new_idom, pred| match new_idom {
+                        None => Some(*pred),
+                        Some(other) => Some(intersect(&idom, &rank, other, *pred)),
+                    });
+                if idom[*block] != new_idom {
+                    idom[*block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        idom[ENTRY] = None;
+        Dominators { idom }
+    }
+
+    pub fn idom(&self, block: BlockId) -> Option<BlockId> {
+        self.idom[block]
+    }
+
+    /// Whether every path from the entry to `block` goes through `dominator`,
+    /// which is the case for `block` itself.
+    pub fn dominates(&self, dominator: BlockId, block: BlockId) -> bool {
+        let mut block = Some(block);
+        while let Some(current) = block {
+            if current == dominator {
+                return true;
+            }
+            block = self.idom[current];
+        }
+        false
+    }
+
+    /// The blocks `block` immediately dominates, in order.
+    pub fn children(&self, block: BlockId) -> Vec<BlockId> {
+        (0..self.idom.len())
+            .filter(|child| self.idom[*child] == Some(block))
+            .collect()
+    }
+
+    /// The dominance frontier of every block: the blocks where its dominance
+    /// stops, because they have another predecessor it doesn't dominate. Going
+    /// up from each predecessor of a join point until its immediate dominator
+    /// finds them, as in Cooper, Harvey and Kennedy.
+    pub fn frontiers<V>(&self, cfg: &Cfg<V>) -> Vec<BTreeSet<BlockId>> {
+        let mut frontiers = vec![BTreeSet::new(); cfg.blocks.len()];
+        for (block, contents) in cfg.blocks.iter().enumerate() {
+            let reached = |pred: &&BlockId| **pred == ENTRY || self.idom[**pred].is_some();
+            let preds: Vec<&BlockId> = contents.preds.iter().filter(reached).collect();
+            if preds.len() < 2 {
+                continue;
+            }
+            for pred in preds {
+                let mut runner = Some(*pred);
+                while let Some(current) = runner {
+                    if Some(current) == self.idom[block] {
+                        break;
+                    }
+                    frontiers[current].insert(block);
+                    runner = self.idom[current];
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+/// The closest common dominator of `a` and `b`, walking up from whichever is
+/// further from the entry in reverse postorder.
+fn intersect(idom: &[Option<BlockId>], rank: &[usize], mut a: BlockId, mut b: BlockId) -> BlockId {
+    while a != b {
+        while rank[a] > rank[b] {
+            a = idom[a].unwrap();
+        }
+        while rank[b] > rank[a] {
+            b = idom[b].unwrap();
+        }
+    }
+    a
+}
@@ -0,0 +1,215 @@
+//! Runs three-address code the way `tiny_interpreter` runs the typed tree, with
+//! the same output and the same run-time errors at the same spans. It's how the
+//! lowering and the passes over the IR are checked against the interpreter.
+
+use crate::{Function, InstrKind, Label, Operand, Program, Var};
+use std::collections::BTreeMap;
+use std::io::Write;
+use tiny_interpreter::runtime::{self, MAX_CALL_DEPTH};
+use tiny_lexer::lexer::Span;
+use tiny_parser::ast::{evaluate, UnOp};
+
+pub use tiny_interpreter::runtime::RuntimeError;
+
+/// Runs `program`, taking the values of `read` from `input` and writing to `output`.
+/// Returns the global variables that were assigned.
+pub fn run<I, W>(
+    program: &Program,
+    input: &mut I,
+    output: &mut W,
+) -> Result<BTreeMap<String, i64>, RuntimeError>
+where
+    I: Iterator<Item = i64>,
+    W: Write,
+{
+    let mut machine = Machine {
+        program,
+        globals: BTreeMap::new(),
+        arrays: program
+            .arrays
+            .iter()
+            .map(|(name, size)| (name.clone(), vec![0; *size]))
+            .collect(),
+        depth: 0,
+        input,
+        output,
+    };
+    machine.call(&program.main, vec![])?;
+    Ok(machine.globals)
+}
+
+struct Machine<'a, I, W> {
+    program: &'a Program,
+    globals: BTreeMap<String, i64>,
+    arrays: BTreeMap<String, Vec<i64>>,
+    depth: usize,
+    input: &'a mut I,
+    output: &'a mut W,
+}
+
+/// The parameters and temporaries of a running function.
+struct Frame {
+    params: BTreeMap<String, i64>,
+    temps: Vec<i64>,
+    line: String,
+}
+
+impl<'a, I, W> Machine<'a, I, W>
+where
+    I: Iterator<Item = i64>,
+    W: Write,
+{
+    fn call(&mut self, function: &Function, args: Vec<i64>) -> Result<(), RuntimeError> {
+        let labels: BTreeMap<Label, usize> = function
+            .body
+            .iter()
+            .enumerate()
+            .filter_map(|(i, instr)| match instr.kind {
+                InstrKind::Label(label) => Some((label, i)),
+                _ => None,
+            })
+            .collect();
+        let mut frame = Frame {
+            params: function.params.iter().cloned().zip(args).collect(),
+            temps: vec![0; function.temps],
+            line: String::new(),
+        };
+
+        let mut pc = 0;
+        while let Some(instr) = function.body.get(pc) {
+            pc += 1;
+            let span = instr.span;
+            match &instr.kind {
+                InstrKind::Copy { dst, src } => {
+                    let value = self.operand(&frame, src);
+                    self.set(&mut frame, dst, value);
+                }
+                InstrKind::BinOp { dst, op, lhs, rhs } => {
+                    let (lhs, rhs) = (self.operand(&frame, lhs), self.operand(&frame, rhs));
+                    let value = evaluate(*op, lhs, rhs).ok_or_else(|| RuntimeError {
+                        span,
+                        message: runtime::DIVISION_BY_ZERO.to_string(),
+                    })?;
+                    self.set(&mut frame, dst, value);
+                }
+                InstrKind::UnOp { dst, op, src } => {
+                    let src = self.operand(&frame, src);
+                    let value = match op {
+                        UnOp::Not => (src == 0) as i64,
+                        UnOp::Neg => src.wrapping_neg(),
+                    };
+                    self.set(&mut frame, dst, value);
+                }
+                InstrKind::Load { dst, array, index } => {
+                    let i = self.index(&frame, array, index, span)?;
+                    let value = self.arrays[array][i];
+                    self.set(&mut frame, dst, value);
+                }
+                InstrKind::Store { array, index, src } => {
+                    let i = self.index(&frame, array, index, span)?;
+                    let value = self.operand(&frame, src);
+                    self.arrays.get_mut(array).unwrap()[i] = value;
+                }
+                InstrKind::Read { dst, name } => match self.input.next() {
+                    Some(value) => self.set(&mut frame, dst, value),
+                    None => {
+                        return Err(RuntimeError {
+                            span,
+                            message: runtime::out_of_input(name),
+                        })
+                    }
+                },
+                InstrKind::Write { src } => {
+                    let value = self.operand(&frame, src);
+                    frame.line.push_str(&value.to_string());
+                }
+                InstrKind::WriteString { value } => frame.line.push_str(value),
+                InstrKind::WriteLine => {
+                    writeln!(self.output, "{}", frame.line).map_err(|err| RuntimeError {
+                        span,
+                        message: runtime::output_failed(err),
+                    })?;
+                    frame.line.clear();
+                }
+                InstrKind::Call { proc, args } => {
+                    let procedure = self
+                        .program
+                        .procedures
+                        .iter()
+                        .find(|procedure| procedure.name == *proc)
+                        .ok_or_else(|| RuntimeError {
+                            span,
+                            message: runtime::undeclared_procedure(proc),
+                        })?;
+                    if self.depth >= MAX_CALL_DEPTH {
+                        return Err(RuntimeError {
+                            span,
+                            message: runtime::calls_too_deep(),
+                        });
+                    }
+                    let args = args.iter().map(|arg| self.operand(&frame, arg)).collect();
+                    self.depth += 1;
+                    let result = self.call(procedure, args);
+                    self.depth -= 1;
+                    result?;
+                }
+                InstrKind::Label(_) => {}
+                InstrKind::Jump(target) => pc = labels[target],
+                InstrKind::JumpIfZero { cond, target } => {
+                    if self.operand(&frame, cond) == 0 {
+                        pc = labels[target];
+                    }
+                }
+                InstrKind::JumpIfNonZero { cond, target } => {
+                    if self.operand(&frame, cond) != 0 {
+                        pc = labels[target];
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn operand(&self, frame: &Frame, operand: &Operand) -> i64 {
+        match operand {
+            Operand::Const(value) => *value,
+            Operand::Var(Var::Temp(i)) => frame.temps[*i],
+            Operand::Var(Var::Named(name)) => frame
+                .params
+                .get(name)
+                .or_else(|| self.globals.get(name))
+                .cloned()
+                .unwrap_or(0),
+        }
+    }
+
+    fn set(&mut self, frame: &mut Frame, var: &Var, value: i64) {
+        match var {
+            Var::Temp(i) => frame.temps[*i] = value,
+            Var::Named(name) if frame.params.contains_key(name) => {
+                frame.params.insert(name.clone(), value);
+            }
+            Var::Named(name) => {
+                self.globals.insert(name.clone(), value);
+            }
+        }
+    }
+
+    fn index(
+        &self,
+        frame: &Frame,
+        array: &str,
+        index: &Operand,
+        span: Span,
+    ) -> Result<usize, RuntimeError> {
+        let i = self.operand(frame, index);
+        let size = self.arrays[array].len();
+        if i < 0 || i as u64 >= size as u64 {
+            return Err(RuntimeError {
+                span,
+                message: runtime::index_out_of_bounds(i, array, size),
+            });
+        }
+        Ok(i as usize)
+    }
+}
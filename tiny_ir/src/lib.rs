@@ -0,0 +1,425 @@
+//! A three-address code for TINY programs: every instruction does one thing to at
+//! most two operands, and control flow is spelled out as labels and jumps. It sits
+//! between the typed tree of `tiny_parser::ast` and the back ends, so that the
+//! analyses and optimisations are written once for all of them.
+//!
+//! `lower::lower` builds it from a checked program, and its `Display` is the dump:
+//!
+//! ```text
+//! main:
+//!   read x
+//!   %0 := x * 2
+//!   ifz %0 goto L0
+//!   write %0
+//!   writeln
+//! L0:
+//! ```
+//!
+//! `cfg` cuts functions into basic blocks for the analyses, `dom` finds their
+//! dominators and `dataflow` solves data-flow problems on them, which `lint`
+//! turns into warnings. `ssa` rewrites them in static single assignment form,
+//! where `opt` propagates constants and copies and removes dead code. `x86_64`
+//! emits assembly to run them natively.
+
+pub mod cfg;
+pub mod dataflow;
+pub mod dom;
+pub mod exec;
+pub mod lint;
+pub mod lower;
+pub mod opt;
+pub mod ssa;
+pub mod x86_64;
+
+use std::collections::BTreeSet;
+use std::fmt;
+use tiny_lexer::lexer::Span;
+use tiny_parser::ast::{BinOp, UnOp};
+use tiny_parser::pretty::string_literal;
+
+pub use lower::lower;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    /// The arrays, all global and zeroed before `main` starts.
+    pub arrays: Vec<(String, usize)>,
+    pub procedures: Vec<Function>,
+    /// The top-level statements, as a function named `main` that the dump shows
+    /// as `main:` rather than as a procedure.
+    pub main: Function,
+}
+
+impl Program {
+    /// The procedures, then `main`.
+    pub fn functions(&self) -> impl Iterator<Item = &Function> {
+        self.procedures.iter().chain(std::iter::once(&self.main))
+    }
+
+    /// The variables that aren't parameters where they appear, all global.
+    pub fn globals(&self) -> BTreeSet<String> {
+        let mut globals = BTreeSet::new();
+        for function in self.functions() {
+            for instr in &function.body {
+                let uses = instr
+                    .kind
+                    .uses()
+                    .into_iter()
+                    .filter_map(|operand| match operand {
+                        Operand::Var(var) => Some(var),
+                        Operand::Const(_) => None,
+                    });
+                for var in uses.chain(instr.kind.def()) {
+                    if let Var::Named(name) = var {
+                        if !function.params.contains(name) {
+                            globals.insert(name.clone());
+                        }
+                    }
+                }
+            }
+        }
+        globals
+    }
+}
+
+/// A procedure, or the main program without any parameters. Inside a procedure a
+/// `Var::Named` is the parameter of that name if there is one, the global otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function<V = Var> {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Instr<V>>,
+    /// How many temporaries and labels the body uses, numbered from 0.
+    pub temps: usize,
+    pub labels: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Var {
+    /// A variable of the program.
+    Named(String),
+    /// A temporary holding an intermediate result, local to its function.
+    Temp(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Operand<V = Var> {
+    Var(V),
+    Const(i64),
+}
+
+impl<V> Operand<V> {
+    pub fn map<W>(&self, f: impl FnOnce(&V) -> W) -> Operand<W> {
+        match self {
+            Operand::Var(var) => Operand::Var(f(var)),
+            Operand::Const(value) => Operand::Const(*value),
+        }
+    }
+}
+
+pub type Label = usize;
+
+/// An instruction and the span of the source it was lowered from, where its
+/// run-time errors are reported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instr<V = Var> {
+    pub kind: InstrKind<V>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstrKind<V = Var> {
+    /// `dst := src`
+    Copy {
+        dst: V,
+        src: Operand<V>,
+    },
+    /// `dst := lhs op rhs`, with the operators evaluating like the interpreter's.
+    /// Never `and` or `or`, which become jumps.
+    BinOp {
+        dst: V,
+        op: BinOp,
+        lhs: Operand<V>,
+        rhs: Operand<V>,
+    },
+    /// `dst := op src`
+    UnOp {
+        dst: V,
+        op: UnOp,
+        src: Operand<V>,
+    },
+    /// `dst := array[index]`, failing if the index is out of bounds.
+    Load {
+        dst: V,
+        array: String,
+        index: Operand<V>,
+    },
+    /// `array[index] := src`, failing if the index is out of bounds.
+    Store {
+        array: String,
+        index: Operand<V>,
+        src: Operand<V>,
+    },
+    /// `read dst`, with `name` the variable or array being read for the error
+    /// when the input runs out, as `dst` may be a temporary.
+    Read {
+        dst: V,
+        name: String,
+    },
+    /// Writes the value of `src` without ending the line.
+    Write {
+        src: Operand<V>,
+    },
+    WriteString {
+        value: String,
+    },
+    /// Ends the line, after the items of a `write`.
+    WriteLine,
+    /// Calls `proc` with the arguments passed by value.
+    Call {
+        proc: String,
+        args: Vec<Operand<V>>,
+    },
+    Label(Label),
+    Jump(Label),
+    JumpIfZero {
+        cond: Operand<V>,
+        target: Label,
+    },
+    JumpIfNonZero {
+        cond: Operand<V>,
+        target: Label,
+    },
+}
+
+impl<V> InstrKind<V> {
+    /// The variable the instruction assigns, if any.
+    pub fn def(&self) -> Option<&V> {
+        match self {
+            InstrKind::Copy { dst, .. }
+            | InstrKind::BinOp { dst, .. }
+            | InstrKind::UnOp { dst, .. }
+            | InstrKind::Load { dst, .. }
+            | InstrKind::Read { dst, .. } => Some(dst),
+            _ => None,
+        }
+    }
+
+    /// The operands the instruction reads, in order.
+    pub fn uses(&self) -> Vec<&Operand<V>> {
+        match self {
+            InstrKind::Copy { src, .. }
+            | InstrKind::UnOp { src, .. }
+            | InstrKind::Write { src } => vec![src],
+            InstrKind::BinOp { lhs, rhs, .. } => vec![lhs, rhs],
+            InstrKind::Load { index, .. } => vec![index],
+            InstrKind::Store { index, src, .. } => vec![index, src],
+            InstrKind::Call { args, .. } => args.iter().collect(),
+            InstrKind::JumpIfZero { cond, .. } | InstrKind::JumpIfNonZero { cond, .. } => {
+                vec![cond]
+            }
+            InstrKind::Read { .. }
+            | InstrKind::WriteString { .. }
+            | InstrKind::WriteLine
+            | InstrKind::Label(_)
+            | InstrKind::Jump(_) => vec![],
+        }
+    }
+
+    /// The variable the instruction assigns, to be changed.
+    pub fn def_mut(&mut self) -> Option<&mut V> {
+        match self {
+            InstrKind::Copy { dst, .. }
+            | InstrKind::BinOp { dst, .. }
+            | InstrKind::UnOp { dst, .. }
+            | InstrKind::Load { dst, .. }
+            | InstrKind::Read { dst, .. } => Some(dst),
+            _ => None,
+        }
+    }
+
+    /// The operands the instruction reads, in order, to be changed.
+    pub fn uses_mut(&mut self) -> Vec<&mut Operand<V>> {
+        match self {
+            InstrKind::Copy { src, .. }
+            | InstrKind::UnOp { src, .. }
+            | InstrKind::Write { src } => vec![src],
+            InstrKind::BinOp { lhs, rhs, .. } => vec![lhs, rhs],
+            InstrKind::Load { index, .. } => vec![index],
+            InstrKind::Store { index, src, .. } => vec![index, src],
+            InstrKind::Call { args, .. } => args.iter_mut().collect(),
+            InstrKind::JumpIfZero { cond, .. } | InstrKind::JumpIfNonZero { cond, .. } => {
+                vec![cond]
+            }
+            InstrKind::Read { .. }
+            | InstrKind::WriteString { .. }
+            | InstrKind::WriteLine
+            | InstrKind::Label(_)
+            | InstrKind::Jump(_) => vec![],
+        }
+    }
+
+    /// The same instruction over other variables, `f` of each of these.
+    pub fn map<W>(&self, mut f: impl FnMut(&V) -> W) -> InstrKind<W> {
+        let mut operand = |operand: &Operand<V>| operand.map(&mut f);
+        match self {
+            InstrKind::Copy { dst, src } => {
+                let src = operand(src);
+                InstrKind::Copy { dst: f(dst), src }
+            }
+            InstrKind::BinOp { dst, op, lhs, rhs } => {
+                let (lhs, rhs) = (operand(lhs), operand(rhs));
+                InstrKind::BinOp {
+                    dst: f(dst),
+                    op: *op,
+                    lhs,
+                    rhs,
+                }
+            }
+            InstrKind::UnOp { dst, op, src } => {
+                let src = operand(src);
+                InstrKind::UnOp {
+                    dst: f(dst),
+                    op: *op,
+                    src,
+                }
+            }
+            InstrKind::Load { dst, array, index } => {
+                let index = operand(index);
+                InstrKind::Load {
+                    dst: f(dst),
+                    array: array.clone(),
+                    index,
+                }
+            }
+            InstrKind::Store { array, index, src } => InstrKind::Store {
+                array: array.clone(),
+                index: operand(index),
+                src: operand(src),
+            },
+            InstrKind::Read { dst, name } => InstrKind::Read {
+                dst: f(dst),
+                name: name.clone(),
+            },
+            InstrKind::Write { src } => InstrKind::Write { src: operand(src) },
+            InstrKind::WriteString { value } => InstrKind::WriteString {
+                value: value.clone(),
+            },
+            InstrKind::WriteLine => InstrKind::WriteLine,
+            InstrKind::Call { proc, args } => InstrKind::Call {
+                proc: proc.clone(),
+                args: args.iter().map(operand).collect(),
+            },
+            InstrKind::Label(label) => InstrKind::Label(*label),
+            InstrKind::Jump(target) => InstrKind::Jump(*target),
+            InstrKind::JumpIfZero { cond, target } => InstrKind::JumpIfZero {
+                cond: operand(cond),
+                target: *target,
+            },
+            InstrKind::JumpIfNonZero { cond, target } => InstrKind::JumpIfNonZero {
+                cond: operand(cond),
+                target: *target,
+            },
+        }
+    }
+
+    /// Where the instruction may jump to, besides falling through.
+    pub fn target(&self) -> Option<Label> {
+        match self {
+            InstrKind::Jump(target)
+            | InstrKind::JumpIfZero { target, .. }
+            | InstrKind::JumpIfNonZero { target, .. } => Some(*target),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, size) in &self.arrays {
+            writeln!(f, "array {}[{}]", name, size)?;
+        }
+        if !self.arrays.is_empty() {
+            writeln!(f)?;
+        }
+        for procedure in &self.procedures {
+            writeln!(f, "{}", procedure)?;
+        }
+        writeln!(f, "main:")?;
+        write_body(f, &self.main.body)
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for Function<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "procedure {}({}):", self.name, self.params.join(", "))?;
+        write_body(f, &self.body)
+    }
+}
+
+/// The instructions one per line, indented unless they are labels.
+pub fn write_body<V: fmt::Display>(f: &mut fmt::Formatter, body: &[Instr<V>]) -> fmt::Result {
+    for instr in body {
+        match instr.kind {
+            InstrKind::Label(_) => writeln!(f, "{}", instr)?,
+            _ => writeln!(f, "  {}", instr)?,
+        }
+    }
+    Ok(())
+}
+
+impl<V: fmt::Display> fmt::Display for Instr<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for InstrKind<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InstrKind::Copy { dst, src } => write!(f, "{} := {}", dst, src),
+            InstrKind::BinOp { dst, op, lhs, rhs } => {
+                write!(f, "{} := {} {} {}", dst, lhs, op.symbol(), rhs)
+            }
+            InstrKind::UnOp {
+                dst,
+                op: UnOp::Not,
+                src,
+            } => write!(f, "{} := not {}", dst, src),
+            InstrKind::UnOp { dst, op, src } => write!(f, "{} := {}{}", dst, op.symbol(), src),
+            InstrKind::Load { dst, array, index } => write!(f, "{} := {}[{}]", dst, array, index),
+            InstrKind::Store { array, index, src } => write!(f, "{}[{}] := {}", array, index, src),
+            InstrKind::Read { dst, .. } => write!(f, "read {}", dst),
+            InstrKind::Write { src } => write!(f, "write {}", src),
+            InstrKind::WriteString { value } => write!(f, "write {}", string_literal(value)),
+            InstrKind::WriteLine => write!(f, "writeln"),
+            InstrKind::Call { proc, args } => {
+                let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+                write!(f, "call {}({})", proc, args.join(", "))
+            }
+            InstrKind::Label(label) => write!(f, "L{}:", label),
+            InstrKind::Jump(target) => write!(f, "goto L{}", target),
+            InstrKind::JumpIfZero { cond, target } => write!(f, "ifz {} goto L{}", cond, target),
+            InstrKind::JumpIfNonZero { cond, target } => {
+                write!(f, "ifnz {} goto L{}", cond, target)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Var::Named(name) => write!(f, "{}", name),
+            Var::Temp(i) => write!(f, "%{}", i),
+        }
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for Operand<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Var(var) => write!(f, "{}", var),
+            Operand::Const(value) => write!(f, "{}", value),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Warnings from the data-flow analyses: assignments whose value is never read,
+//! and variables of the main program that may be read before anything is assigned
+//! to them, which makes them 0.
+
+use crate::cfg::{Cfg, Terminator};
+use crate::dataflow::{solve, Definition, Liveness, ReachingDefinitions};
+use crate::{Function, InstrKind, Operand, Program, Var};
+use std::collections::BTreeSet;
+use std::fmt;
+use tiny_lexer::lexer::Span;
+
+/// A program that runs but probably doesn't do what was meant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Warning at {}..{}:\n{}",
+            self.span.0, self.span.1, self.message
+        )
+    }
+}
+
+/// The warnings for every function of `program`, in source order.
+pub fn warnings(program: &Program) -> Vec<Warning> {
+    let globals = program.globals();
+    let mut warnings = vec![];
+    for procedure in &program.procedures {
+        function_warnings(procedure, &globals, false, &mut warnings);
+    }
+    function_warnings(&program.main, &globals, true, &mut warnings);
+    warnings.sort_by_key(|warning| warning.span);
+    // Both halves of `b := x and y` assign `b`.
+    warnings.dedup();
+    warnings
+}
+
+fn function_warnings(
+    function: &Function,
+    globals: &BTreeSet<String>,
+    is_main: bool,
+    warnings: &mut Vec<Warning>,
+) {
+    let globals: BTreeSet<Var> = globals
+        .iter()
+        .filter(|name| !function.params.contains(name))
+        .map(|name| Var::Named(name.clone()))
+        .collect();
+    let cfg = Cfg::new(function);
+    dead_stores(&cfg, &globals, is_main, warnings);
+    // What a procedure reads may have been assigned by its callers.
+    if is_main {
+        uninitialised_reads(&cfg, &globals, warnings);
+    }
+}
+
+fn dead_stores(cfg: &Cfg, globals: &BTreeSet<Var>, is_main: bool, warnings: &mut Vec<Warning>) {
+    // Procedures leave the globals to their callers, the main program to no one.
+    let liveness = Liveness {
+        globals: globals.clone(),
+        live_at_exit: if is_main {
+            BTreeSet::new()
+        } else {
+            globals.clone()
+        },
+    };
+    let solution = solve(cfg, &liveness);
+    for (id, block) in cfg.blocks.iter().enumerate() {
+        let live = solution.points(&liveness, cfg, id);
+        for (i, instr) in block.instrs.iter().enumerate() {
+            // Reading consumes input even if the value isn't used.
+            if let InstrKind::Read { .. } = instr.kind {
+                continue;
+            }
+            if let Some(var @ Var::Named(name)) = instr.kind.def() {
+                if !live[i + 1].contains(var) {
+                    warnings.push(Warning {
+                        span: instr.span,
+                        message: format!(
+                            "The value assigned to '{}' here is never used.\nSuggested Fix:\tRemove the assignment, or use '{}' before assigning it again.",
+                            name, name
+                        ),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn uninitialised_reads(cfg: &Cfg, globals: &BTreeSet<Var>, warnings: &mut Vec<Warning>) {
+    // Every variable starts out unassigned, as a definition at the entry.
+    let reaching = ReachingDefinitions {
+        globals: globals.clone(),
+        defined_at_entry: globals.clone(),
+    };
+    let solution = solve(cfg, &reaching);
+    for (id, block) in cfg.blocks.iter().enumerate() {
+        let points = solution.points(&reaching, cfg, id);
+        let mut uses: Vec<(&Operand, Span, usize)> = vec![];
+        for (i, instr) in block.instrs.iter().enumerate() {
+            uses.extend(
+                instr
+                    .kind
+                    .uses()
+                    .into_iter()
+                    .map(|operand| (operand, instr.span, i)),
+            );
+        }
+        if let Terminator::Branch { cond, span, .. } = &block.terminator {
+            uses.push((cond, *span, block.instrs.len()));
+        }
+
+        for (operand, span, i) in uses {
+            let (var, name) = match operand {
+                Operand::Var(var @ Var::Named(name)) => (var, name),
+                _ => continue,
+            };
+            let definitions: Vec<Definition> = points[i]
+                .iter()
+                .filter(|(other, _)| other == var)
+                .map(|(_, definition)| *definition)
+                .collect();
+            let message = match definitions.as_slice() {
+                [Definition::Entry] => format!(
+                    "'{}' is read before anything is assigned to it, so it's 0 here.",
+                    name
+                ),
+                definitions if definitions.contains(&Definition::Entry) => format!(
+                    "'{}' may be read before anything is assigned to it, and then it's 0.",
+                    name
+                ),
+                _ => continue,
+            };
+            warnings.push(Warning {
+                span,
+                message: format!(
+                    "{}\nSuggested Fix:\tAssign '{}' a value first.",
+                    message, name
+                ),
+            });
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! From the typed tree to three-address code. Expressions are evaluated into
+//! temporaries in the interpreter's order, and conditions become jumping code:
+//! `if a and b then` jumps past the branch as soon as `a` is zero, without ever
+//! computing the value of `a and b`.
+
+use crate::{Function, Instr, InstrKind, Label, Operand, Program, Var};
+use tiny_lexer::lexer::Span;
+use tiny_parser::ast::{self, BinOp, Exp, Stmt, UnOp, WriteItem};
+
+/// Lowers a program that passed `semantic::check`.
+pub fn lower(program: &ast::Program) -> Program {
+    let mut arrays = vec![];
+    let mut procedures = vec![];
+    for stmt in &program.body {
+        match stmt {
+            Stmt::Array { name, size, .. } => arrays.push((name.clone(), *size)),
+            Stmt::Procedure {
+                name, params, body, ..
+            } => procedures.push(function(name, params, body)),
+            _ => {}
+        }
+    }
+    Program {
+        arrays,
+        procedures,
+        main: function("main", &[], &program.body),
+    }
+}
+
+fn function(name: &str, params: &[String], body: &[Stmt]) -> Function {
+    let mut builder = Builder::default();
+    builder.stmts(body);
+    Function {
+        name: name.to_string(),
+        params: params.to_vec(),
+        body: builder.body,
+        temps: builder.temps,
+        labels: builder.labels,
+    }
+}
+
+#[derive(Default)]
+struct Builder {
+    body: Vec<Instr>,
+    temps: usize,
+    labels: usize,
+}
+
+impl Builder {
+    fn emit(&mut self, kind: InstrKind, span: Span) {
+        self.body.push(Instr { kind, span });
+    }
+
+    fn temp(&mut self) -> Var {
+        self.temps += 1;
+        Var::Temp(self.temps - 1)
+    }
+
+    fn label(&mut self) -> Label {
+        self.labels += 1;
+        self.labels - 1
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        let span = stmt.span();
+        match stmt {
+            Stmt::If {
+                cond,
+                then_part,
+                elsif_parts,
+                else_part,
+                ..
+            } => {
+                let end = self.label();
+                let branches: Vec<_> = std::iter::once((cond, then_part))
+                    .chain(elsif_parts.iter().map(|part| (&part.cond, &part.body)))
+                    .collect();
+                for (i, (cond, body)) in branches.iter().enumerate() {
+                    let next = self.label();
+                    self.jump_if(cond, false, next, span);
+                    self.stmts(body);
+                    if i + 1 < branches.len() || !else_part.is_empty() {
+                        self.emit(InstrKind::Jump(end), span);
+                    }
+                    self.emit(InstrKind::Label(next), span);
+                }
+                self.stmts(else_part);
+                self.emit(InstrKind::Label(end), span);
+            }
+            Stmt::Repeat { body, cond, .. } => {
+                let top = self.label();
+                self.emit(InstrKind::Label(top), span);
+                self.stmts(body);
+                self.jump_if(cond, false, top, span);
+            }
+            Stmt::While { cond, body, .. } => {
+                let (top, end) = (self.label(), self.label());
+                self.emit(InstrKind::Label(top), span);
+                self.jump_if(cond, false, end, span);
+                self.stmts(body);
+                self.emit(InstrKind::Jump(top), span);
+                self.emit(InstrKind::Label(end), span);
+            }
+            // Lowered on their own by `lower`.
+            Stmt::Procedure { .. } | Stmt::Array { .. } => {}
+            Stmt::Call { name, args, .. } => {
+                let args = args.iter().map(|arg| self.exp(arg)).collect();
+                self.emit(
+                    InstrKind::Call {
+                        proc: name.clone(),
+                        args,
+                    },
+                    span,
+                );
+            }
+            Stmt::Assign {
+                name, index, value, ..
+            } => match index {
+                Some(index) => {
+                    let src = self.exp(value);
+                    let i = self.exp(index);
+                    self.emit(
+                        InstrKind::Store {
+                            array: name.clone(),
+                            index: i,
+                            src,
+                        },
+                        index.span(),
+                    );
+                }
+                None => self.exp_into(value, Var::Named(name.clone())),
+            },
+            Stmt::Read { name, index, .. } => match index {
+                Some(index) => {
+                    let dst = self.temp();
+                    self.emit(
+                        InstrKind::Read {
+                            dst: dst.clone(),
+                            name: name.clone(),
+                        },
+                        span,
+                    );
+                    let i = self.exp(index);
+                    self.emit(
+                        InstrKind::Store {
+                            array: name.clone(),
+                            index: i,
+                            src: Operand::Var(dst),
+                        },
+                        index.span(),
+                    );
+                }
+                None => self.emit(
+                    InstrKind::Read {
+                        dst: Var::Named(name.clone()),
+                        name: name.clone(),
+                    },
+                    span,
+                ),
+            },
+            Stmt::Write { items, .. } => {
+                // Everything is evaluated before anything is written, so that a
+                // run-time error doesn't leave half a line behind.
+                let values: Vec<_> = items
+                    .iter()
+                    .map(|item| match item {
+                        WriteItem::String { value, .. } => InstrKind::WriteString {
+                            value: value.clone(),
+                        },
+                        WriteItem::Exp(exp) => InstrKind::Write { src: self.exp(exp) },
+                    })
+                    .collect();
+                for kind in values {
+                    self.emit(kind, span);
+                }
+                self.emit(InstrKind::WriteLine, span);
+            }
+        }
+    }
+
+    /// The value of `exp`, computed into a new temporary unless it's a constant or
+    /// a variable already.
+    fn exp(&mut self, exp: &Exp) -> Operand {
+        match exp {
+            Exp::Number { value, .. } => Operand::Const(*value),
+            Exp::Identifier { name, .. } => Operand::Var(Var::Named(name.clone())),
+            _ => {
+                let dst = self.temp();
+                self.exp_into(exp, dst.clone());
+                Operand::Var(dst)
+            }
+        }
+    }
+
+    /// Computes `exp` into `dst`, which is only assigned once all of `exp` has been
+    /// evaluated.
+    fn exp_into(&mut self, exp: &Exp, dst: Var) {
+        let span = exp.span();
+        match exp {
+            Exp::Number { .. } | Exp::Identifier { .. } => {
+                let src = self.exp(exp);
+                self.emit(InstrKind::Copy { dst, src }, span);
+            }
+            Exp::Index { name, index, .. } => {
+                let index = self.exp(index);
+                self.emit(
+                    InstrKind::Load {
+                        dst,
+                        array: name.clone(),
+                        index,
+                    },
+                    span,
+                );
+            }
+            Exp::UnOp {
+                op: UnOp::Not,
+                operand,
+                ..
+            } if is_boolean(operand) => self.boolean_into(exp, dst),
+            Exp::UnOp { op, operand, .. } => {
+                let src = self.exp(operand);
+                self.emit(InstrKind::UnOp { dst, op: *op, src }, span);
+            }
+            Exp::BinOp { op: BinOp::And, .. } | Exp::BinOp { op: BinOp::Or, .. } => {
+                self.boolean_into(exp, dst)
+            }
+            Exp::BinOp { op, lhs, rhs, .. } => {
+                let lhs = self.exp(lhs);
+                let rhs = self.exp(rhs);
+                self.emit(
+                    InstrKind::BinOp {
+                        dst,
+                        op: *op,
+                        lhs,
+                        rhs,
+                    },
+                    span,
+                );
+            }
+        }
+    }
+
+    /// Computes the 1 or 0 of a short-circuiting `exp` into `dst` with jumps.
+    fn boolean_into(&mut self, exp: &Exp, dst: Var) {
+        let span = exp.span();
+        let (if_false, end) = (self.label(), self.label());
+        self.jump_if(exp, false, if_false, span);
+        self.emit(
+            InstrKind::Copy {
+                dst: dst.clone(),
+                src: Operand::Const(1),
+            },
+            span,
+        );
+        self.emit(InstrKind::Jump(end), span);
+        self.emit(InstrKind::Label(if_false), span);
+        self.emit(
+            InstrKind::Copy {
+                dst,
+                src: Operand::Const(0),
+            },
+            span,
+        );
+        self.emit(InstrKind::Label(end), span);
+    }
+
+    /// Jumps to `target` if `cond` is non-zero (`when` is true) or zero (false),
+    /// falling through otherwise. `span` is the statement's, for the jumps.
+    fn jump_if(&mut self, cond: &Exp, when: bool, target: Label, span: Span) {
+        match cond {
+            Exp::UnOp {
+                op: UnOp::Not,
+                operand,
+                ..
+            } => self.jump_if(operand, !when, target, span),
+            // `a and b` is false as soon as `a` is and true only if both are; `a or b`
+            // the other way around.
+            Exp::BinOp { op, lhs, rhs, .. } if *op == BinOp::And || *op == BinOp::Or => {
+                let short_circuits = (*op == BinOp::Or) == when;
+                if short_circuits {
+                    self.jump_if(lhs, when, target, span);
+                    self.jump_if(rhs, when, target, span);
+                } else {
+                    let skip = self.label();
+                    self.jump_if(lhs, !when, skip, span);
+                    self.jump_if(rhs, when, target, span);
+                    self.emit(InstrKind::Label(skip), span);
+                }
+            }
+            _ => {
+                let cond = self.exp(cond);
+                let kind = if when {
+                    InstrKind::JumpIfNonZero { cond, target }
+                } else {
+                    InstrKind::JumpIfZero { cond, target }
+                };
+                self.emit(kind, span);
+            }
+        }
+    }
+}
+
+/// Whether `exp` is computed with jumps, and so is its negation.
+fn is_boolean(exp: &Exp) -> bool {
+    match exp {
+        Exp::BinOp { op, .. } => *op == BinOp::And || *op == BinOp::Or,
+        Exp::UnOp {
+            op: UnOp::Not,
+            operand,
+            ..
+        } => is_boolean(operand),
+        _ => false,
+    }
+}
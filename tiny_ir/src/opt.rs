@@ -0,0 +1,359 @@
+//! Optimisations on SSA form, `optimise_ssa` running them on a function until
+//! none of them changes anything more, and `optimise` doing so for a whole program.
+//!
+//! None of them moves an instruction, and none removes one that can fail at run
+//! time (a load, a division by something that may be 0) or does input or
+//! output, so an optimised program writes the same lines and fails with the
+//! same error at the same span as the original.
+
+use crate::cfg::{BlockId, Terminator, ENTRY};
+use crate::ssa::{memory_vars, Ssa, SsaVar};
+use crate::{Function, InstrKind, Operand, Program, Var};
+use std::collections::{BTreeMap, BTreeSet};
+use tiny_parser::ast::{evaluate, BinOp, UnOp};
+
+/// The program with every function optimised. What's left of the SSA versions
+/// are temporaries, so the optimised program assigns fewer globals.
+pub fn optimise(program: &Program) -> Program {
+    let memory = memory_vars(program);
+    let optimise_function = |function: &Function| {
+        let mut ssa = Ssa::new(function, &memory);
+        optimise_ssa(&mut ssa);
+        ssa.to_function()
+    };
+    Program {
+        arrays: program.arrays.clone(),
+        procedures: program.procedures.iter().map(optimise_function).collect(),
+        main: optimise_function(&program.main),
+    }
+}
+
+/// Runs every optimisation on `ssa` until none of them changes anything more.
+pub fn optimise_ssa(ssa: &mut Ssa) {
+    // Every pass runs every time, as each may give the others more to do.
+    while constant_propagation(ssa) | copy_propagation(ssa) | dead_code_elimination(ssa) {}
+}
+
+/// What constant propagation knows about a value: not computed yet as far as it
+/// can tell, always the same constant, or anything.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Unknown,
+    Const(i64),
+    Varying,
+}
+
+impl Value {
+    fn meet(self, other: Value) -> Value {
+        match (self, other) {
+            (Value::Unknown, value) | (value, Value::Unknown) => value,
+            (Value::Const(a), Value::Const(b)) if a == b => Value::Const(a),
+            _ => Value::Varying,
+        }
+    }
+}
+
+/// Sparse conditional constant propagation (Wegman and Zadeck): values are
+/// assumed constant until shown otherwise, and only along the edges branches
+/// can take given what's known. The variables found constant are replaced by
+/// their values, branches on constants become jumps, and the blocks they no
+/// longer reach are dropped. Returns whether anything changed.
+pub fn constant_propagation(ssa: &mut Ssa) -> bool {
+    let mut values: BTreeMap<SsaVar, Value> = BTreeMap::new();
+    let mut executable = vec![false; ssa.cfg.blocks.len()];
+    let mut edges: BTreeSet<(BlockId, BlockId)> = BTreeSet::new();
+    executable[ENTRY] = true;
+    let order = ssa.cfg.reverse_postorder();
+
+    // Round after round over the blocks found to run, until nothing changes.
+    // Values only ever go from unknown to constant to varying, so it ends.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let set = |values: &mut BTreeMap<SsaVar, Value>, var: &SsaVar, value: Value| match var {
+            SsaVar::Version(..) => values.insert(var.clone(), value) != Some(value),
+            SsaVar::Memory(_) => false,
+        };
+        for &id in &order {
+            if !executable[id] {
+                continue;
+            }
+            let block = &ssa.cfg.blocks[id];
+            for phi in &ssa.phis[id] {
+                let value = block
+                    .preds
+                    .iter()
+                    .zip(&phi.args)
+                    .filter(|(pred, _)| edges.contains(&(**pred, id)))
+                    .fold(Value::Unknown, |value, (_, arg)| {
+                        value.meet(value_of(ssa, &values, arg))
+                    });
+                changed |= set(&mut values, &phi.dst, value);
+            }
+            for instr in &block.instrs {
+                if let Some(dst) = instr.kind.def() {
+                    let value = evaluate_instr(ssa, &values, &instr.kind);
+                    changed |= set(&mut values, dst, value);
+                }
+            }
+            let taken = match &block.terminator {
+                Terminator::Goto(target) => vec![*target],
+                Terminator::Branch {
+                    cond,
+                    nonzero,
+                    zero,
+                    ..
+                } => match value_of(ssa, &values, cond) {
+                    Value::Unknown => vec![],
+                    Value::Const(0) => vec![*zero],
+                    Value::Const(_) => vec![*nonzero],
+                    Value::Varying => vec![*nonzero, *zero],
+                },
+                Terminator::Return => vec![],
+            };
+            for target in taken {
+                if edges.insert((id, target)) {
+                    executable[target] = true;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let constants: BTreeMap<SsaVar, i64> = values
+        .into_iter()
+        .filter_map(|(var, value)| match value {
+            Value::Const(value) => Some((var, value)),
+            _ => None,
+        })
+        .collect();
+    let mut changed = substitute(ssa, |var| {
+        constants.get(var).map(|value| Operand::Const(*value))
+    });
+
+    for id in 0..ssa.cfg.blocks.len() {
+        let folded = match ssa.cfg.blocks[id].terminator {
+            Terminator::Branch {
+                cond: Operand::Const(value),
+                nonzero,
+                zero,
+                ..
+            } => Some(if value != 0 {
+                (nonzero, zero)
+            } else {
+                (zero, nonzero)
+            }),
+            _ => None,
+        };
+        if let Some((taken, skipped)) = folded {
+            ssa.cfg.blocks[id].terminator = Terminator::Goto(taken);
+            if skipped != taken {
+                ssa.remove_edge(id, skipped);
+            }
+            changed = true;
+        }
+    }
+    let blocks = ssa.cfg.blocks.len();
+    ssa.remove_unreachable();
+    changed || ssa.cfg.blocks.len() != blocks
+}
+
+fn value_of(ssa: &Ssa, values: &BTreeMap<SsaVar, Value>, operand: &Operand<SsaVar>) -> Value {
+    match operand {
+        Operand::Const(value) => Value::Const(*value),
+        Operand::Var(SsaVar::Memory(_)) => Value::Varying,
+        // The arguments of a procedure could be anything, the globals of the main
+        // program start out as 0.
+        Operand::Var(SsaVar::Version(Var::Named(name), 0)) => {
+            if ssa.cfg.params.contains(name) {
+                Value::Varying
+            } else {
+                Value::Const(0)
+            }
+        }
+        Operand::Var(var) => values.get(var).cloned().unwrap_or(Value::Unknown),
+    }
+}
+
+fn evaluate_instr(ssa: &Ssa, values: &BTreeMap<SsaVar, Value>, kind: &InstrKind<SsaVar>) -> Value {
+    match kind {
+        InstrKind::Copy { src, .. } => value_of(ssa, values, src),
+        InstrKind::BinOp { op, lhs, rhs, .. } => {
+            match (value_of(ssa, values, lhs), value_of(ssa, values, rhs)) {
+                // A division by 0 is left to fail when it runs.
+                (Value::Const(lhs), Value::Const(rhs)) => evaluate(*op, lhs, rhs)
+                    .map(Value::Const)
+                    .unwrap_or(Value::Varying),
+                (Value::Varying, _) | (_, Value::Varying) => Value::Varying,
+                _ => Value::Unknown,
+            }
+        }
+        InstrKind::UnOp { op, src, .. } => match value_of(ssa, values, src) {
+            Value::Const(src) => Value::Const(match op {
+                UnOp::Not => (src == 0) as i64,
+                UnOp::Neg => src.wrapping_neg(),
+            }),
+            value => value,
+        },
+        _ => Value::Varying,
+    }
+}
+
+/// Replaces the reads of versions copied from another version or a constant by
+/// reads of that, as well as those of phis choosing the same value from every
+/// predecessor. Copies from memory variables stay, a call may change those in
+/// between. Returns whether anything changed.
+pub fn copy_propagation(ssa: &mut Ssa) -> bool {
+    let is_copyable =
+        |operand: &Operand<SsaVar>| !matches!(operand, Operand::Var(SsaVar::Memory(_)));
+    let mut copies: BTreeMap<SsaVar, Operand<SsaVar>> = BTreeMap::new();
+    for block in &ssa.cfg.blocks {
+        for instr in &block.instrs {
+            if let InstrKind::Copy {
+                dst: dst @ SsaVar::Version(..),
+                src,
+            } = &instr.kind
+            {
+                if is_copyable(src) {
+                    copies.insert(dst.clone(), src.clone());
+                }
+            }
+        }
+    }
+    for phi in ssa.phis.iter().flatten() {
+        let itself = Operand::Var(phi.dst.clone());
+        let mut args = phi.args.iter().filter(|arg| **arg != itself);
+        if let Some(first) = args.next() {
+            if is_copyable(first) && args.all(|arg| arg == first) {
+                copies.insert(phi.dst.clone(), first.clone());
+            }
+        }
+    }
+
+    // Copies of copies go back to the first, with a bound for the cycles of phis
+    // only unreachable code has.
+    substitute(ssa, |var| {
+        let mut operand = copies.get(var)?;
+        for _ in 0..copies.len() {
+            match operand {
+                Operand::Var(var) if copies.contains_key(var) => operand = &copies[var],
+                _ => break,
+            }
+        }
+        Some(operand.clone())
+    })
+}
+
+/// Where a version is assigned: the `i`th phi or instruction of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Site {
+    Phi(BlockId, usize),
+    Instr(BlockId, usize),
+}
+
+/// Removes the phis and instructions only assigning versions no one reads,
+/// marking what's needed from what has to stay (Cytron et al.) so that loops
+/// of otherwise unused phis go too. Returns whether anything changed.
+pub fn dead_code_elimination(ssa: &mut Ssa) -> bool {
+    let mut sites: BTreeMap<&SsaVar, Site> = BTreeMap::new();
+    for (id, block) in ssa.cfg.blocks.iter().enumerate() {
+        for (i, phi) in ssa.phis[id].iter().enumerate() {
+            sites.insert(&phi.dst, Site::Phi(id, i));
+        }
+        for (i, instr) in block.instrs.iter().enumerate() {
+            if let Some(dst) = instr.kind.def() {
+                sites.insert(dst, Site::Instr(id, i));
+            }
+        }
+    }
+
+    let mut needed: BTreeSet<Site> = BTreeSet::new();
+    let mut reads: Vec<&Operand<SsaVar>> = vec![];
+    for (id, block) in ssa.cfg.blocks.iter().enumerate() {
+        for (i, instr) in block.instrs.iter().enumerate() {
+            if has_effect(&instr.kind) {
+                needed.insert(Site::Instr(id, i));
+                reads.extend(instr.kind.uses());
+            }
+        }
+        if let Terminator::Branch { cond, .. } = &block.terminator {
+            reads.push(cond);
+        }
+    }
+    while let Some(operand) = reads.pop() {
+        let site = match operand {
+            Operand::Var(var) => sites.get(var),
+            Operand::Const(_) => None,
+        };
+        if let Some(&site) = site {
+            if needed.insert(site) {
+                match site {
+                    Site::Phi(id, i) => reads.extend(&ssa.phis[id][i].args),
+                    Site::Instr(id, i) => reads.extend(ssa.cfg.blocks[id].instrs[i].kind.uses()),
+                }
+            }
+        }
+    }
+
+    let mut changed = false;
+    for id in 0..ssa.cfg.blocks.len() {
+        let (phis, instrs) = (ssa.phis[id].len(), ssa.cfg.blocks[id].instrs.len());
+        let mut i = 0;
+        ssa.phis[id].retain(|_| {
+            i += 1;
+            needed.contains(&Site::Phi(id, i - 1))
+        });
+        let mut i = 0;
+        ssa.cfg.blocks[id].instrs.retain(|_| {
+            i += 1;
+            needed.contains(&Site::Instr(id, i - 1))
+        });
+        changed |= ssa.phis[id].len() != phis || ssa.cfg.blocks[id].instrs.len() != instrs;
+    }
+    changed
+}
+
+/// Whether the instruction has to stay even if no one reads what it assigns.
+fn has_effect(kind: &InstrKind<SsaVar>) -> bool {
+    match kind {
+        InstrKind::Copy { dst, .. } | InstrKind::UnOp { dst, .. } => {
+            matches!(dst, SsaVar::Memory(_))
+        }
+        InstrKind::BinOp { dst, op, rhs, .. } => {
+            matches!(dst, SsaVar::Memory(_))
+                || (*op == BinOp::Div && !matches!(rhs, Operand::Const(value) if *value != 0))
+        }
+        _ => true,
+    }
+}
+
+/// Replaces every read of a variable `replacement` has something for, in phis,
+/// instructions and branches. Returns whether anything changed.
+fn substitute<F>(ssa: &mut Ssa, replacement: F) -> bool
+where
+    F: Fn(&SsaVar) -> Option<Operand<SsaVar>>,
+{
+    let mut changed = false;
+    let mut replace = |operand: &mut Operand<SsaVar>| {
+        if let Operand::Var(var) = operand {
+            if let Some(new) = replacement(var) {
+                if *operand != new {
+                    *operand = new;
+                    changed = true;
+                }
+            }
+        }
+    };
+    for phi in ssa.phis.iter_mut().flatten() {
+        phi.args.iter_mut().for_each(&mut replace);
+    }
+    for block in &mut ssa.cfg.blocks {
+        for instr in &mut block.instrs {
+            instr.kind.uses_mut().into_iter().for_each(&mut replace);
+        }
+        if let Some(cond) = block.terminator.cond_mut() {
+            replace(cond);
+        }
+    }
+    changed
+}
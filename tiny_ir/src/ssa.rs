@@ -0,0 +1,456 @@
+//! Static single assignment form, built as in Cytron et al. ("Efficiently
+//! Computing Static Single Assignment Form and the Control Dependence Graph"):
+//! phis go at the iterated dominance frontiers of a variable's assignments, then
+//! a walk down the dominator tree numbers every assignment and points every read
+//! at the one it sees.
+//!
+//! A global that some procedure uses stays a `SsaVar::Memory` variable, since
+//! any call may read or assign it. Everything else, the main program's other
+//! globals, parameters and temporaries, gets a version per assignment.
+
+use crate::cfg::{Block, BlockId, Cfg, Terminator, ENTRY};
+use crate::{Function, Instr, InstrKind, Operand, Program, Var};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SsaVar {
+    /// The value of the `n`th assignment to a variable, with 0 being the value
+    /// it has when the function starts: the argument for a parameter, and 0 for
+    /// a global of the main program.
+    Version(Var, usize),
+    /// A global calls may change, read and assigned like in the original code.
+    Memory(String),
+}
+
+/// `dst := phi(args)`: the value of `args[i]` when coming from the `i`th
+/// predecessor of the block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phi {
+    pub dst: SsaVar,
+    pub args: Vec<Operand<SsaVar>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ssa {
+    pub cfg: Cfg<SsaVar>,
+    /// The phis at the start of every block, their arguments in the order of the
+    /// block's `preds`.
+    pub phis: Vec<Vec<Phi>>,
+}
+
+/// The globals some procedure uses, which stay `SsaVar::Memory` variables.
+pub fn memory_vars(program: &Program) -> BTreeSet<String> {
+    let mut memory = BTreeSet::new();
+    for procedure in &program.procedures {
+        for instr in &procedure.body {
+            let uses = instr
+                .kind
+                .uses()
+                .into_iter()
+                .filter_map(|operand| match operand {
+                    Operand::Var(var) => Some(var),
+                    Operand::Const(_) => None,
+                });
+            for var in uses.chain(instr.kind.def()) {
+                if let Var::Named(name) = var {
+                    if !procedure.params.contains(name) {
+                        memory.insert(name.clone());
+                    }
+                }
+            }
+        }
+    }
+    memory
+}
+
+impl Ssa {
+    /// Builds the SSA form of `function`, with the globals in `memory` left as
+    /// they are. Blocks the entry doesn't reach are dropped.
+    pub fn new(function: &Function, memory: &BTreeSet<String>) -> Ssa {
+        let cfg = Cfg::new(function);
+        let dominators = cfg.dominators();
+        let frontiers = dominators.frontiers(&cfg);
+
+        // Every variable starts at version 0 until renamed.
+        let unnumbered = |var: &Var| match var {
+            Var::Named(name) if memory.contains(name) && !function.params.contains(name) => {
+                SsaVar::Memory(name.clone())
+            }
+            var => SsaVar::Version(var.clone(), 0),
+        };
+        let mut ssa = Ssa {
+            cfg: Cfg {
+                name: cfg.name.clone(),
+                params: cfg.params.clone(),
+                blocks: cfg
+                    .blocks
+                    .iter()
+                    .map(|block| Block {
+                        instrs: block
+                            .instrs
+                            .iter()
+                            .map(|instr| Instr {
+                                kind: instr.kind.map(unnumbered),
+                                span: instr.span,
+                            })
+                            .collect(),
+                        terminator: block.terminator.map(unnumbered),
+                        preds: block.preds.clone(),
+                    })
+                    .collect(),
+                exit: cfg.exit,
+                temps: cfg.temps,
+            },
+            phis: vec![vec![]; cfg.blocks.len()],
+        };
+
+        // Where each variable is assigned, then a phi wherever two of those meet.
+        // Only the variables some block reads before assigning them need one, the
+        // temporaries of a single expression don't (Briggs' semi-pruned form).
+        let mut assigned: BTreeMap<Var, BTreeSet<BlockId>> = BTreeMap::new();
+        let mut non_local: BTreeSet<&Var> = BTreeSet::new();
+        for (id, block) in ssa.cfg.blocks.iter().enumerate() {
+            let mut local = BTreeSet::new();
+            let uses = block
+                .instrs
+                .iter()
+                .map(|instr| (instr.kind.uses(), instr.kind.def()));
+            let cond = block.terminator.cond().map(|cond| (vec![cond], None));
+            for (uses, def) in uses.chain(cond) {
+                for operand in uses {
+                    if let Operand::Var(SsaVar::Version(var, _)) = operand {
+                        if !local.contains(var) {
+                            non_local.insert(var);
+                        }
+                    }
+                }
+                if let Some(SsaVar::Version(var, _)) = def {
+                    local.insert(var);
+                    assigned.entry(var.clone()).or_default().insert(id);
+                }
+            }
+        }
+        for (var, blocks) in assigned {
+            if !non_local.contains(&var) {
+                continue;
+            }
+            let mut worklist: Vec<BlockId> = blocks.iter().cloned().collect();
+            let mut has_phi = BTreeSet::new();
+            while let Some(block) = worklist.pop() {
+                for frontier in &frontiers[block] {
+                    if has_phi.insert(*frontier) {
+                        let unnumbered = SsaVar::Version(var.clone(), 0);
+                        ssa.phis[*frontier].push(Phi {
+                            dst: unnumbered.clone(),
+                            args: vec![
+                                Operand::Var(unnumbered);
+                                ssa.cfg.blocks[*frontier].preds.len()
+                            ],
+                        });
+                        if !blocks.contains(frontier) {
+                            worklist.push(*frontier);
+                        }
+                    }
+                }
+            }
+        }
+        for phis in &mut ssa.phis {
+            phis.sort_by(|a, b| a.dst.cmp(&b.dst));
+        }
+
+        let mut renamer = Renamer {
+            children: (0..ssa.cfg.blocks.len())
+                .map(|block| dominators.children(block))
+                .collect(),
+            versions: BTreeMap::new(),
+            stacks: BTreeMap::new(),
+        };
+        renamer.rename(&mut ssa, ENTRY);
+        ssa.remove_unreachable();
+        ssa
+    }
+
+    /// Drops the edge from `from` to `to`, along with the phi arguments for it.
+    pub fn remove_edge(&mut self, from: BlockId, to: BlockId) {
+        let preds = &mut self.cfg.blocks[to].preds;
+        if let Some(i) = preds.iter().position(|pred| *pred == from) {
+            preds.remove(i);
+            for phi in &mut self.phis[to] {
+                phi.args.remove(i);
+            }
+        }
+    }
+
+    /// Drops the blocks the entry doesn't reach, except the exit, and numbers the
+    /// others again in the same order.
+    pub fn remove_unreachable(&mut self) {
+        let mut kept = vec![false; self.cfg.blocks.len()];
+        for block in self.cfg.reverse_postorder() {
+            kept[block] = true;
+        }
+        kept[self.cfg.exit] = true;
+        for block in 0..self.cfg.blocks.len() {
+            for pred in self.cfg.blocks[block].preds.clone() {
+                if !kept[pred] {
+                    self.remove_edge(pred, block);
+                }
+            }
+        }
+
+        let mut renumbered = vec![0; kept.len()];
+        let mut next = 0;
+        for (block, kept) in kept.iter().enumerate() {
+            if *kept {
+                renumbered[block] = next;
+                next += 1;
+            }
+        }
+        let blocks = std::mem::take(&mut self.cfg.blocks);
+        let phis = std::mem::take(&mut self.phis);
+        for ((mut block, phis), kept) in blocks.into_iter().zip(phis).zip(kept) {
+            if !kept {
+                continue;
+            }
+            block.terminator = match block.terminator {
+                Terminator::Goto(target) => Terminator::Goto(renumbered[target]),
+                Terminator::Branch {
+                    cond,
+                    nonzero,
+                    zero,
+                    span,
+                } => Terminator::Branch {
+                    cond,
+                    nonzero: renumbered[nonzero],
+                    zero: renumbered[zero],
+                    span,
+                },
+                Terminator::Return => Terminator::Return,
+            };
+            for pred in &mut block.preds {
+                *pred = renumbered[*pred];
+            }
+            self.cfg.blocks.push(block);
+            self.phis.push(phis);
+        }
+        self.cfg.exit = renumbered[self.cfg.exit];
+    }
+
+    /// Leaves SSA form. Every version of a variable becomes a temporary of its
+    /// own, except the versions 0 of the parameters and globals, which are those
+    /// variables, and a phi becomes a copy at the end of every predecessor into
+    /// a temporary of its own, and a copy from that at the start of its block.
+    /// Copying through that temporary keeps phis reading each other's values
+    /// from before the block, and makes a copy on an edge it wasn't meant for
+    /// harmless.
+    pub fn to_function(&self) -> Function {
+        let mut temps = 0;
+        let incoming: Vec<Vec<Var>> = self
+            .phis
+            .iter()
+            .map(|phis| {
+                phis.iter()
+                    .map(|_| {
+                        temps += 1;
+                        Var::Temp(temps - 1)
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut names: BTreeMap<SsaVar, Var> = BTreeMap::new();
+        let mut name = |var: &SsaVar| match var {
+            SsaVar::Memory(name) | SsaVar::Version(Var::Named(name), 0) => Var::Named(name.clone()),
+            var => {
+                let next = Var::Temp(temps + names.len());
+                names.entry(var.clone()).or_insert(next).clone()
+            }
+        };
+
+        // Phi copies can't fail, so their span doesn't matter.
+        let instr = |kind| Instr { kind, span: (0, 0) };
+        let mut body = vec![];
+        for (id, block) in self.cfg.blocks.iter().enumerate() {
+            body.push(instr(InstrKind::Label(id)));
+            for (phi, temp) in self.phis[id].iter().zip(&incoming[id]) {
+                body.push(instr(InstrKind::Copy {
+                    dst: name(&phi.dst),
+                    src: Operand::Var(temp.clone()),
+                }));
+            }
+            for original in &block.instrs {
+                body.push(Instr {
+                    kind: original.kind.map(&mut name),
+                    span: original.span,
+                });
+            }
+            for succ in block.terminator.succs() {
+                let i = self.cfg.blocks[succ]
+                    .preds
+                    .iter()
+                    .position(|pred| *pred == id)
+                    .unwrap();
+                for (phi, temp) in self.phis[succ].iter().zip(&incoming[succ]) {
+                    body.push(instr(InstrKind::Copy {
+                        dst: temp.clone(),
+                        src: phi.args[i].map(&mut name),
+                    }));
+                }
+            }
+
+            let next = id + 1;
+            match &block.terminator {
+                Terminator::Goto(target) if *target == next => {}
+                Terminator::Goto(target) => body.push(instr(InstrKind::Jump(*target))),
+                Terminator::Branch {
+                    cond,
+                    nonzero,
+                    zero,
+                    span,
+                } => {
+                    let cond = cond.map(&mut name);
+                    let jump = |kind| Instr { kind, span: *span };
+                    if *nonzero == next {
+                        body.push(jump(InstrKind::JumpIfZero {
+                            cond,
+                            target: *zero,
+                        }));
+                    } else {
+                        body.push(jump(InstrKind::JumpIfNonZero {
+                            cond,
+                            target: *nonzero,
+                        }));
+                        if *zero != next {
+                            body.push(jump(InstrKind::Jump(*zero)));
+                        }
+                    }
+                }
+                Terminator::Return => {}
+            }
+        }
+
+        let targets: BTreeSet<_> = body
+            .iter()
+            .filter_map(|instr| instr.kind.target())
+            .collect();
+        body.retain(|instr| match instr.kind {
+            InstrKind::Label(label) => targets.contains(&label),
+            _ => true,
+        });
+        let temps = temps + names.len();
+        Function {
+            name: self.cfg.name.clone(),
+            params: self.cfg.params.clone(),
+            body,
+            temps,
+            labels: self.cfg.blocks.len(),
+        }
+    }
+}
+
+struct Renamer {
+    children: Vec<Vec<BlockId>>,
+    /// How many versions of each variable there are so far.
+    versions: BTreeMap<Var, usize>,
+    /// The version of each variable every block on the way down the dominator
+    /// tree assigned last, so the top one is what's read.
+    stacks: BTreeMap<Var, Vec<usize>>,
+}
+
+impl Renamer {
+    fn rename(&mut self, ssa: &mut Ssa, block: BlockId) {
+        let mut assigned = vec![];
+        for phi in &mut ssa.phis[block] {
+            self.assign(&mut phi.dst, &mut assigned);
+        }
+        let contents = &mut ssa.cfg.blocks[block];
+        for instr in &mut contents.instrs {
+            for operand in instr.kind.uses_mut() {
+                self.read(operand);
+            }
+            if let Some(dst) = instr.kind.def_mut() {
+                self.assign(dst, &mut assigned);
+            }
+        }
+        if let Some(cond) = contents.terminator.cond_mut() {
+            self.read(cond);
+        }
+
+        for succ in contents.terminator.succs() {
+            let i = ssa.cfg.blocks[succ]
+                .preds
+                .iter()
+                .position(|pred| *pred == block)
+                .unwrap();
+            for phi in &mut ssa.phis[succ] {
+                self.read(&mut phi.args[i]);
+            }
+        }
+        for child in self.children[block].clone() {
+            self.rename(ssa, child);
+        }
+
+        for var in assigned {
+            self.stacks.get_mut(&var).unwrap().pop();
+        }
+    }
+
+    fn read(&self, operand: &mut Operand<SsaVar>) {
+        if let Operand::Var(SsaVar::Version(var, version)) = operand {
+            *version = self
+                .stacks
+                .get(var)
+                .and_then(|stack| stack.last())
+                .cloned()
+                .unwrap_or(0);
+        }
+    }
+
+    fn assign(&mut self, dst: &mut SsaVar, assigned: &mut Vec<Var>) {
+        if let SsaVar::Version(var, version) = dst {
+            let count = self.versions.entry(var.clone()).or_insert(0);
+            *count += 1;
+            *version = *count;
+            self.stacks.entry(var.clone()).or_default().push(*count);
+            assigned.push(var.clone());
+        }
+    }
+}
+
+/// The pretty-printed form: the blocks with their predecessors, each starting
+/// with its phis, `x.2 := phi(B0: x.0, B3: x.1)`.
+impl fmt::Display for Ssa {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}({}):", self.cfg.name, self.cfg.params.join(", "))?;
+        for (id, block) in self.cfg.blocks.iter().enumerate() {
+            write!(f, "{}:", self.cfg.block_name(id))?;
+            if !block.preds.is_empty() {
+                let preds: Vec<String> = block.preds.iter().map(|p| format!("B{}", p)).collect();
+                write!(f, " preds {}", preds.join(", "))?;
+            }
+            writeln!(f)?;
+            for phi in &self.phis[id] {
+                let args: Vec<String> = block
+                    .preds
+                    .iter()
+                    .zip(&phi.args)
+                    .map(|(pred, arg)| format!("B{}: {}", pred, arg))
+                    .collect();
+                writeln!(f, "  {} := phi({})", phi.dst, args.join(", "))?;
+            }
+            for instr in &block.instrs {
+                writeln!(f, "  {}", instr)?;
+            }
+            writeln!(f, "  {}", block.terminator)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SsaVar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SsaVar::Version(var, version) => write!(f, "{}.{}", var, version),
+            SsaVar::Memory(name) => write!(f, "{}", name),
+        }
+    }
+}
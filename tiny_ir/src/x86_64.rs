@@ -0,0 +1,444 @@
+//! A back end emitting x86-64 assembly for the GNU assembler, to be linked with
+//! the C library by the system compiler (`cc program.s -o program`).
+//!
+//! Every variable is a quadword in `.bss`, and every parameter and temporary a
+//! slot in its function's frame. Procedures follow the System V stack alignment
+//! but take their arguments on the stack, pushed last to first, so that any
+//! number of them fits. `read` and `write` go through the small runtime at the
+//! end of the file, calling `scanf` and `snprintf`/`printf`. The line being
+//! written is kept until it ends, and run-time errors are written to stderr
+//! before exiting with status 1, so a program writes what it writes under `exec`.
+
+use crate::{Function, InstrKind, Operand, Program, Var};
+use tiny_interpreter::runtime::{self, MAX_CALL_DEPTH};
+use tiny_lexer::lexer::Span;
+use tiny_parser::ast::{BinOp, UnOp};
+
+/// The assembly of `program`, defining `main`.
+pub fn emit(program: &Program) -> String {
+    let mut emitter = Emitter {
+        program,
+        text: String::new(),
+        rodata: String::new(),
+        labels: 0,
+    };
+    for procedure in &program.procedures {
+        emitter.function(procedure, &format!("proc_{}", procedure.name));
+    }
+    emitter.function(&program.main, "main");
+
+    let mut asm = String::from("\t.text\n\t.globl main\n");
+    asm.push_str(&emitter.text);
+    asm.push_str("\n\t.section .rodata\n");
+    asm.push_str(&emitter.rodata);
+    asm.push_str("\n\t.bss\n");
+    for name in program.globals() {
+        asm.push_str(&format!("\t.lcomm var_{}, 8\n", name));
+    }
+    // `semantic::check` keeps the sizes within `semantic::MAX_ARRAY_SIZE`.
+    for (name, size) in &program.arrays {
+        asm.push_str(&format!("\t.lcomm arr_{}, {}\n", name, 8 * (*size).max(1)));
+    }
+    asm.push_str(RUNTIME);
+    asm
+}
+
+struct Emitter<'a> {
+    program: &'a Program,
+    text: String,
+    rodata: String,
+    /// How many local labels and constants are used so far, numbered from 0.
+    labels: usize,
+}
+
+impl<'a> Emitter<'a> {
+    fn function(&mut self, function: &Function, symbol: &str) {
+        // The frame keeps the stack aligned to 16 bytes for the calls inside.
+        let frame = (8 * function.temps).div_ceil(16) * 16;
+        self.line(&format!("\n{}:", symbol));
+        self.line("\tpushq %rbp");
+        self.line("\tmovq %rsp, %rbp");
+        if frame > 0 {
+            self.line(&format!("\tsubq ${}, %rsp", frame));
+        }
+
+        for instr in &function.body {
+            let span = instr.span;
+            match &instr.kind {
+                InstrKind::Copy { dst, src } => {
+                    self.load(function, src, "%rax");
+                    self.store(function, dst);
+                }
+                InstrKind::BinOp { dst, op, lhs, rhs } => {
+                    self.load(function, lhs, "%rax");
+                    self.load(function, rhs, "%rcx");
+                    self.binop(*op, rhs, span);
+                    self.store(function, dst);
+                }
+                InstrKind::UnOp { dst, op, src } => {
+                    self.load(function, src, "%rax");
+                    match op {
+                        UnOp::Not => self.set_if("e", "\ttestq %rax, %rax"),
+                        UnOp::Neg => self.line("\tnegq %rax"),
+                    }
+                    self.store(function, dst);
+                }
+                InstrKind::Load { dst, array, index } => {
+                    self.index(function, array, index, span);
+                    self.line(&format!("\tleaq arr_{}(%rip), %rcx", array));
+                    self.line("\tmovq (%rcx,%rax,8), %rax");
+                    self.store(function, dst);
+                }
+                InstrKind::Store { array, index, src } => {
+                    self.index(function, array, index, span);
+                    self.load(function, src, "%rcx");
+                    self.line(&format!("\tleaq arr_{}(%rip), %rdx", array));
+                    self.line("\tmovq %rcx, (%rdx,%rax,8)");
+                }
+                InstrKind::Read { dst, name } => {
+                    let message = error_format(span, &runtime::out_of_input(name));
+                    let message = self.constant(&message);
+                    self.line(&format!("\tleaq {}(%rip), %rdi", message));
+                    self.line("\tcall tiny_read");
+                    self.store(function, dst);
+                }
+                InstrKind::Write { src } => {
+                    self.load(function, src, "%rdi");
+                    self.line("\tcall tiny_write_int");
+                }
+                InstrKind::WriteString { value } => {
+                    let value = self.constant(value);
+                    self.line(&format!("\tleaq {}(%rip), %rdi", value));
+                    self.line("\tcall tiny_write_str");
+                }
+                InstrKind::WriteLine => self.line("\tcall tiny_writeln"),
+                InstrKind::Call { proc, args } => self.call(function, proc, args, span),
+                InstrKind::Label(label) => self.line(&format!(".L{}_{}:", symbol, label)),
+                InstrKind::Jump(target) => self.line(&format!("\tjmp .L{}_{}", symbol, target)),
+                InstrKind::JumpIfZero { cond, target } => {
+                    self.load(function, cond, "%rax");
+                    self.line("\ttestq %rax, %rax");
+                    self.line(&format!("\tjz .L{}_{}", symbol, target));
+                }
+                InstrKind::JumpIfNonZero { cond, target } => {
+                    self.load(function, cond, "%rax");
+                    self.line("\ttestq %rax, %rax");
+                    self.line(&format!("\tjnz .L{}_{}", symbol, target));
+                }
+            }
+        }
+
+        self.line("\txorl %eax, %eax");
+        self.line("\tleave");
+        self.line("\tret");
+    }
+
+    /// `%rax op %rcx` into `%rax`, `rhs` being what's in `%rcx`.
+    fn binop(&mut self, op: BinOp, rhs: &Operand, span: Span) {
+        let comparison = match op {
+            BinOp::Add => return self.line("\taddq %rcx, %rax"),
+            BinOp::Sub => return self.line("\tsubq %rcx, %rax"),
+            BinOp::Mul => return self.line("\timulq %rcx, %rax"),
+            BinOp::Div => return self.divide(rhs, span),
+            BinOp::Lt => "l",
+            BinOp::Le => "le",
+            BinOp::Gt => "g",
+            BinOp::Ge => "ge",
+            BinOp::Eq => "e",
+            BinOp::Ne => "ne",
+            BinOp::And | BinOp::Or => {
+                self.line("\ttestq %rax, %rax");
+                self.line("\tsetne %al");
+                self.line("\ttestq %rcx, %rcx");
+                self.line("\tsetne %cl");
+                let op = if op == BinOp::And { "andb" } else { "orb" };
+                self.line(&format!("\t{} %cl, %al", op));
+                return self.line("\tmovzbq %al, %rax");
+            }
+        };
+        self.set_if(comparison, "\tcmpq %rcx, %rax");
+    }
+
+    /// `%rax / %rcx` into `%rax`, failing on a division by 0. `idiv` traps on the
+    /// one quotient that doesn't fit, of the smallest number by -1, which wraps
+    /// around to itself like in the interpreter.
+    fn divide(&mut self, rhs: &Operand, span: Span) {
+        match rhs {
+            Operand::Const(0) | Operand::Var(_) => {
+                self.line("\ttestq %rcx, %rcx");
+                self.fail_unless("nz", span, runtime::DIVISION_BY_ZERO, false);
+            }
+            Operand::Const(_) => {}
+        }
+        let (divide, done) = (self.label(), self.label());
+        self.line("\tcmpq $-1, %rcx");
+        self.line(&format!("\tjne {}", divide));
+        self.line("\tnegq %rax");
+        self.line(&format!("\tjmp {}", done));
+        self.line(&format!("{}:", divide));
+        self.line("\tcqto");
+        self.line("\tidivq %rcx");
+        self.line(&format!("{}:", done));
+    }
+
+    /// 1 in `%rax` if the flags `test` sets meet `condition`, 0 otherwise.
+    fn set_if(&mut self, condition: &str, test: &str) {
+        self.line(test);
+        self.line(&format!("\tset{} %al", condition));
+        self.line("\tmovzbq %al, %rax");
+    }
+
+    /// The value of `index` in `%rax`, failing unless it's within `array`.
+    fn index(&mut self, function: &Function, array: &str, index: &Operand, span: Span) {
+        let size = self
+            .program
+            .arrays
+            .iter()
+            .find(|(name, _)| name == array)
+            .map_or(0, |(_, size)| *size);
+        self.load(function, index, "%rax");
+        self.load(function, &Operand::Const(size as i64), "%rcx");
+        // Negative indices are above every size as unsigned numbers.
+        self.line("\tcmpq %rcx, %rax");
+        self.line("\tmovq %rax, %rsi");
+        let message = runtime::index_out_of_bounds("%ld", array, size);
+        self.fail_unless("b", span, &message, true);
+    }
+
+    fn call(&mut self, function: &Function, proc: &str, args: &[Operand], span: Span) {
+        if !self
+            .program
+            .procedures
+            .iter()
+            .any(|procedure| procedure.name == proc)
+        {
+            let message = error_format(span, &runtime::undeclared_procedure(proc));
+            return self.fail(&message);
+        }
+        self.line("\tmovq tiny_depth(%rip), %rax");
+        self.line(&format!("\tcmpq ${}, %rax", MAX_CALL_DEPTH));
+        self.fail_unless("b", span, &runtime::calls_too_deep(), false);
+
+        // The arguments go below the return address, the first one nearest.
+        let padding = if args.len() % 2 == 1 { 8 } else { 0 };
+        if padding > 0 {
+            self.line("\tsubq $8, %rsp");
+        }
+        for arg in args.iter().rev() {
+            self.load(function, arg, "%rax");
+            self.line("\tpushq %rax");
+        }
+        self.line("\tincq tiny_depth(%rip)");
+        self.line(&format!("\tcall proc_{}", proc));
+        self.line("\tdecq tiny_depth(%rip)");
+        let pushed = 8 * args.len() + padding;
+        if pushed > 0 {
+            self.line(&format!("\taddq ${}, %rsp", pushed));
+        }
+    }
+
+    /// Fails with `message` at `span` unless the flags meet `condition`, with the
+    /// value in `%rsi` for a `%ld` in the message if `with_value`.
+    fn fail_unless(&mut self, condition: &str, span: Span, message: &str, with_value: bool) {
+        let message = if with_value {
+            format!("Runtime Error at {}..{}:\n{}\n", span.0, span.1, message)
+        } else {
+            error_format(span, message)
+        };
+        let ok = self.label();
+        self.line(&format!("\tj{} {}", condition, ok));
+        self.fail(&message);
+        self.line(&format!("{}:", ok));
+    }
+
+    /// Fails with what `printf` makes of the format `message`.
+    fn fail(&mut self, message: &str) {
+        let message = self.constant(message);
+        self.line(&format!("\tleaq {}(%rip), %rdi", message));
+        self.line("\tcall tiny_fail");
+    }
+
+    fn load(&mut self, function: &Function, operand: &Operand, register: &str) {
+        match operand {
+            Operand::Const(value) if *value as i32 as i64 == *value => {
+                self.line(&format!("\tmovq ${}, {}", value, register))
+            }
+            Operand::Const(value) => self.line(&format!("\tmovabsq ${}, {}", value, register)),
+            Operand::Var(var) => {
+                let location = location(function, var);
+                self.line(&format!("\tmovq {}, {}", location, register))
+            }
+        }
+    }
+
+    /// Stores `%rax` into `var`.
+    fn store(&mut self, function: &Function, var: &Var) {
+        self.line(&format!("\tmovq %rax, {}", location(function, var)));
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!(".L{}", self.labels - 1)
+    }
+
+    /// The label of a string constant holding `value`.
+    fn constant(&mut self, value: &str) -> String {
+        let label = self.label();
+        self.rodata
+            .push_str(&format!("{}:\n\t.asciz \"{}\"\n", label, asm_string(value)));
+        label
+    }
+
+    fn line(&mut self, line: &str) {
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+}
+
+/// Where `var` lives in `function`: the arguments above the return address, the
+/// temporaries in the frame, the globals in `.bss`.
+fn location(function: &Function, var: &Var) -> String {
+    match var {
+        Var::Temp(i) => format!("-{}(%rbp)", 8 * (i + 1)),
+        Var::Named(name) => match function.params.iter().position(|param| param == name) {
+            Some(i) => format!("{}(%rbp)", 16 + 8 * i),
+            None => format!("var_{}(%rip)", name),
+        },
+    }
+}
+
+/// The `printf` format writing the run-time error `message` at `span`, as
+/// `runtime::RuntimeError` displays it.
+fn error_format(span: Span, message: &str) -> String {
+    format!(
+        "Runtime Error at {}..{}:\n{}\n",
+        span.0,
+        span.1,
+        escape_format(message)
+    )
+}
+
+fn escape_format(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+/// `value` as the contents of a `.asciz` directive.
+fn asm_string(value: &str) -> String {
+    let mut escaped = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            b' '..=b'~' => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    escaped
+}
+
+/// Reading, writing and failing, shared by every program.
+const RUNTIME: &str = r#"	.lcomm tiny_line, 4096
+	.lcomm tiny_line_length, 8
+	.lcomm tiny_depth, 8
+
+	.section .rodata
+.Lint_format:
+	.asciz "%ld"
+.Lstr_format:
+	.asciz "%s"
+.Lline_format:
+	.asciz "%s\n"
+
+	.text
+# Writes what printf makes of the format in %rdi and the value in %rsi to
+# stderr, then exits with status 1.
+tiny_fail:
+	subq $8, %rsp
+	movq %rsi, %rdx
+	movq %rdi, %rsi
+	movq stderr@GOTPCREL(%rip), %rdi
+	movq (%rdi), %rdi
+	xorl %eax, %eax
+	call fprintf@PLT
+	movl $1, %edi
+	call exit@PLT
+
+# Reads a number into %rax, failing with the format in %rdi if there's none.
+tiny_read:
+	pushq %rbx
+	subq $16, %rsp
+	movq %rdi, %rbx
+	leaq .Lint_format(%rip), %rdi
+	movq %rsp, %rsi
+	xorl %eax, %eax
+	call scanf@PLT
+	cmpl $1, %eax
+	jne 1f
+	movq (%rsp), %rax
+	addq $16, %rsp
+	popq %rbx
+	ret
+1:	movq %rbx, %rdi
+	call tiny_fail
+
+tiny_write_int:
+	movq %rdi, %rdx
+	leaq .Lint_format(%rip), %rsi
+	jmp tiny_append
+
+tiny_write_str:
+	movq %rdi, %rdx
+	leaq .Lstr_format(%rip), %rsi
+	jmp tiny_append
+
+# Adds what printf makes of the format in %rsi and the value in %rdx to the
+# line being written. If it doesn't fit, the line so far is written out first.
+tiny_append:
+	pushq %rbx
+	pushq %r12
+	pushq %r13
+	movq %rsi, %r12
+	movq %rdx, %r13
+	movq tiny_line_length(%rip), %rbx
+	leaq tiny_line(%rip), %rdi
+	addq %rbx, %rdi
+	movq $4096, %rsi
+	subq %rbx, %rsi
+	movq %r12, %rdx
+	movq %r13, %rcx
+	xorl %eax, %eax
+	call snprintf@PLT
+	addq %rbx, %rax
+	cmpq $4096, %rax
+	jae 1f
+	movq %rax, tiny_line_length(%rip)
+	jmp 2f
+1:	leaq tiny_line(%rip), %rsi
+	movb $0, (%rsi,%rbx)
+	leaq .Lstr_format(%rip), %rdi
+	xorl %eax, %eax
+	call printf@PLT
+	movq %r12, %rdi
+	movq %r13, %rsi
+	xorl %eax, %eax
+	call printf@PLT
+	movq $0, tiny_line_length(%rip)
+	movb $0, tiny_line(%rip)
+2:	popq %r13
+	popq %r12
+	popq %rbx
+	ret
+
+tiny_writeln:
+	subq $8, %rsp
+	leaq .Lline_format(%rip), %rdi
+	leaq tiny_line(%rip), %rsi
+	xorl %eax, %eax
+	call printf@PLT
+	movq $0, tiny_line_length(%rip)
+	movb $0, tiny_line(%rip)
+	addq $8, %rsp
+	ret
+
+	.section .note.GNU-stack,"",@progbits
+"#;
@@ -0,0 +1,120 @@
+mod common;
+
+use common::main_cfg;
+use tiny_ir::cfg::{self, Cfg, Terminator, ENTRY};
+use tiny_ir::{lower, Function, Instr, InstrKind, Operand, Var};
+use tiny_parser::ast::parse_program;
+
+#[test]
+fn cuts_blocks_at_labels_and_jumps() {
+    let cfg = main_cfg("read x; if x < 0 then x := -x else write x end; write x ");
+    assert_eq!(
+        cfg.to_string(),
+        "main():
+B0 (entry):
+  read x
+  %0 := x < 0
+  if %0 goto B1 else B2
+B1: preds B0; idom B0
+  x := -x
+  goto B3
+B2: preds B0; idom B0
+  write x
+  writeln
+  goto B3
+B3: preds B1, B2; idom B0
+  write x
+  writeln
+  goto B4
+B4 (exit): preds B3; idom B3
+  return
+"
+    );
+}
+
+#[test]
+fn loops_at_the_start_get_their_own_header() {
+    // Nothing jumps back to the entry, even when the program starts with a loop.
+    let cfg = main_cfg("repeat x := x + 1 until x > 3 ");
+    assert!(cfg.blocks[ENTRY].instrs.is_empty());
+    assert!(cfg.blocks[ENTRY].preds.is_empty());
+    assert_eq!(cfg.blocks[1].preds, vec![0, 1]);
+    assert_eq!(
+        cfg.blocks[1].terminator.succs(),
+        vec![2, 1],
+        "leaves the loop when the condition is non-zero"
+    );
+}
+
+#[test]
+fn branches_to_the_same_block_are_one_edge() {
+    let instr = |kind| Instr { kind, span: (0, 1) };
+    let function = Function {
+        name: "main".to_string(),
+        params: vec![],
+        body: vec![
+            instr(InstrKind::JumpIfZero {
+                cond: Operand::Var(Var::Named("x".to_string())),
+                target: 0,
+            }),
+            instr(InstrKind::Label(0)),
+        ],
+        temps: 0,
+        labels: 1,
+    };
+    let cfg = Cfg::new(&function);
+    match cfg.blocks[ENTRY].terminator {
+        Terminator::Branch { nonzero, zero, .. } => assert_eq!((nonzero, zero), (1, 1)),
+        ref terminator => panic!("expected a branch, found {:?}", terminator),
+    }
+    assert_eq!(cfg.blocks[1].preds, vec![ENTRY]);
+}
+
+#[test]
+fn finds_dominators() {
+    let cfg = main_cfg(
+        "read x;
+while x > 0 do
+  if x = 5 then write 5 end;
+  x := x - 1
+end;
+write x ",
+    );
+    let dominators = cfg.dominators();
+    let header = 1;
+    for block in 1..cfg.blocks.len() {
+        assert!(dominators.dominates(ENTRY, block));
+        assert!(dominators.dominates(block, block));
+    }
+    // Every block of the loop and everything after it is only reached through
+    // the header, but nothing is only reached through the `then` part.
+    let (body, then_part) = (2, 3);
+    assert_eq!(dominators.idom(body), Some(header));
+    assert!(dominators.dominates(header, cfg.exit));
+    assert!(dominators.dominates(body, then_part));
+    assert!(dominators.children(then_part).is_empty());
+    assert!(!dominators.dominates(body, cfg.exit));
+    assert_eq!(dominators.idom(ENTRY), None);
+}
+
+#[test]
+fn exports_graphviz_and_json() {
+    let program =
+        lower(&parse_program("procedure p(s) write \"say \\\"hi\\\"\" end; call p(1) ").unwrap());
+    let cfgs: Vec<Cfg> = program.functions().map(Cfg::new).collect();
+
+    let dot = cfg::to_dot(&cfgs, true);
+    assert!(dot.contains("subgraph \"cluster_p\""));
+    assert!(
+        dot.contains(r#""p.B0" [label = "B0 (entry):\l  write \"say \\\"hi\\\"\"\l  writeln\l"];"#)
+    );
+    assert!(dot.contains(r#""main.B0" -> "main.B1" [style = dashed"#));
+
+    let json = cfg::to_json(&cfgs);
+    assert_eq!(json[1]["name"], "main");
+    assert_eq!(json[1]["exit"], 1);
+    assert_eq!(json[1]["blocks"][0]["instrs"][0]["text"], "call p(1)");
+    assert_eq!(json[1]["blocks"][0]["terminator"], "goto B1");
+    assert_eq!(json[1]["blocks"][1]["idom"], 0);
+    assert!(json[1]["blocks"][1]["span"].is_null());
+}
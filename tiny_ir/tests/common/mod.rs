@@ -0,0 +1,58 @@
+//! Helpers shared by the tests of the passes. Not every test file uses all of them.
+#![allow(dead_code)]
+
+use tiny_interpreter::Interpreter;
+use tiny_ir::cfg::Cfg;
+use tiny_ir::{exec, lower, Program};
+use tiny_parser::ast::{self, parse_program};
+
+/// What a program writes, then its run-time error if it has one.
+pub type Run = (String, Result<(), String>);
+
+pub fn main_cfg(src: &str) -> Cfg {
+    Cfg::new(&lower(&parse_program(src).unwrap()).main)
+}
+
+/// Runs `program` with the interpreter.
+pub fn interpret(program: &ast::Program, input: &[i64]) -> Run {
+    let mut output = vec![];
+    let result = Interpreter::new()
+        .run(program, &mut input.iter().cloned(), &mut output)
+        .map_err(|err| err.to_string());
+    (String::from_utf8(output).unwrap(), result)
+}
+
+/// Runs the three-address code `program`.
+pub fn execute(program: &Program, input: &[i64]) -> Run {
+    let mut output = vec![];
+    let result = exec::run(program, &mut input.iter().cloned(), &mut output)
+        .map(|_| ())
+        .map_err(|err| err.to_string());
+    (String::from_utf8(output).unwrap(), result)
+}
+
+/// A pass over a whole program, named for the assertion messages.
+pub type Pass<'a> = (&'a str, fn(&Program) -> Program);
+
+/// Checks that the lowered program runs like the interpreter runs `src` after
+/// each of the named `passes`, with each of `inputs`.
+pub fn assert_runs_alike(src: &str, inputs: &[&[i64]], passes: &[Pass]) {
+    let program = parse_program(src).unwrap();
+    let lowered = lower(&program);
+    let passed: Vec<(&str, Program)> = passes
+        .iter()
+        .map(|(name, pass)| (*name, pass(&lowered)))
+        .collect();
+    for input in inputs {
+        let interpreted = interpret(&program, input);
+        for (name, program) in &passed {
+            assert_eq!(
+                execute(program, input),
+                interpreted,
+                "{} with input {:?}",
+                name,
+                input
+            );
+        }
+    }
+}
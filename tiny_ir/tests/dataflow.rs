@@ -0,0 +1,151 @@
+mod common;
+
+use common::main_cfg;
+use std::collections::BTreeSet;
+use tiny_ir::cfg::ENTRY;
+use tiny_ir::dataflow::{
+    solve, AvailableExpressions, Definition, Expr, Liveness, Point, ReachingDefinitions,
+};
+use tiny_ir::lint::warnings;
+use tiny_ir::{lower, Operand, Var};
+use tiny_parser::ast::{parse_program, BinOp};
+
+fn named(names: &[&str]) -> BTreeSet<Var> {
+    names
+        .iter()
+        .map(|name| Var::Named(name.to_string()))
+        .collect()
+}
+
+/// The messages of the warnings for `src`, each after its span.
+fn lint(src: &str) -> Vec<String> {
+    warnings(&lower(&parse_program(src).unwrap()))
+        .iter()
+        .map(|warning| {
+            let first_line = warning.message.lines().next().unwrap();
+            format!("{}..{}: {}", warning.span.0, warning.span.1, first_line)
+        })
+        .collect()
+}
+
+#[test]
+fn liveness_flows_backward_through_loops() {
+    // B0 reads, B1 is the loop header, B2 its body, B3 after the loop.
+    let cfg = main_cfg("read n; s := 0; while n > 0 do s := s + n; n := n - 1 end; write s ");
+    let liveness = Liveness {
+        globals: named(&["n", "s"]),
+        live_at_exit: BTreeSet::new(),
+    };
+    let solution = solve(&cfg, &liveness);
+    assert_eq!(solution.before[ENTRY], BTreeSet::new());
+    assert_eq!(solution.after[ENTRY], named(&["n", "s"]));
+    assert_eq!(solution.before[3], named(&["s"]));
+    assert_eq!(solution.after[3], BTreeSet::new());
+
+    // `n` is dead right before `read n` and live right after it.
+    let points = solution.points(&liveness, &cfg, ENTRY);
+    assert_eq!(points.len(), cfg.blocks[ENTRY].instrs.len() + 1);
+    assert!(!points[0].contains(&Var::Named("n".to_string())));
+    assert!(points[1].contains(&Var::Named("n".to_string())));
+}
+
+#[test]
+fn calls_use_every_global() {
+    let cfg = main_cfg("procedure p() write x end; x := 1; call p() ");
+    let liveness = Liveness {
+        globals: named(&["x"]),
+        live_at_exit: BTreeSet::new(),
+    };
+    let solution = solve(&cfg, &liveness);
+    let points = solution.points(&liveness, &cfg, ENTRY);
+    assert_eq!(points[1], named(&["x"]));
+}
+
+#[test]
+fn reaching_definitions_merge_at_joins() {
+    let cfg = main_cfg("read x; if x then y := 1 else y := 2 end; write y ");
+    let reaching = ReachingDefinitions {
+        globals: named(&["x", "y"]),
+        defined_at_entry: named(&["y"]),
+    };
+    let solution = solve(&cfg, &reaching);
+    let y = |block, index| {
+        (
+            Var::Named("y".to_string()),
+            Definition::At(Point { block, index }),
+        )
+    };
+    let join = &solution.before[3];
+    assert!(join.contains(&y(1, 0)));
+    assert!(join.contains(&y(2, 0)));
+    assert!(!join.contains(&(Var::Named("y".to_string()), Definition::Entry)));
+    assert!(solution.after[ENTRY].contains(&(Var::Named("y".to_string()), Definition::Entry)));
+}
+
+#[test]
+fn available_expressions_need_every_path() {
+    let cfg = main_cfg(
+        "read a; read b;
+x := a + b;
+if x then y := a + b else a := 1 end;
+z := a + b;
+w := -b ",
+    );
+    let analysis = AvailableExpressions::new(&cfg, named(&["a", "b", "w", "x", "y", "z"]));
+    let a_plus_b = Expr::BinOp(
+        BinOp::Add,
+        Operand::Var(Var::Named("a".to_string())),
+        Operand::Var(Var::Named("b".to_string())),
+    );
+    let index = analysis.exprs.iter().position(|e| *e == a_plus_b).unwrap();
+    let solution = solve(&cfg, &analysis);
+    // Available in the `then` part, but `a` changes in the `else` part.
+    assert!(solution.before[1].contains(&index));
+    assert!(!solution.after[2].contains(&index));
+    assert!(!solution.before[3].contains(&index));
+    assert!(solution.after[3].contains(&index));
+    assert_eq!(analysis.exprs.len(), 2);
+}
+
+#[test]
+fn warns_about_values_that_are_never_used() {
+    assert_eq!(
+        lint("x := 1; x := 2; write x "),
+        vec!["5..6: The value assigned to 'x' here is never used."]
+    );
+    // Both halves of a boolean value are one warning, and `read` consumes input
+    // whether or not the value is used.
+    assert_eq!(
+        lint("read x; read y; b := x or y "),
+        vec!["21..27: The value assigned to 'b' here is never used."]
+    );
+    // Parameters are copies, globals are left to the caller.
+    assert_eq!(
+        lint("procedure p(n) n := n + 1; g := 1 end; call p(1) "),
+        vec!["20..25: The value assigned to 'n' here is never used."]
+    );
+    // A call may read anything assigned before it.
+    assert!(lint("procedure p() write x end; x := 1; call p() ").is_empty());
+    assert!(lint("read n; repeat n := n - 1 until n = 0 ").is_empty());
+}
+
+#[test]
+fn warns_about_reads_before_assignments() {
+    assert_eq!(
+        lint("write x "),
+        vec!["6..7: 'x' is read before anything is assigned to it, so it's 0 here."]
+    );
+    assert_eq!(
+        lint("read c; if c then x := 1 end; write x "),
+        vec!["36..37: 'x' may be read before anything is assigned to it, and then it's 0."]
+    );
+    // Conditions are reads as well.
+    assert_eq!(
+        lint("while i < 3 do i := 1 end "),
+        vec!["6..11: 'i' may be read before anything is assigned to it, and then it's 0."]
+    );
+    // Procedures are called with what their callers assigned, and may assign
+    // anything themselves.
+    assert!(lint("procedure p() write g end; g := 1; call p() ").is_empty());
+    assert!(lint("procedure init() x := 1 end; call init(); write x ").is_empty());
+}
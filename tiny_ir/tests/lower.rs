@@ -0,0 +1,134 @@
+mod common;
+
+use common::execute;
+use tiny_ir::{lower, Program};
+use tiny_parser::ast::parse_program;
+
+fn dump(src: &str) -> String {
+    lower(&parse_program(src).unwrap()).to_string()
+}
+
+fn assert_runs_alike(src: &str, inputs: &[&[i64]]) {
+    common::assert_runs_alike(src, inputs, &[("lowered", Program::clone)]);
+}
+
+#[test]
+fn dumps_one_instruction_per_line() {
+    assert_eq!(
+        dump("array a[2]; procedure p(n) write \"n=\", n end; read x; a[x] := -x * 2 + 1; call p(a[0]) "),
+        "array a[2]
+
+procedure p(n):
+  write \"n=\"
+  write n
+  writeln
+
+main:
+  read x
+  %2 := -x
+  %1 := %2 * 2
+  %0 := %1 + 1
+  a[x] := %0
+  %3 := a[0]
+  call p(%3)
+"
+    );
+}
+
+#[test]
+fn conditions_become_jumps() {
+    assert_eq!(
+        dump("while x < 3 and not (y = 0) do x := x + 1 end "),
+        "main:
+L0:
+  %0 := x < 3
+  ifz %0 goto L1
+  %1 := y = 0
+  ifnz %1 goto L1
+  x := x + 1
+  goto L0
+L1:
+"
+    );
+    assert_eq!(
+        dump("if x then write 1 elsif y or z then write 2 end "),
+        "main:
+  ifz x goto L1
+  write 1
+  writeln
+  goto L0
+L1:
+  ifnz y goto L3
+  ifz z goto L2
+L3:
+  write 2
+  writeln
+L2:
+L0:
+"
+    );
+}
+
+#[test]
+fn boolean_values_are_computed_with_jumps() {
+    assert_eq!(
+        dump("b := x and y "),
+        "main:
+  ifz x goto L0
+  ifz y goto L0
+  b := 1
+  goto L1
+L0:
+  b := 0
+L1:
+"
+    );
+}
+
+#[test]
+fn runs_like_the_interpreter() {
+    let factorial = "read x;
+if 0 < x then
+  fact := 1;
+  repeat
+    fact := fact * x;
+    x := x - 1
+  until x = 0;
+  write fact
+end ";
+    assert_runs_alike(factorial, &[&[5], &[0], &[21]]);
+
+    let procedures = "procedure fib(n)
+  if n < 2 then r := n
+  else call fib(n - 1); t := r; call fib(n - 2); r := r + t end
+end;
+read n; call fib(n); write \"fib = \", r ";
+    assert_runs_alike(procedures, &[&[0], &[1], &[10]]);
+
+    let arrays = "array a[4]; read n; i := 0;
+while i < n do read a[i]; i := i + 1 end;
+repeat i := i - 1; write i, \": \", a[i] until i <= 0 ";
+    assert_runs_alike(arrays, &[&[2, 7, 8], &[4, 1, 2, 3, 4]]);
+
+    let booleans = "read x;
+b := x <> 0 and 10 / x > 1; c := not (x < 0 or x > 5); d := not x;
+if x = 1 then write \"one\" elsif x = 2 then write \"two\" else write b, c, d end ";
+    assert_runs_alike(booleans, &[&[0], &[1], &[2], &[3], &[-4], &[20]]);
+}
+
+#[test]
+fn fails_like_the_interpreter() {
+    let arrays = "array a[4]; read n; i := 0;
+while i < n do read a[i]; i := i + 1 end;
+write a[n - 5] ";
+    // Out of bounds on writing, on reading and then running out of input.
+    assert_runs_alike(arrays, &[&[5, 1, 2, 3, 4, 5], &[3, 1, 2, 3], &[2, 1]]);
+
+    let program = lower(&parse_program("read x; write 1; write 2 / x ").unwrap());
+    let (output, result) = execute(&program, &[0]);
+    assert_eq!(output, "1\n");
+    assert!(result.unwrap_err().contains("Division by zero."));
+    assert_runs_alike("read x; write 1; write 2 / x, 3 ", &[&[0], &[1]]);
+    assert_runs_alike("read x; read y ", &[&[1]]);
+    assert_runs_alike("procedure p(n) call p(n + 1) end; call p(0) ", &[&[]]);
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use tiny_ir::opt::optimise;
+use tiny_ir::ssa::{memory_vars, Ssa};
+use tiny_ir::{lower, Program};
+use tiny_parser::ast::parse_program;
+
+fn ssa_dump(src: &str) -> String {
+    let program = lower(&parse_program(src).unwrap());
+    Ssa::new(&program.main, &memory_vars(&program)).to_string()
+}
+
+fn optimised_dump(src: &str) -> String {
+    optimise(&lower(&parse_program(src).unwrap())).to_string()
+}
+
+/// The program in and back out of SSA form, without optimising it.
+fn round_trip(program: &Program) -> Program {
+    let memory = memory_vars(program);
+    let convert = |function| Ssa::new(function, &memory).to_function();
+    Program {
+        arrays: program.arrays.clone(),
+        procedures: program.procedures.iter().map(convert).collect(),
+        main: convert(&program.main),
+    }
+}
+
+fn assert_runs_alike(src: &str, inputs: &[&[i64]]) {
+    common::assert_runs_alike(
+        src,
+        inputs,
+        &[("round trip", round_trip), ("optimised", optimise)],
+    );
+}
+
+#[test]
+fn places_phis_where_assignments_meet() {
+    assert_eq!(
+        ssa_dump("read x; if x < 0 then x := -x end; while x > 0 do x := x - 2 end; write x "),
+        "main():
+B0 (entry):
+  read x.1
+  %0.1 := x.1 < 0
+  if %0.1 goto B1 else B2
+B1: preds B0
+  x.2 := -x.1
+  goto B2
+B2: preds B0, B1, B3
+  x.3 := phi(B0: x.1, B1: x.2, B3: x.4)
+  %1.1 := x.3 > 0
+  if %1.1 goto B3 else B4
+B3: preds B2
+  x.4 := x.3 - 2
+  goto B2
+B4: preds B2
+  write x.3
+  writeln
+  goto B5
+B5 (exit): preds B4
+  return
+"
+    );
+}
+
+#[test]
+fn leaves_globals_of_procedures_in_memory() {
+    let dump = ssa_dump("procedure p() g := g + 1 end; g := 1; call p(); x := g; write x, g ");
+    assert!(
+        dump.contains("  g := 1\n  call p()\n  x.1 := g\n"),
+        "{}",
+        dump
+    );
+}
+
+#[test]
+fn folds_constants_through_variables() {
+    assert_eq!(
+        optimised_dump("x := 2; y := x * 3; write y "),
+        "main:\n  write 6\n  writeln\n"
+    );
+    // The globals of the main program start out as 0.
+    assert_eq!(
+        optimised_dump("y := x + 1; write y "),
+        "main:\n  write 1\n  writeln\n"
+    );
+}
+
+#[test]
+fn removes_branches_that_are_never_taken() {
+    assert_eq!(
+        optimised_dump(
+            "x := 1;
+if x = 1 then write \"one\" else write \"other\" end;
+while x > 5 do x := x - 1 end;
+write x "
+        ),
+        "main:
+  write \"one\"
+  writeln
+  write 1
+  writeln
+"
+    );
+}
+
+#[test]
+fn keeps_what_can_fail_or_has_effects() {
+    let dump =
+        optimised_dump("array a[2]; read y; z := y * 0; w := 10 / y; v := a[y]; u := 1 / 0 ");
+    assert_eq!(
+        dump,
+        "array a[2]
+
+main:
+  read %0
+  %1 := 10 / %0
+  %2 := a[%0]
+  %3 := 1 / 0
+"
+    );
+}
+
+#[test]
+fn runs_like_the_interpreter() {
+    let factorial = "read x;
+if 0 < x then
+  fact := 1;
+  repeat
+    fact := fact * x;
+    x := x - 1
+  until x = 0;
+  write fact
+end ";
+    assert_runs_alike(factorial, &[&[5], &[0], &[21]]);
+
+    let procedures = "procedure fib(n)
+  if n < 2 then r := n
+  else call fib(n - 1); t := r; call fib(n - 2); r := r + t end
+end;
+read n; call fib(n); write \"fib = \", r ";
+    assert_runs_alike(procedures, &[&[0], &[1], &[10]]);
+
+    let arrays = "array a[4]; read n; i := 0;
+while i < n do read a[i]; i := i + 1 end;
+repeat i := i - 1; write i, \": \", a[i] until i <= 0 ";
+    assert_runs_alike(arrays, &[&[2, 7, 8], &[4, 1, 2, 3, 4]]);
+
+    let booleans = "read x;
+b := x <> 0 and 10 / x > 1; c := not (x < 0 or x > 5); d := not x;
+if x = 1 then write \"one\" elsif x = 2 then write \"two\" else write b, c, d end ";
+    assert_runs_alike(booleans, &[&[0], &[1], &[2], &[3], &[-4], &[20]]);
+
+    let swaps = "read x; read y; i := 0;
+while i < 3 do t := x; x := y; y := t; i := i + 1 end;
+write x, y ";
+    assert_runs_alike(swaps, &[&[1, 2]]);
+}
+
+#[test]
+fn fails_like_the_interpreter() {
+    let arrays = "array a[4]; read n; i := 0;
+while i < n do read a[i]; i := i + 1 end;
+write a[n - 5] ";
+    // Out of bounds on writing, on reading and then running out of input.
+    assert_runs_alike(arrays, &[&[5, 1, 2, 3, 4, 5], &[3, 1, 2, 3], &[2, 1]]);
+
+    assert_runs_alike("read x; write 1; y := 2 / x; write 3 ", &[&[0], &[1]]);
+    assert_runs_alike("x := 0; write 1; y := 2 / x ", &[&[]]);
+    assert_runs_alike("read x; read y ", &[&[1]]);
+}
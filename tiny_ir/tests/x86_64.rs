@@ -0,0 +1,161 @@
+mod common;
+
+use common::{interpret, Run};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tiny_ir::opt::optimise;
+use tiny_ir::{lower, x86_64, Program};
+use tiny_parser::ast::parse_program;
+
+/// Builds `program` with the system's C compiler, or `None` if there isn't one.
+fn build(program: &Program) -> Option<PathBuf> {
+    static BUILDS: AtomicUsize = AtomicUsize::new(0);
+    let name = format!(
+        "tiny-x86_64-{}-{}",
+        std::process::id(),
+        BUILDS.fetch_add(1, Ordering::SeqCst)
+    );
+    let (asm, exe) = (
+        env::temp_dir().join(format!("{}.s", name)),
+        env::temp_dir().join(name),
+    );
+    fs::write(&asm, x86_64::emit(program)).unwrap();
+    let built = Command::new("cc")
+        .arg(&asm)
+        .arg("-o")
+        .arg(&exe)
+        .output()
+        .ok()?;
+    fs::remove_file(&asm).unwrap();
+    assert!(
+        built.status.success(),
+        "{}",
+        String::from_utf8_lossy(&built.stderr)
+    );
+    Some(exe)
+}
+
+fn run_native(exe: &PathBuf, input: &[i64]) -> Run {
+    let mut child = Command::new(exe)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input: Vec<String> = input.iter().map(ToString::to_string).collect();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.join("\n").as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let result = if output.status.success() {
+        assert_eq!(stderr, "");
+        Ok(())
+    } else {
+        assert_eq!(output.status.code(), Some(1));
+        Err(stderr.trim_end_matches('\n').to_string())
+    };
+    (String::from_utf8(output.stdout).unwrap(), result)
+}
+
+/// Checks that the program runs natively like in the interpreter, before and
+/// after optimising it, with each of `inputs`.
+fn assert_runs_alike(src: &str, inputs: &[&[i64]]) {
+    let program = parse_program(src).unwrap();
+    let lowered = lower(&program);
+    let exes = match (build(&lowered), build(&optimise(&lowered))) {
+        (Some(exe), Some(optimised)) => [exe, optimised],
+        _ => return eprintln!("Skipped, as there's no cc to build with."),
+    };
+    for input in inputs {
+        let interpreted = interpret(&program, input);
+        for exe in &exes {
+            assert_eq!(
+                run_native(exe, input),
+                interpreted,
+                "{} with input {:?}",
+                exe.display(),
+                input
+            );
+        }
+    }
+    for exe in &exes {
+        fs::remove_file(exe).unwrap();
+    }
+}
+
+#[test]
+fn emits_a_main_for_the_c_library() {
+    let asm = x86_64::emit(&lower(&parse_program("read x; write x ").unwrap()));
+    assert!(asm.contains("\t.globl main\n"));
+    assert!(asm.contains("\t.lcomm var_x, 8\n"));
+    assert!(asm.contains("\tcall scanf@PLT\n"));
+}
+
+#[test]
+fn runs_like_the_interpreter() {
+    let factorial = "read x;
+if 0 < x then
+  fact := 1;
+  repeat
+    fact := fact * x;
+    x := x - 1
+  until x = 0;
+  write fact
+end ";
+    assert_runs_alike(factorial, &[&[5], &[0], &[21]]);
+
+    let procedures = "procedure fib(n)
+  if n < 2 then r := n
+  else call fib(n - 1); t := r; call fib(n - 2); r := r + t end
+end;
+procedure sum(a, b, c) write a + b + c; a := 0; write a end;
+read n; call fib(n); write \"fib = \", r; call sum(n, 2, 3); write n ";
+    assert_runs_alike(procedures, &[&[0], &[1], &[10]]);
+
+    let arrays = "array a[4]; read n; i := 0;
+while i < n do read a[i]; i := i + 1 end;
+repeat i := i - 1; write i, \": \", a[i] until i <= 0 ";
+    assert_runs_alike(arrays, &[&[2, 7, 8], &[4, 1, 2, 3, 4]]);
+
+    let booleans = "read x;
+b := x <> 0 and 10 / x > 1; c := not (x < 0 or x > 5); d := not x;
+if x = 1 then write \"one\" elsif x = 2 then write \"two\" else write b, c, d end ";
+    assert_runs_alike(booleans, &[&[0], &[1], &[2], &[3], &[-4], &[20]]);
+
+    let arithmetic = "read x; read y;
+write x + y, \" \", x - y, \" \", x * y, \" \", x / y, \" \", -x;
+write x < y, x <= y, x > y, x >= y, x = y, x <> y ";
+    assert_runs_alike(
+        arithmetic,
+        &[
+            &[7, 2],
+            &[-7, 2],
+            &[2, 2],
+            &[9223372036854775807, 2],
+            &[-9223372036854775808, -1],
+        ],
+    );
+
+    assert_runs_alike("write \"\\\"100%\\\" \\\\ done\" ", &[&[]]);
+}
+
+#[test]
+fn fails_like_the_interpreter() {
+    let arrays = "array a[4]; read n; i := 0;
+while i < n do read a[i]; i := i + 1 end;
+write a[n - 5] ";
+    // Out of bounds on writing, on reading and then running out of input.
+    assert_runs_alike(arrays, &[&[5, 1, 2, 3, 4, 5], &[3, 1, 2, 3], &[2, 1]]);
+
+    assert_runs_alike("read x; write 1; write 2 / x, 3 ", &[&[0], &[1]]);
+    assert_runs_alike("read x; read y ", &[&[1]]);
+    assert_runs_alike("procedure p(n) call p(n + 1) end; call p(0) ", &[&[]]);
+}
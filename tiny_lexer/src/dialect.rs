@@ -0,0 +1,152 @@
+//! The language extensions the lexer and parser accept on top of textbook TINY.
+
+/// Which extensions are switched on. `Dialect::textbook()` (the default) is the
+/// language as in the book, `Dialect::extended()` is the course language.
+///
+/// The extended dialect doesn't take every textbook program as it is: it reserves
+/// `while`, `do`, `elsif`, `elseif`, `and`, `or`, `not`, `procedure`, `call` and
+/// `array`, and reads `IF` and `Then` as keywords, so a textbook program naming a
+/// variable `do` or `IF` only parses in `Dialect::textbook()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    /// `<=`, `>`, `>=` and `<>` besides `<` and `=`.
+    pub comparison_ops: bool,
+    /// `while ... do ... end` loops.
+    pub while_loops: bool,
+    /// `elsif` (or `elseif`) branches in `if` statements.
+    pub elsif: bool,
+    /// `and`, `or` and `not`.
+    pub boolean_ops: bool,
+    /// Unary `-` and `+`.
+    pub unary_ops: bool,
+    /// `procedure` declarations and `call` statements.
+    pub procedures: bool,
+    /// `array` declarations and `[]` indexing.
+    pub arrays: bool,
+    /// String literals, and comma-separated lists of them and expressions in `write`.
+    pub strings: bool,
+    /// `IF`, `Then` and so on are keywords too. Without it, `lexer::warnings` points out
+    /// identifiers that would be.
+    pub case_insensitive_keywords: bool,
+    /// `Total` and `total` name the same variable. Off in both presets, as it
+    /// changes what existing programs mean.
+    pub case_insensitive_identifiers: bool,
+    /// `//` comments running to the end of the line.
+    pub line_comments: bool,
+    /// `{ a { b } c }` is a single comment rather than one ending at the first `}`.
+    pub nested_comments: bool,
+    /// Identifiers like `x1`, `total_sum` and `_tmp`: digits after the first
+    /// character, and `_` anywhere. Without it, `lexer::warnings` points out
+    /// identifiers directly followed by digits.
+    pub identifier_digits: bool,
+    /// `0xFF` and `0b1010` number literals, and `_` between digits as in `1_000`.
+    pub extended_numbers: bool,
+    /// The integers that number literals have to fit in.
+    pub int_width: IntWidth,
+}
+
+/// The width of a signed integer: 16 or 32 bits like the `int` of the TM machine
+/// the textbook compiles for, or 64 like the interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntWidth {
+    Bits16,
+    Bits32,
+    Bits64,
+}
+
+impl Dialect {
+    pub fn textbook() -> Dialect {
+        Dialect {
+            comparison_ops: false,
+            while_loops: false,
+            elsif: false,
+            boolean_ops: false,
+            unary_ops: false,
+            procedures: false,
+            arrays: false,
+            strings: false,
+            case_insensitive_keywords: false,
+            case_insensitive_identifiers: false,
+            line_comments: false,
+            nested_comments: false,
+            identifier_digits: false,
+            extended_numbers: false,
+            int_width: IntWidth::Bits32,
+        }
+    }
+
+    /// Every extension but case-insensitive identifiers. The server, the wasm
+    /// bindings and the `tiny` command read programs in this dialect.
+    pub fn extended() -> Dialect {
+        Dialect {
+            comparison_ops: true,
+            while_loops: true,
+            elsif: true,
+            boolean_ops: true,
+            unary_ops: true,
+            procedures: true,
+            arrays: true,
+            strings: true,
+            case_insensitive_keywords: true,
+            case_insensitive_identifiers: false,
+            line_comments: true,
+            nested_comments: true,
+            identifier_digits: true,
+            extended_numbers: true,
+            int_width: IntWidth::Bits64,
+        }
+    }
+
+    /// Whether `word` is reserved, given the extensions that bring keywords along.
+    pub fn is_keyword(&self, word: &str) -> bool {
+        let lowercase;
+        let word = if self.case_insensitive_keywords {
+            lowercase = word.to_ascii_lowercase();
+            &lowercase
+        } else {
+            word
+        };
+        match word {
+            "if" | "then" | "else" | "repeat" | "end" | "until" | "read" | "write" => true,
+            "while" | "do" => self.while_loops,
+            "elsif" | "elseif" => self.elsif,
+            "and" | "or" | "not" => self.boolean_ops,
+            "procedure" | "call" => self.procedures,
+            "array" => self.arrays,
+            _ => false,
+        }
+    }
+
+    /// The name `identifier` stands for, the same for all of its spellings.
+    pub fn identifier_name(&self, identifier: &str) -> String {
+        if self.case_insensitive_identifiers {
+            identifier.to_lowercase()
+        } else {
+            identifier.to_string()
+        }
+    }
+}
+
+impl IntWidth {
+    pub fn bits(self) -> u32 {
+        match self {
+            IntWidth::Bits16 => 16,
+            IntWidth::Bits32 => 32,
+            IntWidth::Bits64 => 64,
+        }
+    }
+
+    pub fn min(self) -> i64 {
+        i64::MIN >> (64 - self.bits())
+    }
+
+    pub fn max(self) -> i64 {
+        i64::MAX >> (64 - self.bits())
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect::textbook()
+    }
+}
@@ -1,28 +1,49 @@
+use crate::dialect::Dialect;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug)]
 enum State {
     START,
     INCOMMENT,
+    INLINECOMMENT,
+    INSLASH,
     INNUM,
     INID,
     INASSIGN,
+    INLESS,
+    INGREATER,
+    INSTRING,
+    INESCAPE,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 enum TokenType {
     RESERVED,
     IDENTIFIER,
     NUMBER,
     COMMENT,
     SYMBOL,
+    LBRACKET,
+    RBRACKET,
+    STRING,
 }
 
 //TODO: use crates.io/crates/shrinkwraprs
-#[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Token {
     RESERVED(Span),
     IDENTIFIER(Span),
     NUMBER(Span),
     COMMENT(Span),
     SYMBOL(Span),
+    /// `[`, opening an array index or size.
+    LBRACKET(Span),
+    /// `]`
+    RBRACKET(Span),
+    /// A double-quoted string literal, quotes included. One that isn't closed
+    /// before the end of its line still makes a token; see `string_value`.
+    STRING(Span),
 }
 
 impl Token {
@@ -32,130 +53,486 @@ impl Token {
             | Token::IDENTIFIER(span)
             | Token::NUMBER(span)
             | Token::COMMENT(span)
-            | Token::SYMBOL(span) => *span,
+            | Token::SYMBOL(span)
+            | Token::LBRACKET(span)
+            | Token::RBRACKET(span)
+            | Token::STRING(span) => *span,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Token::RESERVED(_) => "RESERVED",
+            Token::IDENTIFIER(_) => "IDENTIFIER",
+            Token::NUMBER(_) => "NUMBER",
+            Token::COMMENT(_) => "COMMENT",
+            Token::SYMBOL(_) => "SYMBOL",
+            Token::LBRACKET(_) => "LBRACKET",
+            Token::RBRACKET(_) => "RBRACKET",
+            Token::STRING(_) => "STRING",
+        }
+    }
+
+    pub fn with_span(&self, span: Span) -> Token {
+        match self {
+            Token::RESERVED(_) => Token::RESERVED(span),
+            Token::IDENTIFIER(_) => Token::IDENTIFIER(span),
+            Token::NUMBER(_) => Token::NUMBER(span),
+            Token::COMMENT(_) => Token::COMMENT(span),
+            Token::SYMBOL(_) => Token::SYMBOL(span),
+            Token::LBRACKET(_) => Token::LBRACKET(span),
+            Token::RBRACKET(_) => Token::RBRACKET(span),
+            Token::STRING(_) => Token::STRING(span),
         }
     }
 }
 
 pub type Span = (usize, usize);
 
-pub fn tokenize<'a>(file_contents: &'a str, comments_allowed: bool) -> Vec<Token> {
-    //Need a hotfix for the ' '
-    let mut char_iter = file_contents.chars();
+/// A token the lexer could delimit but whose contents are malformed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Lexical Error at {}..{}:\n{}",
+            self.span.0, self.span.1, self.message
+        )
+    }
+}
+
+/// Source that lexes fine but probably doesn't mean what it says.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexWarning {
+    pub span: Span,
+    pub message: String,
+}
+
+impl std::fmt::Display for LexWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Warning at {}..{}:\n{}",
+            self.span.0, self.span.1, self.message
+        )
+    }
+}
+
+/// Lexes `file_contents` as textbook TINY; `tokenize_with` takes the extensions.
+///
+/// Signs are never part of a `NUMBER` token: both `3-1` and `-1` lex the `-` as a
+/// `SYMBOL`, and the parser tells subtraction from negation by where it appears.
+pub fn tokenize(file_contents: &str, comments_allowed: bool) -> Vec<Token> {
+    tokenize_with(file_contents, comments_allowed, &Dialect::default())
+}
+
+/// Same as `tokenize`, for the given dialect. The keywords of switched off
+/// extensions are identifiers, and their symbols are lexed one character at a time.
+pub fn tokenize_with(file_contents: &str, comments_allowed: bool, dialect: &Dialect) -> Vec<Token> {
+    tokenize_from(file_contents, 0, comments_allowed, dialect, |_| false).0
+}
+
+/// Lexes `file_contents` starting at byte `start`, which has to be a point where the
+/// lexer is in its `START` state (the beginning of the file or of a non-comment token).
+///
+/// Before a token is emitted, `resync` is asked about its starting offset; if it
+/// returns true, lexing stops there and that offset is returned with the tokens
+/// lexed so far. This lets callers splice previously lexed tokens back in after an edit.
+pub fn tokenize_from<F>(
+    file_contents: &str,
+    start: usize,
+    comments_allowed: bool,
+    dialect: &Dialect,
+    mut resync: F,
+) -> (Vec<Token>, Option<usize>)
+where
+    F: FnMut(usize) -> bool,
+{
+    // `at` is where the current character starts and `after` where it ends, so
+    // spans always lie on character boundaries, whatever the encoded lengths.
+    let mut char_iter = file_contents[start..]
+        .char_indices()
+        .map(|(i, c)| (start + i, c));
     let mut curr_char = char_iter.next();
     let mut curr_state = State::START;
     let mut tokens: Vec<Token> = Vec::new();
-    let (mut curr_index, mut curr_offset) = (0, 1);
+    let mut curr_index = start;
+    let mut checked = 0;
+    // How many comments the one being lexed is nested in.
+    let mut comment_depth = 0;
+
+    while let Some((at, c)) = curr_char {
+        if let Some(i) = find_resync(&tokens, &mut checked, &mut resync) {
+            let resynced_at = tokens[i].get_span().0;
+            tokens.truncate(i);
+            return (tokens, Some(resynced_at));
+        }
 
-    while curr_char.is_some() {
-        let c = curr_char.unwrap();
+        let after = at + c.len_utf8();
         match curr_state {
             State::START => {
                 if c.is_ascii_whitespace() {
                     curr_char = char_iter.next();
-                    curr_index += c.len_utf8();
-                    curr_offset += c.len_utf8();
+                    curr_index = after;
                 } else if c == '{' {
                     curr_state = State::INCOMMENT;
+                    comment_depth = 0;
                     curr_char = char_iter.next();
                     if comments_allowed {
-                        add_token((curr_index, curr_offset), TokenType::SYMBOL, &mut tokens);
+                        add_token((at, after), TokenType::SYMBOL, &mut tokens);
                     }
-                    curr_index += c.len_utf8();
-                } else if c.is_alphabetic() {
+                    curr_index = after;
+                } else if is_identifier_start(c, dialect) {
                     curr_state = State::INID;
                     curr_char = char_iter.next();
-                } else if c.is_digit(10) {
+                } else if c.is_ascii_digit() {
                     curr_state = State::INNUM;
                     curr_char = char_iter.next();
                 } else if c == ':' {
                     curr_state = State::INASSIGN;
                     curr_char = char_iter.next();
+                } else if c == '<' && dialect.comparison_ops {
+                    curr_state = State::INLESS;
+                    curr_char = char_iter.next();
+                } else if c == '>' && dialect.comparison_ops {
+                    curr_state = State::INGREATER;
+                    curr_char = char_iter.next();
+                } else if c == '"' && dialect.strings {
+                    curr_state = State::INSTRING;
+                    curr_char = char_iter.next();
+                } else if c == '/' && dialect.line_comments {
+                    curr_state = State::INSLASH;
+                    curr_char = char_iter.next();
                 } else {
-                    add_token((curr_index, curr_offset), TokenType::SYMBOL, &mut tokens);
-                    curr_index = curr_offset;
-                    curr_offset += c.len_utf8();
+                    let tok_type = match c {
+                        '[' if dialect.arrays => TokenType::LBRACKET,
+                        ']' if dialect.arrays => TokenType::RBRACKET,
+                        _ => TokenType::SYMBOL,
+                    };
+                    add_token((at, after), tok_type, &mut tokens);
+                    curr_index = after;
                     curr_char = char_iter.next();
                 }
             }
             State::INCOMMENT => {
-                if c == '}' {
+                if c == '{' && dialect.nested_comments {
+                    comment_depth += 1;
+                } else if c == '}' && comment_depth > 0 {
+                    comment_depth -= 1;
+                } else if c == '}' {
                     if comments_allowed {
-                        add_token((curr_index, curr_offset), TokenType::COMMENT, &mut tokens);
+                        add_token((curr_index, at), TokenType::COMMENT, &mut tokens);
+                        add_token((at, after), TokenType::SYMBOL, &mut tokens);
                     }
-                    curr_index = curr_offset;
-                    curr_offset += c.len_utf8();
+                    curr_state = State::START;
+                    curr_index = after;
+                }
+                curr_char = char_iter.next();
+            }
+            State::INSLASH => {
+                if c == '/' {
                     if comments_allowed {
-                        add_token((curr_index, curr_offset), TokenType::SYMBOL, &mut tokens);
+                        add_token((curr_index, after), TokenType::SYMBOL, &mut tokens);
                     }
-
-                    curr_state = State::START;
+                    curr_index = after;
                     curr_char = char_iter.next();
-                    curr_index = curr_offset;
-                    curr_offset += c.len_utf8();
+                    curr_state = State::INLINECOMMENT;
+                } else {
+                    add_token((curr_index, at), TokenType::SYMBOL, &mut tokens);
+                    curr_index = at;
+                    curr_state = State::START;
+                }
+            }
+            State::INLINECOMMENT => {
+                if c == '\n' {
+                    if comments_allowed {
+                        add_token((curr_index, at), TokenType::COMMENT, &mut tokens);
+                    }
+                    curr_index = at;
+                    curr_state = State::START;
                 } else {
-                    curr_offset += c.len_utf8();
                     curr_char = char_iter.next();
-                    curr_state = State::INCOMMENT;
                 }
             }
             State::INNUM => {
-                if c.is_digit(10) {
-                    curr_offset += c.len_utf8();
+                // Letters are only taken in right after a leading `0`, and then as
+                // hexadecimal digits, so that `x<3then` still ends the number
+                // before `then`. Any decimal digit is taken, so that a malformed
+                // literal like `0b12` stays a single token.
+                let literal = &file_contents[curr_index..at];
+                let hex = literal.starts_with("0x") || literal.starts_with("0X");
+                if c.is_ascii_digit()
+                    || (dialect.extended_numbers
+                        && (c == '_'
+                            || (literal == "0" && matches!(c, 'x' | 'X' | 'b' | 'B'))
+                            || (hex && c.is_ascii_hexdigit())))
+                {
                     curr_char = char_iter.next();
                 } else {
-                    add_token((curr_index, curr_offset), TokenType::NUMBER, &mut tokens);
-                    curr_index = curr_offset;
-                    curr_offset += c.len_utf8();
+                    add_token((curr_index, at), TokenType::NUMBER, &mut tokens);
+                    curr_index = at;
                     curr_state = State::START;
                 }
             }
             State::INID => {
-                if c.is_alphabetic() {
-                    curr_offset += c.len_utf8();
+                if is_identifier_continue(c, dialect) {
                     curr_char = char_iter.next();
                 } else {
                     add_token(
-                        (curr_index, curr_offset),
-                        get_token_type((curr_index, curr_offset), file_contents),
+                        (curr_index, at),
+                        get_token_type((curr_index, at), file_contents, dialect),
                         &mut tokens,
                     );
-                    curr_index = curr_offset;
-                    curr_offset += c.len_utf8();
+                    curr_index = at;
                     curr_state = State::START;
                 }
             }
-            State::INASSIGN => {
+            State::INASSIGN | State::INGREATER => {
                 if c == '=' {
-                    curr_offset += c.len_utf8();
-                    add_token((curr_index, curr_offset), TokenType::SYMBOL, &mut tokens);
-
-                    curr_index = curr_offset;
-                    curr_offset += c.len_utf8();
+                    add_token((curr_index, after), TokenType::SYMBOL, &mut tokens);
+                    curr_index = after;
                     curr_char = char_iter.next();
-                    curr_state = State::START;
                 } else {
-                    add_token((curr_index, curr_offset), TokenType::SYMBOL, &mut tokens);
-                    curr_index = curr_offset;
-                    curr_offset += c.len_utf8();
+                    add_token((curr_index, at), TokenType::SYMBOL, &mut tokens);
+                    curr_index = at;
+                }
+                curr_state = State::START;
+            }
+            State::INLESS => {
+                if c == '=' || c == '>' {
+                    add_token((curr_index, after), TokenType::SYMBOL, &mut tokens);
+                    curr_index = after;
+                    curr_char = char_iter.next();
+                } else {
+                    add_token((curr_index, at), TokenType::SYMBOL, &mut tokens);
+                    curr_index = at;
+                }
+                curr_state = State::START;
+            }
+            State::INSTRING => {
+                if c == '"' {
+                    add_token((curr_index, after), TokenType::STRING, &mut tokens);
+                    curr_index = after;
+                    curr_char = char_iter.next();
+                    curr_state = State::START;
+                } else if c == '\n' {
+                    // Unterminated: end the literal with its line so that the
+                    // rest of the program still lexes as usual.
+                    add_token((curr_index, at), TokenType::STRING, &mut tokens);
+                    curr_index = at;
                     curr_state = State::START;
+                } else {
+                    if c == '\\' {
+                        curr_state = State::INESCAPE;
+                    }
+                    curr_char = char_iter.next();
                 }
             }
+            State::INESCAPE => {
+                curr_state = State::INSTRING;
+                if c != '\n' {
+                    curr_char = char_iter.next();
+                }
+            }
+        }
+    }
+    // The end of the file ends the token being lexed like any other character
+    // that can't be part of it, and a comment or string that was never closed
+    // runs up to it.
+    let end = file_contents.len();
+    match curr_state {
+        State::START => {}
+        State::INID => add_token(
+            (curr_index, end),
+            get_token_type((curr_index, end), file_contents, dialect),
+            &mut tokens,
+        ),
+        State::INNUM => add_token((curr_index, end), TokenType::NUMBER, &mut tokens),
+        State::INASSIGN | State::INLESS | State::INGREATER | State::INSLASH => {
+            add_token((curr_index, end), TokenType::SYMBOL, &mut tokens)
+        }
+        State::INCOMMENT | State::INLINECOMMENT => {
+            if comments_allowed {
+                add_token((curr_index, end), TokenType::COMMENT, &mut tokens);
+            }
+        }
+        State::INSTRING | State::INESCAPE => {
+            add_token((curr_index, end), TokenType::STRING, &mut tokens)
+        }
+    }
+    if let Some(i) = find_resync(&tokens, &mut checked, &mut resync) {
+        let resynced_at = tokens[i].get_span().0;
+        tokens.truncate(i);
+        return (tokens, Some(resynced_at));
+    }
+    (tokens, None)
+}
+
+/// The warnings for `tokens`, lexed from `file_contents` in `dialect`. With
+/// case-sensitive keywords, those are the identifiers that are keywords in
+/// another case, like `IF`, and without `Dialect::identifier_digits` the ones
+/// directly followed by a number, like `x1`.
+pub fn warnings(file_contents: &str, tokens: &[Token], dialect: &Dialect) -> Vec<LexWarning> {
+    let any_case = Dialect {
+        case_insensitive_keywords: true,
+        ..*dialect
+    };
+    let mut warnings = vec![];
+    for (i, tok) in tokens.iter().enumerate() {
+        if let Token::IDENTIFIER((i0, i1)) = *tok {
+            let word = &file_contents[i0..i1];
+            if !dialect.case_insensitive_keywords && any_case.is_keyword(word) {
+                warnings.push(LexWarning {
+                    span: (i0, i1),
+                    message: format!("Did you mean the keyword `{}`? Keywords are case-sensitive, so '{}' is an identifier.", word.to_ascii_lowercase(), word),
+                });
+            }
+            if let Some(Token::NUMBER((n0, n1))) = tokens.get(i + 1) {
+                if !dialect.identifier_digits && *n0 == i1 {
+                    warnings.push(LexWarning {
+                        span: (i0, *n1),
+                        message: format!("Identifiers can't contain digits, so '{}' is the identifier '{}' followed by the number '{}'.", &file_contents[i0..*n1], word, &file_contents[*n0..*n1]),
+                    });
+                }
+            }
+        }
+    }
+    warnings
+}
+
+/// The error for a `{` comment that `tokens`, lexed from `file_contents` in
+/// `dialect`, leave open at the end of the file. It spans from the `{` to the end.
+pub fn unterminated_comment(
+    file_contents: &str,
+    tokens: &[Token],
+    dialect: &Dialect,
+) -> Option<LexError> {
+    // Only comments and whitespace follow the last token, so lex them again with
+    // the comments kept. An open comment is the only way they end in one.
+    let tail = tokens.last().map_or(0, |tok| tok.get_span().1);
+    let comments = tokenize_from(file_contents, tail, true, dialect, |_| false).0;
+    match comments[..] {
+        [.., Token::SYMBOL((i0, i1)), Token::COMMENT(_)] if &file_contents[i0..i1] == "{" => {
+            Some(LexError {
+                span: (i0, file_contents.len()),
+                message: "Unterminated comment.\n\tSuggested Fix:\tClose the comment with a '}'."
+                    .to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The value of the number literal at `span` in `file_contents`: decimal, or with
+/// `Dialect::extended_numbers` hexadecimal after `0x`, binary after `0b`, and with
+/// single `_`s between the digits. Values too large for a `u64` saturate to
+/// `u64::MAX`, which is out of range for every `IntWidth`.
+pub fn number_value(file_contents: &str, span: Span) -> Result<u64, LexError> {
+    let literal = &file_contents[span.0..span.1];
+    let (radix, name, digits_start) = match literal.get(..2) {
+        Some("0x") | Some("0X") => (16, "hexadecimal", 2),
+        Some("0b") | Some("0B") => (2, "binary", 2),
+        _ => (10, "decimal", 0),
+    };
+
+    let digits = &literal[digits_start..];
+    let mut value = 0u64;
+    let mut any_digits = false;
+    let mut after_digit = false;
+    for (i, c) in digits.char_indices() {
+        if c == '_' {
+            if !after_digit || i + 1 == digits.len() {
+                let at = span.0 + digits_start + i;
+                return Err(LexError {
+                    span: (at, at + 1),
+                    message: format!(
+                        "Malformed number literal '{}': '_' can only stand between two digits.",
+                        literal
+                    ),
+                });
+            }
+            after_digit = false;
+            continue;
         }
+        let digit = match c.to_digit(radix) {
+            Some(digit) => digit,
+            None => {
+                let at = span.0 + digits_start + i;
+                return Err(LexError {
+                    span: (at, at + c.len_utf8()),
+                    message: format!(
+                        "Malformed number literal '{}': '{}' is not a {} digit.",
+                        literal, c, name
+                    ),
+                });
+            }
+        };
+        any_digits = true;
+        after_digit = true;
+        value = value
+            .checked_mul(u64::from(radix))
+            .and_then(|value| value.checked_add(u64::from(digit)))
+            .unwrap_or(u64::MAX);
     }
-    tokens
+    if !any_digits {
+        return Err(LexError {
+            span,
+            message: format!(
+                "Malformed number literal '{}': expected {} digits after '{}'.",
+                literal,
+                name,
+                &literal[..digits_start]
+            ),
+        });
+    }
+    Ok(value)
 }
 
-fn get_token_type(span: Span, src: &str) -> TokenType {
-    match &src[span.0..span.1] {
-        "if" | "then" | "else" | "repeat" | "end" | "until" | "read" | "write" => {
-            TokenType::RESERVED
+fn find_resync<F>(tokens: &[Token], checked: &mut usize, resync: &mut F) -> Option<usize>
+where
+    F: FnMut(usize) -> bool,
+{
+    while *checked < tokens.len() {
+        match tokens[*checked] {
+            Token::COMMENT(_) => {}
+            tok => {
+                if resync(tok.get_span().0) {
+                    return Some(*checked);
+                }
+            }
         }
-        _ => TokenType::IDENTIFIER,
+        *checked += 1;
     }
+    None
 }
 
-fn add_token<'a>(val: Span, tok_type: TokenType, tokens: &mut Vec<Token>) {
+/// Identifiers follow UAX #31: they start with a character of `XID_Start`, which
+/// takes in the letters of every script, or with `Dialect::identifier_digits` `_`.
+fn is_identifier_start(c: char, dialect: &Dialect) -> bool {
+    unicode_ident::is_xid_start(c) || (dialect.identifier_digits && c == '_')
+}
+
+/// The rest of an identifier is `XID_Continue`, which adds combining marks, less
+/// the digits and `_` unless `Dialect::identifier_digits` lets them in.
+fn is_identifier_continue(c: char, dialect: &Dialect) -> bool {
+    unicode_ident::is_xid_continue(c)
+        && (dialect.identifier_digits || !(c.is_numeric() || c == '_'))
+}
+
+fn get_token_type(span: Span, src: &str, dialect: &Dialect) -> TokenType {
+    if dialect.is_keyword(&src[span.0..span.1]) {
+        TokenType::RESERVED
+    } else {
+        TokenType::IDENTIFIER
+    }
+}
+
+fn add_token(val: Span, tok_type: TokenType, tokens: &mut Vec<Token>) {
     match tok_type {
         TokenType::RESERVED => {
             tokens.push(Token::RESERVED(val));
@@ -166,11 +543,52 @@ fn add_token<'a>(val: Span, tok_type: TokenType, tokens: &mut Vec<Token>) {
         TokenType::SYMBOL => {
             tokens.push(Token::SYMBOL(val));
         }
+        TokenType::LBRACKET => {
+            tokens.push(Token::LBRACKET(val));
+        }
+        TokenType::RBRACKET => {
+            tokens.push(Token::RBRACKET(val));
+        }
         TokenType::IDENTIFIER => {
             tokens.push(Token::IDENTIFIER(val));
         }
         TokenType::COMMENT => {
             tokens.push(Token::COMMENT(val));
         }
+        TokenType::STRING => {
+            tokens.push(Token::STRING(val));
+        }
+    }
+}
+
+/// The text of the string literal at `span` in `src`, without its quotes and with
+/// the escapes `\"`, `\\`, `\n` and `\t` replaced. Unterminated literals and
+/// unknown escapes are errors, spanning the whole literal and the escape respectively.
+pub fn string_value(src: &str, span: Span) -> Result<String, LexError> {
+    let literal = &src[span.0..span.1];
+    let mut value = String::new();
+    let mut chars = literal.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok(value),
+            '\\' => match chars.next() {
+                Some((_, '"')) => value.push('"'),
+                Some((_, '\\')) => value.push('\\'),
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((j, other)) => {
+                    return Err(LexError {
+                        span: (span.0 + i, span.0 + j + other.len_utf8()),
+                        message: format!("Unknown escape sequence '\\{}' in string literal.\n\tSuggested Fix:\tUse one of '\\\"', '\\\\', '\\n' or '\\t'.", other),
+                    })
+                }
+                None => break,
+            },
+            _ => value.push(c),
+        }
     }
+    Err(LexError {
+        span,
+        message: "Unterminated string literal.\n\tSuggested Fix:\tClose the string with a '\"' before the end of the line.".to_string(),
+    })
 }
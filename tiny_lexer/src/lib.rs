@@ -1 +1,2 @@
+pub mod dialect;
 pub mod lexer;
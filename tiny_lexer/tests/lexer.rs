@@ -0,0 +1,376 @@
+use tiny_lexer::dialect::Dialect;
+use tiny_lexer::lexer::{
+    number_value, string_value, tokenize, tokenize_with, unterminated_comment, warnings, Token,
+};
+
+/// Lexes `src` in the extended dialect, which most of these tests are about.
+fn lex(src: &str, comments_allowed: bool) -> Vec<Token> {
+    tokenize_with(src, comments_allowed, &Dialect::extended())
+}
+
+fn contents(src: &str) -> Vec<&str> {
+    lex(src, false)
+        .iter()
+        .map(|tok| {
+            let (i0, i1) = tok.get_span();
+            &src[i0..i1]
+        })
+        .collect()
+}
+
+#[test]
+fn comparison_operators_are_single_tokens() {
+    assert_eq!(contents("a <= b "), vec!["a", "<=", "b"]);
+    assert_eq!(contents("a>=b "), vec!["a", ">=", "b"]);
+    assert_eq!(
+        contents("a<>b<c>d "),
+        vec!["a", "<>", "b", "<", "c", ">", "d"]
+    );
+    assert_eq!(contents("a < = b "), vec!["a", "<", "=", "b"]);
+    assert_eq!(contents("x := y "), vec!["x", ":=", "y"]);
+    assert_eq!(lex("a<=b ", false)[1], Token::SYMBOL((1, 3)));
+}
+
+#[test]
+fn a_trailing_comparison_is_kept() {
+    assert_eq!(contents("a <"), vec!["a", "<"]);
+    assert_eq!(contents("a >"), vec!["a", ">"]);
+}
+
+#[test]
+fn the_last_token_ends_with_the_file() {
+    assert_eq!(contents("write 5"), vec!["write", "5"]);
+    assert_eq!(contents("x := 5"), vec!["x", ":=", "5"]);
+    assert_eq!(contents("read x"), vec!["read", "x"]);
+    assert_eq!(contents("x :"), vec!["x", ":"]);
+    assert_eq!(contents("x := 0x1F"), vec!["x", ":=", "0x1F"]);
+    assert_eq!(lex("read x", false)[1], Token::IDENTIFIER((5, 6)));
+    assert_eq!(lex("x := end", false)[2], Token::RESERVED((5, 8)));
+}
+
+#[test]
+fn unterminated_comments_are_errors() {
+    let dialect = Dialect::extended();
+    let error = |src: &str| unterminated_comment(src, &lex(src, false), &dialect);
+    assert_eq!(error("x := 1 { one").map(|err| err.span), Some((7, 12)));
+    assert_eq!(error("x := 1 {").map(|err| err.span), Some((7, 8)));
+    assert_eq!(error("{ a { b } c").map(|err| err.span), Some((0, 11)));
+    assert_eq!(error("x := 1 { one } "), None);
+    assert_eq!(error("x := 1 // { one"), None);
+    assert_eq!(error("x := \"{\" "), None);
+    assert_eq!(error(""), None);
+    assert_eq!(lex("x { one", true).last(), Some(&Token::COMMENT((3, 7))));
+}
+
+#[test]
+fn signs_are_separate_symbols() {
+    assert_eq!(contents("3-1 "), vec!["3", "-", "1"]);
+    assert_eq!(contents("x:=-5 "), vec!["x", ":=", "-", "5"]);
+    assert!(matches!(lex("3-1 ", false)[2], Token::NUMBER(_)));
+}
+
+#[test]
+fn brackets_have_their_own_token_types() {
+    let tokens = lex("a[i-1] ", false);
+    assert_eq!(tokens[1], Token::LBRACKET((1, 2)));
+    assert_eq!(tokens[5], Token::RBRACKET((5, 6)));
+    assert_eq!(tokens[1].type_name(), "LBRACKET");
+}
+
+#[test]
+fn strings_are_single_tokens() {
+    let src = r#"write "a {b} ; \"c\"", x "#;
+    assert_eq!(contents(src), vec!["write", r#""a {b} ; \"c\"""#, ",", "x"]);
+    let tokens = lex(src, true);
+    assert_eq!(tokens[1], Token::STRING((6, 21)));
+    assert_eq!(string_value(src, (6, 21)).unwrap(), r#"a {b} ; "c""#);
+    assert_eq!(
+        string_value(r#""tab\tnew\nline\\""#, (0, 18)).unwrap(),
+        "tab\tnew\nline\\"
+    );
+}
+
+#[test]
+fn unterminated_strings_end_with_their_line() {
+    let src = "write \"abc\nx := 1 ";
+    assert_eq!(contents(src), vec!["write", "\"abc", "x", ":=", "1"]);
+    let err = string_value(src, (6, 10)).unwrap_err();
+    assert_eq!(err.span, (6, 10));
+    assert!(err.message.starts_with("Unterminated string literal."));
+
+    assert_eq!(contents("write \"ab\\"), vec!["write", "\"ab\\"]);
+    assert!(string_value("\"ab\\", (0, 4)).is_err());
+    assert_eq!(contents("\""), vec!["\""]);
+}
+
+#[test]
+fn unknown_escapes_are_errors() {
+    let src = r#"write "a\qb" "#;
+    let err = string_value(src, (6, 12)).unwrap_err();
+    assert_eq!(err.span, (8, 10));
+    assert_eq!(
+        err.to_string().lines().next().unwrap(),
+        "Lexical Error at 8..10:"
+    );
+}
+
+#[test]
+fn tokenize_lexes_textbook_tiny() {
+    let src = "while X do y := -0x1 end ";
+    assert_eq!(
+        tokenize(src, false),
+        tokenize_with(src, false, &Dialect::textbook())
+    );
+    assert_ne!(tokenize(src, false), lex(src, false));
+}
+
+fn textbook_contents(src: &str) -> Vec<&str> {
+    tokenize_with(src, false, &Dialect::textbook())
+        .iter()
+        .map(|tok| {
+            let (i0, i1) = tok.get_span();
+            &src[i0..i1]
+        })
+        .collect()
+}
+
+#[test]
+fn textbook_dialect_lexes_extension_symbols_one_char_at_a_time() {
+    assert_eq!(textbook_contents("a <= b "), vec!["a", "<", "=", "b"]);
+    assert_eq!(textbook_contents("a <> b "), vec!["a", "<", ">", "b"]);
+    assert_eq!(textbook_contents("a // b "), vec!["a", "/", "/", "b"]);
+    let tokens = tokenize_with("a[\"b\"] ", false, &Dialect::textbook());
+    assert!(tokens
+        .iter()
+        .all(|tok| matches!(tok, Token::IDENTIFIER(_) | Token::SYMBOL(_))));
+}
+
+#[test]
+fn keywords_depend_on_the_dialect() {
+    let src = "while WHILE If and array x ";
+    let types = |dialect: &Dialect| -> Vec<&str> {
+        tokenize_with(src, false, dialect)
+            .iter()
+            .map(Token::type_name)
+            .collect()
+    };
+    assert_eq!(
+        types(&Dialect::extended()),
+        vec![
+            "RESERVED",
+            "RESERVED",
+            "RESERVED",
+            "RESERVED",
+            "RESERVED",
+            "IDENTIFIER"
+        ]
+    );
+    assert_eq!(types(&Dialect::textbook()), vec!["IDENTIFIER"; 6]);
+    let dialect = Dialect {
+        case_insensitive_keywords: false,
+        ..Dialect::extended()
+    };
+    assert_eq!(
+        types(&dialect),
+        vec![
+            "RESERVED",
+            "IDENTIFIER",
+            "IDENTIFIER",
+            "RESERVED",
+            "RESERVED",
+            "IDENTIFIER"
+        ]
+    );
+}
+
+#[test]
+fn line_comments_run_to_the_end_of_the_line() {
+    let src = "x := 1 // one { not a brace\ny := 2 ";
+    assert_eq!(contents(src), vec!["x", ":=", "1", "y", ":=", "2"]);
+    let tokens = lex(src, true);
+    assert_eq!(tokens[3], Token::SYMBOL((7, 9)));
+    assert_eq!(tokens[4], Token::COMMENT((9, 27)));
+    assert_eq!(contents("x / y // z"), vec!["x", "/", "y"]);
+    assert_eq!(lex("x // z", true).last(), Some(&Token::COMMENT((4, 6))));
+}
+
+#[test]
+fn comments_nest_in_the_extended_dialect() {
+    let src = "{ a { b } c } x := 1 ";
+    assert_eq!(contents(src), vec!["x", ":=", "1"]);
+    assert_eq!(lex(src, true)[1], Token::COMMENT((1, 12)));
+    assert_eq!(textbook_contents(src), vec!["c", "}", "x", ":=", "1"]);
+}
+
+#[test]
+fn case_sensitive_keywords_warn_about_other_spellings() {
+    let src = "IF x Then y := While ";
+    let textbook = Dialect::textbook();
+    let found = warnings(src, &tokenize_with(src, false, &textbook), &textbook);
+    let spans: Vec<_> = found.iter().map(|warning| warning.span).collect();
+    // `while` isn't a keyword of textbook TINY in any case.
+    assert_eq!(spans, vec![(0, 2), (5, 9)]);
+    assert!(found[0]
+        .message
+        .starts_with("Did you mean the keyword `if`?"));
+
+    let extended = Dialect::extended();
+    assert!(warnings(src, &tokenize_with(src, false, &extended), &extended).is_empty());
+}
+
+#[test]
+fn number_values_saturate() {
+    let src = "18446744073709551615 18446744073709551616 ";
+    assert_eq!(number_value(src, (0, 20)).unwrap(), u64::MAX);
+    assert_eq!(number_value(src, (21, 41)).unwrap(), u64::MAX);
+    assert_eq!(number_value("0042 ", (0, 4)).unwrap(), 42);
+    assert_eq!(
+        number_value("0x1_0000_0000_0000_0000 ", (0, 23)).unwrap(),
+        u64::MAX
+    );
+}
+
+#[test]
+fn hex_binary_and_separated_numbers_are_single_tokens() {
+    let src = "x := 0xFF + 0b1010 + 1_000_000 ";
+    assert_eq!(
+        contents(src),
+        vec!["x", ":=", "0xFF", "+", "0b1010", "+", "1_000_000"]
+    );
+    assert_eq!(number_value(src, (5, 9)).unwrap(), 255);
+    assert_eq!(number_value(src, (12, 18)).unwrap(), 10);
+    assert_eq!(number_value(src, (21, 30)).unwrap(), 1_000_000);
+    assert_eq!(number_value("0XaB ", (0, 4)).unwrap(), 0xab);
+}
+
+#[test]
+fn malformed_numbers_are_errors() {
+    let src = "0x 0b2 1__0 ";
+    assert_eq!(contents(src), vec!["0x", "0b2", "1__0"]);
+
+    let err = number_value(src, (0, 2)).unwrap_err();
+    assert_eq!(err.span, (0, 2));
+    assert_eq!(
+        err.message,
+        "Malformed number literal '0x': expected hexadecimal digits after '0x'."
+    );
+
+    let err = number_value(src, (3, 6)).unwrap_err();
+    assert_eq!(err.span, (5, 6));
+    assert_eq!(
+        err.message,
+        "Malformed number literal '0b2': '2' is not a binary digit."
+    );
+
+    let err = number_value(src, (7, 11)).unwrap_err();
+    assert_eq!(err.span, (9, 10));
+    assert_eq!(
+        err.message,
+        "Malformed number literal '1__0': '_' can only stand between two digits."
+    );
+}
+
+#[test]
+fn separators_stand_between_digits() {
+    for (src, at) in [("1_ ", 1), ("0x_F ", 2), ("0b1_ ", 3), ("1_000__0 ", 6)] {
+        let err = number_value(src, (0, src.len() - 1)).unwrap_err();
+        assert_eq!(err.span, (at, at + 1), "{:?}", src);
+    }
+    assert_eq!(number_value("0b1_0 ", (0, 5)).unwrap(), 2);
+}
+
+#[test]
+fn numbers_end_before_the_letters_of_a_keyword() {
+    assert_eq!(
+        contents("if x<3then write 1 end "),
+        vec!["if", "x", "<", "3", "then", "write", "1", "end"]
+    );
+    assert_eq!(
+        contents("0xFFthen 0b10else 12abc "),
+        vec!["0xFF", "then", "0b10", "else", "12", "abc"]
+    );
+}
+
+#[test]
+fn textbook_numbers_are_decimal_digits_only() {
+    assert_eq!(
+        textbook_contents("0xFF 1_000 "),
+        vec!["0", "xFF", "1", "_", "000"]
+    );
+}
+
+fn assert_on_char_boundaries(src: &str, dialect: &Dialect) {
+    for tok in tokenize_with(src, true, dialect) {
+        let (i0, i1) = tok.get_span();
+        assert!(
+            src.is_char_boundary(i0) && src.is_char_boundary(i1),
+            "{:?} splits a character in {:?}",
+            tok,
+            src
+        );
+    }
+}
+
+#[test]
+fn spans_lie_on_char_boundaries() {
+    let sources = [
+        "{ Sample program in TINY language – computes factorial }\nread x; { don’t } write x ",
+        "größe := 1 ",
+        "x := ü€ + é;ä ",
+        "write \"naïve ☃\", \"😀\\ñ\" ",
+        "write \"unterminated ☃\nx := 1 ",
+        "// ünïcödé\nx := 1 ",
+        "x <ü >€ :ß ",
+        "{ { ü } ü } 0xü 1_€ ",
+        "€",
+        "x€",
+    ];
+    for src in sources.iter() {
+        assert_on_char_boundaries(src, &Dialect::extended());
+        assert_on_char_boundaries(src, &Dialect::textbook());
+    }
+}
+
+#[test]
+fn identifiers_can_be_in_any_script() {
+    assert_eq!(
+        contents("größe := 变量 + café; Ωμέγα := 1 "),
+        vec!["größe", ":=", "变量", "+", "café", ";", "Ωμέγα", ":=", "1"]
+    );
+    // A combining accent continues the identifier, a symbol doesn't.
+    assert_eq!(contents("cafe\u{301} "), vec!["cafe\u{301}"]);
+    assert_eq!(contents("x€y "), vec!["x", "€", "y"]);
+    assert_eq!(
+        contents("{ don’t } x "),
+        vec!["x"],
+        "the comment is skipped whole"
+    );
+}
+
+#[test]
+fn identifiers_take_digits_and_underscores() {
+    assert_eq!(
+        contents("x1 := total_sum + _tmp2 + 1x "),
+        vec!["x1", ":=", "total_sum", "+", "_tmp2", "+", "1", "x"]
+    );
+    assert_eq!(
+        textbook_contents("x1 := total_sum + _tmp2 "),
+        vec!["x", "1", ":=", "total", "_", "sum", "+", "_", "tmp", "2"]
+    );
+}
+
+#[test]
+fn strict_identifiers_warn_about_trailing_digits() {
+    let textbook = Dialect::textbook();
+    let src = "x1 := y 2 + z34 ";
+    let found = warnings(src, &tokenize_with(src, false, &textbook), &textbook);
+    let spans: Vec<_> = found.iter().map(|warning| warning.span).collect();
+    assert_eq!(spans, vec![(0, 2), (12, 15)]);
+    assert_eq!(
+        found[1].message,
+        "Identifiers can't contain digits, so 'z34' is the identifier 'z' followed by the number '34'."
+    );
+
+    let extended = Dialect::extended();
+    assert!(warnings(src, &tokenize_with(src, false, &extended), &extended).is_empty());
+}
@@ -0,0 +1,483 @@
+//! A typed view of the simplified tree, for the passes that run after parsing
+//! (the interpreter, and anything else that needs to know what a node means
+//! rather than how it was written).
+
+use crate::{parse_with, semantic, Dialect, Node, NodeType, OpType, StmtType};
+use std::convert::TryFrom;
+use tiny_lexer::lexer::{string_value, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    /// `elsif_parts` are tried in order after `cond` fails. Lowering a tree
+    /// passed through `desugar::desugar_elsif` leaves them empty.
+    If {
+        cond: Exp,
+        then_part: Vec<Stmt>,
+        elsif_parts: Vec<ElsifPart>,
+        else_part: Vec<Stmt>,
+        span: Span,
+    },
+    Repeat {
+        body: Vec<Stmt>,
+        cond: Exp,
+        span: Span,
+    },
+    While {
+        cond: Exp,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    /// Only allowed at the top level, see `semantic::check`.
+    Procedure {
+        name: String,
+        params: Vec<String>,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    Call {
+        name: String,
+        args: Vec<Exp>,
+        span: Span,
+    },
+    /// Declares a global array of `size` elements, all 0, indexed from 0.
+    Array {
+        name: String,
+        size: usize,
+        span: Span,
+    },
+    /// Assigns to `name[index]` if there's an index, to the variable otherwise.
+    Assign {
+        name: String,
+        index: Option<Exp>,
+        value: Exp,
+        span: Span,
+    },
+    Read {
+        name: String,
+        index: Option<Exp>,
+        span: Span,
+    },
+    /// Writes the items one after the other, then ends the line.
+    Write { items: Vec<WriteItem>, span: Span },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteItem {
+    /// A string literal, with its escapes already replaced.
+    String {
+        value: String,
+        span: Span,
+    },
+    Exp(Exp),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElsifPart {
+    pub cond: Exp,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Exp {
+    Number {
+        value: i64,
+        span: Span,
+    },
+    Identifier {
+        name: String,
+        span: Span,
+    },
+    BinOp {
+        op: BinOp,
+        lhs: Box<Exp>,
+        rhs: Box<Exp>,
+        span: Span,
+    },
+    UnOp {
+        op: UnOp,
+        operand: Box<Exp>,
+        span: Span,
+    },
+    Index {
+        name: String,
+        index: Box<Exp>,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    /// Short-circuiting: `rhs` is only evaluated when `lhs` is non-zero. Like the
+    /// comparisons, the result is 1 or 0.
+    And,
+    /// Short-circuiting: `rhs` is only evaluated when `lhs` is zero. The result is
+    /// 1 or 0.
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    /// 1 if the operand is zero, 0 otherwise.
+    Not,
+    /// Wrapping negation.
+    Neg,
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::If { span, .. }
+            | Stmt::Repeat { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::Procedure { span, .. }
+            | Stmt::Call { span, .. }
+            | Stmt::Array { span, .. }
+            | Stmt::Assign { span, .. }
+            | Stmt::Read { span, .. }
+            | Stmt::Write { span, .. } => *span,
+        }
+    }
+}
+
+impl Exp {
+    pub fn span(&self) -> Span {
+        match self {
+            Exp::Number { span, .. }
+            | Exp::Identifier { span, .. }
+            | Exp::BinOp { span, .. }
+            | Exp::UnOp { span, .. }
+            | Exp::Index { span, .. } => *span,
+        }
+    }
+}
+
+impl BinOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::Eq => "=",
+            BinOp::Ne => "<>",
+            BinOp::And => "and",
+            BinOp::Or => "or",
+        }
+    }
+
+    fn from_symbol(symbol: &str) -> Option<BinOp> {
+        match symbol {
+            "+" => Some(BinOp::Add),
+            "-" => Some(BinOp::Sub),
+            "*" => Some(BinOp::Mul),
+            "/" => Some(BinOp::Div),
+            "<" => Some(BinOp::Lt),
+            "<=" => Some(BinOp::Le),
+            ">" => Some(BinOp::Gt),
+            ">=" => Some(BinOp::Ge),
+            "=" => Some(BinOp::Eq),
+            "<>" => Some(BinOp::Ne),
+            "and" => Some(BinOp::And),
+            "or" => Some(BinOp::Or),
+            _ => None,
+        }
+    }
+}
+
+/// `lhs op rhs` with both operands evaluated, the way every TINY program computes
+/// it: arithmetic wraps, and comparisons, `and` and `or` give 1 or 0. `None` for a
+/// division by zero.
+pub fn evaluate(op: BinOp, lhs: i64, rhs: i64) -> Option<i64> {
+    Some(match op {
+        BinOp::Add => lhs.wrapping_add(rhs),
+        BinOp::Sub => lhs.wrapping_sub(rhs),
+        BinOp::Mul => lhs.wrapping_mul(rhs),
+        BinOp::Div if rhs == 0 => return None,
+        BinOp::Div => lhs.wrapping_div(rhs),
+        BinOp::Lt => (lhs < rhs) as i64,
+        BinOp::Le => (lhs <= rhs) as i64,
+        BinOp::Gt => (lhs > rhs) as i64,
+        BinOp::Ge => (lhs >= rhs) as i64,
+        BinOp::Eq => (lhs == rhs) as i64,
+        BinOp::Ne => (lhs != rhs) as i64,
+        BinOp::And => (lhs != 0 && rhs != 0) as i64,
+        BinOp::Or => (lhs != 0 || rhs != 0) as i64,
+    })
+}
+
+impl UnOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnOp::Not => "not",
+            UnOp::Neg => "-",
+        }
+    }
+}
+
+/// Parses `src` in the extended dialect into a simplified tree, lowers it and
+/// checks it with `semantic::check`, failing with the messages of every syntax
+/// error in the tree or else of every semantic error in the program.
+pub fn parse_program(src: &str) -> Result<Program, Vec<String>> {
+    let dialect = Dialect::extended();
+    let program = lower_with(&parse_with(src, true, &dialect), src, &dialect)?;
+    match semantic::check(&program) {
+        Ok(_) => Ok(program),
+        Err(errors) => Err(errors.iter().map(ToString::to_string).collect()),
+    }
+}
+
+/// Lowers a tree returned by `parse(src, true)`.
+pub fn lower(root: &Node, src: &str) -> Result<Program, Vec<String>> {
+    lower_with(root, src, &Dialect::default())
+}
+
+/// Lowers a tree returned by `parse_with(src, true, dialect)`.
+pub fn lower_with(root: &Node, src: &str, dialect: &Dialect) -> Result<Program, Vec<String>> {
+    let mut errors = vec![];
+    collect_errors(root, &mut errors);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let body = match root.children.first() {
+        Some(first) => stmt_seq(first, src, dialect),
+        None => Ok(vec![]),
+    };
+    body.map(|body| Program { body }).map_err(|err| vec![err])
+}
+
+fn collect_errors(node: &Node, errors: &mut Vec<String>) {
+    if let NodeType::Error(_, message) = &node.n_type {
+        errors.push(message.clone());
+    }
+    for child in node.children.iter().chain(node.nextstmt.iter()) {
+        collect_errors(child, errors);
+    }
+}
+
+fn stmt_seq(first: &Node, src: &str, dialect: &Dialect) -> Result<Vec<Stmt>, String> {
+    let mut stmts = vec![stmt(first, src, dialect)?];
+    let mut curr = first;
+    while let Some(next) = curr.nextstmt.first() {
+        stmts.push(stmt(next, src, dialect)?);
+        curr = next;
+    }
+    Ok(stmts)
+}
+
+fn stmt(node: &Node, src: &str, dialect: &Dialect) -> Result<Stmt, String> {
+    let span = node.span;
+    let children = &node.children;
+    match &node.n_type {
+        NodeType::Stmt(StmtType::IfStmt) => {
+            let elsifs = children.iter().skip(2);
+            let else_first = elsifs.clone().find(|c| c.n_type != NodeType::Elsif);
+            Ok(Stmt::If {
+                cond: exp(child(node, 0)?, src, dialect)?,
+                then_part: stmt_seq(child(node, 1)?, src, dialect)?,
+                elsif_parts: elsifs
+                    .take_while(|c| c.n_type == NodeType::Elsif)
+                    .map(|elsif| elsif_part(elsif, src, dialect))
+                    .collect::<Result<_, _>>()?,
+                else_part: match else_first {
+                    Some(else_first) => stmt_seq(else_first, src, dialect)?,
+                    None => vec![],
+                },
+                span,
+            })
+        }
+        NodeType::Stmt(StmtType::RepeatStmt) => Ok(Stmt::Repeat {
+            body: stmt_seq(child(node, 0)?, src, dialect)?,
+            cond: exp(child(node, 1)?, src, dialect)?,
+            span,
+        }),
+        NodeType::Stmt(StmtType::WhileStmt) => Ok(Stmt::While {
+            cond: exp(child(node, 0)?, src, dialect)?,
+            body: stmt_seq(child(node, 1)?, src, dialect)?,
+            span,
+        }),
+        NodeType::Stmt(StmtType::ProcDecl) => Ok(Stmt::Procedure {
+            name: name(child(node, 0)?, src, dialect),
+            params: child(node, 1)?
+                .children
+                .iter()
+                .map(|param| name(param, src, dialect))
+                .collect(),
+            body: stmt_seq(child(node, 2)?, src, dialect)?,
+            span,
+        }),
+        NodeType::Stmt(StmtType::CallStmt) => Ok(Stmt::Call {
+            name: name(child(node, 0)?, src, dialect),
+            args: child(node, 1)?
+                .children
+                .iter()
+                .map(|arg| exp(arg, src, dialect))
+                .collect::<Result<_, _>>()?,
+            span,
+        }),
+        NodeType::Stmt(StmtType::ArrayDecl) => {
+            let size = child(node, 1)?;
+            Ok(Stmt::Array {
+                name: name(child(node, 0)?, src, dialect),
+                size: usize::try_from(value(size)?)
+                    .map_err(|_| format!("Array size '{}' is out of range", content(size, src)))?,
+                span,
+            })
+        }
+        NodeType::Stmt(StmtType::AssignStmt) => {
+            let (name, index) = variable(child(node, 0)?, src, dialect)?;
+            Ok(Stmt::Assign {
+                name,
+                index,
+                value: exp(child(node, 1)?, src, dialect)?,
+                span,
+            })
+        }
+        NodeType::Stmt(StmtType::ReadStmt) => {
+            let (name, index) = variable(child(node, 0)?, src, dialect)?;
+            Ok(Stmt::Read { name, index, span })
+        }
+        NodeType::Stmt(StmtType::WriteStmt) => Ok(Stmt::Write {
+            items: children
+                .iter()
+                .map(|item| write_item(item, src, dialect))
+                .collect::<Result<_, _>>()?,
+            span,
+        }),
+        n_type => Err(format!("Expected a statement, found {:?}", n_type)),
+    }
+}
+
+fn elsif_part(node: &Node, src: &str, dialect: &Dialect) -> Result<ElsifPart, String> {
+    Ok(ElsifPart {
+        cond: exp(child(node, 0)?, src, dialect)?,
+        body: stmt_seq(child(node, 1)?, src, dialect)?,
+        span: node.span,
+    })
+}
+
+fn write_item(node: &Node, src: &str, dialect: &Dialect) -> Result<WriteItem, String> {
+    match node.n_type {
+        NodeType::String => Ok(WriteItem::String {
+            value: string_value(src, node.span).map_err(|err| err.to_string())?,
+            span: node.span,
+        }),
+        _ => Ok(WriteItem::Exp(exp(node, src, dialect)?)),
+    }
+}
+
+fn exp(node: &Node, src: &str, dialect: &Dialect) -> Result<Exp, String> {
+    match &node.n_type {
+        NodeType::Number(value) => Ok(Exp::Number {
+            value: *value as i64,
+            span: node.span,
+        }),
+        NodeType::Index => {
+            let (name, index) = variable(node, src, dialect)?;
+            Ok(Exp::Index {
+                name,
+                index: Box::new(index.unwrap()),
+                span: node.span,
+            })
+        }
+        NodeType::Identifier => Ok(Exp::Identifier {
+            name: name(node, src, dialect),
+            span: node.span,
+        }),
+        NodeType::Op(OpType::UnaryOp) => {
+            let operand = child(node, 0)?;
+            match (node.op_symbol(src, dialect), &operand.n_type) {
+                ("+", _) => exp(operand, src, dialect),
+                // A negated literal is a literal of its own, which is how the most
+                // negative value can be written at all.
+                ("-", NodeType::Number(value)) => Ok(Exp::Number {
+                    value: (*value as i64).wrapping_neg(),
+                    span: (node.span.0, operand.span.1),
+                }),
+                _ => {
+                    let operand = exp(operand, src, dialect)?;
+                    Ok(Exp::UnOp {
+                        op: UnOp::Neg,
+                        span: (node.span.0, operand.span().1),
+                        operand: Box::new(operand),
+                    })
+                }
+            }
+        }
+        NodeType::Op(OpType::NotOp) => {
+            let operand = exp(child(node, 0)?, src, dialect)?;
+            Ok(Exp::UnOp {
+                op: UnOp::Not,
+                span: (node.span.0, operand.span().1),
+                operand: Box::new(operand),
+            })
+        }
+        NodeType::Op(_) => {
+            let symbol = node.op_symbol(src, dialect);
+            let op = BinOp::from_symbol(&symbol.to_ascii_lowercase())
+                .ok_or_else(|| format!("Unknown operator '{}'", symbol))?;
+            let lhs = exp(child(node, 0)?, src, dialect)?;
+            let rhs = exp(child(node, 1)?, src, dialect)?;
+            Ok(Exp::BinOp {
+                op,
+                span: (lhs.span().0, rhs.span().1),
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        n_type => Err(format!("Expected an expression, found {:?}", n_type)),
+    }
+}
+
+/// The name and index of an `Identifier` or `Index` node.
+fn variable(node: &Node, src: &str, dialect: &Dialect) -> Result<(String, Option<Exp>), String> {
+    match node.n_type {
+        NodeType::Index => Ok((
+            name(child(node, 0)?, src, dialect),
+            Some(exp(child(node, 1)?, src, dialect)?),
+        )),
+        _ => Ok((name(node, src, dialect), None)),
+    }
+}
+
+fn value(node: &Node) -> Result<u64, String> {
+    match node.n_type {
+        NodeType::Number(value) => Ok(value),
+        ref n_type => Err(format!("Expected a number, found {:?}", n_type)),
+    }
+}
+
+fn child(node: &Node, i: usize) -> Result<&Node, String> {
+    node.children
+        .get(i)
+        .ok_or_else(|| format!("Malformed {:?} node", node.n_type))
+}
+
+/// The name an identifier node stands for in `dialect`.
+fn name(node: &Node, src: &str, dialect: &Dialect) -> String {
+    dialect.identifier_name(content(node, src))
+}
+
+fn content<'a>(node: &Node, src: &'a str) -> &'a str {
+    &src[node.span.0..node.span.1]
+}
@@ -0,0 +1,48 @@
+//! Rewrites of the simplified tree into plainer forms, so that passes written
+//! against the core TINY grammar keep working on programs using the extensions.
+
+use crate::{Node, NodeType, StmtType};
+
+/// Turns every else-if chain into nested if statements, so that
+/// `if a then s1 elsif b then s2 else s3 end` reads as
+/// `if a then s1 else if b then s2 else s3 end end`. The nested `IfStmt` node
+/// spans from its `elsif` condition to the end of the chain.
+///
+/// Only the simplified tree can be desugared: the full one has keyword nodes that
+/// the nested ifs would need but the source doesn't have, so it panics on a
+/// `StmtSeq` node.
+pub fn desugar_elsif(node: &Node) -> Node {
+    assert!(
+        node.n_type != NodeType::StmtSeq,
+        "desugar_elsif takes the simplified tree"
+    );
+    let mut children: Vec<Node> = node.children.iter().map(desugar_elsif).collect();
+    let nextstmt = node.nextstmt.iter().map(desugar_elsif).collect();
+
+    if node.n_type == NodeType::Stmt(StmtType::IfStmt) {
+        if let Some(first) = children.iter().position(|c| c.n_type == NodeType::Elsif) {
+            let mut tail = children.split_off(first);
+            let last = tail
+                .iter()
+                .rposition(|c| c.n_type == NodeType::Elsif)
+                .unwrap();
+            let mut rest = tail.split_off(last + 1);
+            for elsif in tail.into_iter().rev() {
+                let mut if_node = Node::new();
+                if_node.n_type = NodeType::Stmt(StmtType::IfStmt);
+                for child in elsif.children.into_iter().chain(rest) {
+                    if_node.add_child(child);
+                }
+                rest = vec![if_node];
+            }
+            children.extend(rest);
+        }
+    }
+
+    Node {
+        span: node.span,
+        n_type: node.n_type.clone(),
+        nextstmt,
+        children,
+    }
+}
@@ -0,0 +1,200 @@
+//! Constant folding on the typed tree: constant subexpressions are evaluated the
+//! way the interpreter would (`2 * 3 + x` becomes `6 + x`), and operations that
+//! can't change their other operand are dropped (`x * 1`, `x + 0`).
+//!
+//! Only rewrites that keep every run-time error are made: `x * 0` stays, as `x`
+//! may index past the end of an array. A division by a constant zero would fail
+//! whenever it's reached, so it's reported instead.
+
+use crate::ast::{evaluate, BinOp, ElsifPart, Exp, Program, Stmt, UnOp, WriteItem};
+use crate::semantic::SemanticError;
+use tiny_lexer::lexer::Span;
+
+pub fn fold(program: &Program) -> Result<Program, Vec<SemanticError>> {
+    let mut folder = Folder { errors: vec![] };
+    let body = folder.stmts(&program.body);
+    if folder.errors.is_empty() {
+        Ok(Program { body })
+    } else {
+        Err(folder.errors)
+    }
+}
+
+struct Folder {
+    errors: Vec<SemanticError>,
+}
+
+impl Folder {
+    fn stmts(&mut self, stmts: &[Stmt]) -> Vec<Stmt> {
+        stmts.iter().map(|stmt| self.stmt(stmt)).collect()
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Stmt {
+        match stmt {
+            Stmt::If {
+                cond,
+                then_part,
+                elsif_parts,
+                else_part,
+                span,
+            } => Stmt::If {
+                cond: self.exp(cond),
+                then_part: self.stmts(then_part),
+                elsif_parts: elsif_parts
+                    .iter()
+                    .map(|elsif| ElsifPart {
+                        cond: self.exp(&elsif.cond),
+                        body: self.stmts(&elsif.body),
+                        span: elsif.span,
+                    })
+                    .collect(),
+                else_part: self.stmts(else_part),
+                span: *span,
+            },
+            Stmt::Repeat { body, cond, span } => Stmt::Repeat {
+                body: self.stmts(body),
+                cond: self.exp(cond),
+                span: *span,
+            },
+            Stmt::While { cond, body, span } => Stmt::While {
+                cond: self.exp(cond),
+                body: self.stmts(body),
+                span: *span,
+            },
+            Stmt::Procedure {
+                name,
+                params,
+                body,
+                span,
+            } => Stmt::Procedure {
+                name: name.clone(),
+                params: params.clone(),
+                body: self.stmts(body),
+                span: *span,
+            },
+            Stmt::Call { name, args, span } => Stmt::Call {
+                name: name.clone(),
+                args: args.iter().map(|arg| self.exp(arg)).collect(),
+                span: *span,
+            },
+            Stmt::Array { .. } => stmt.clone(),
+            Stmt::Assign {
+                name,
+                index,
+                value,
+                span,
+            } => Stmt::Assign {
+                name: name.clone(),
+                index: index.as_ref().map(|index| self.exp(index)),
+                value: self.exp(value),
+                span: *span,
+            },
+            Stmt::Read { name, index, span } => Stmt::Read {
+                name: name.clone(),
+                index: index.as_ref().map(|index| self.exp(index)),
+                span: *span,
+            },
+            Stmt::Write { items, span } => Stmt::Write {
+                items: items
+                    .iter()
+                    .map(|item| match item {
+                        WriteItem::Exp(exp) => WriteItem::Exp(self.exp(exp)),
+                        string => string.clone(),
+                    })
+                    .collect(),
+                span: *span,
+            },
+        }
+    }
+
+    /// The folded expression keeps the span of the one it replaces.
+    fn exp(&mut self, exp: &Exp) -> Exp {
+        match exp {
+            Exp::Number { .. } | Exp::Identifier { .. } => exp.clone(),
+            Exp::Index { name, index, span } => Exp::Index {
+                name: name.clone(),
+                index: Box::new(self.exp(index)),
+                span: *span,
+            },
+            Exp::UnOp { op, operand, span } => {
+                let operand = self.exp(operand);
+                match (op, constant(&operand)) {
+                    (UnOp::Not, Some(value)) => number((value == 0) as i64, *span),
+                    (UnOp::Neg, Some(value)) => number(value.wrapping_neg(), *span),
+                    // `- -x` is `x`, even for the most negative value.
+                    (UnOp::Neg, None) => match operand {
+                        Exp::UnOp {
+                            op: UnOp::Neg,
+                            operand,
+                            ..
+                        } => *operand,
+                        operand => Exp::UnOp {
+                            op: *op,
+                            operand: Box::new(operand),
+                            span: *span,
+                        },
+                    },
+                    (UnOp::Not, None) => Exp::UnOp {
+                        op: *op,
+                        operand: Box::new(operand),
+                        span: *span,
+                    },
+                }
+            }
+            Exp::BinOp { op, lhs, rhs, .. } => {
+                let (lhs, rhs) = (self.exp(lhs), self.exp(rhs));
+                self.bin_op(*op, lhs, rhs, exp)
+            }
+        }
+    }
+
+    /// Reports the division by a constant 0 and leaves `original` as it is.
+    fn division_by_zero(&mut self, original: &Exp) -> Exp {
+        self.errors.push(SemanticError {
+            span: original.span(),
+            message:
+                "Division by zero.\nSuggested Fix:\tDivide by something other than a constant 0."
+                    .to_string(),
+        });
+        original.clone()
+    }
+
+    fn bin_op(&mut self, op: BinOp, lhs: Exp, rhs: Exp, original: &Exp) -> Exp {
+        let span = original.span();
+        match (op, constant(&lhs), constant(&rhs)) {
+            (_, Some(l), Some(r)) => {
+                return match evaluate(op, l, r) {
+                    Some(value) => number(value, span),
+                    None => self.division_by_zero(original),
+                }
+            }
+            (BinOp::Div, _, Some(0)) => return self.division_by_zero(original),
+            // Short-circuiting stops at a constant left operand.
+            (BinOp::And, Some(0), _) => return number(0, span),
+            (BinOp::Or, Some(l), _) if l != 0 => return number(1, span),
+            (BinOp::Add, Some(0), None) | (BinOp::Mul, Some(1), None) => return rhs,
+            (BinOp::Add, None, Some(0))
+            | (BinOp::Sub, None, Some(0))
+            | (BinOp::Mul, None, Some(1))
+            | (BinOp::Div, None, Some(1)) => return lhs,
+            _ => {}
+        }
+        Exp::BinOp {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            span,
+        }
+    }
+}
+
+fn constant(exp: &Exp) -> Option<i64> {
+    match exp {
+        Exp::Number { value, .. } => Some(*value),
+        _ => None,
+    }
+}
+
+fn number(value: i64, span: Span) -> Exp {
+    Exp::Number { value, span }
+}
@@ -0,0 +1,282 @@
+use crate::{
+    program, top_level_stmts, unterminated_comment_error, Dialect, ErrorType, Node, NodeType,
+    TopLevelStmt, Trace,
+};
+use std::fmt;
+use tiny_lexer::lexer::{tokenize_from, tokenize_with, Span, Token};
+
+/// Replaces the bytes in `span` of the source with `replacement`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(span: Span, replacement: &str) -> TextEdit {
+        TextEdit {
+            span,
+            replacement: replacement.to_string(),
+        }
+    }
+
+    /// The edited source, or an error if the span isn't a range of `src` starting
+    /// and ending between characters.
+    pub fn apply(&self, src: &str) -> Result<String, EditError> {
+        let (start, end) = self.span;
+        if start > end {
+            return Err(EditError {
+                span: self.span,
+                message: "The edit ends before it starts.".to_string(),
+            });
+        }
+        if end > src.len() {
+            return Err(EditError {
+                span: self.span,
+                message: "The edit goes past the end of the source.".to_string(),
+            });
+        }
+        if !src.is_char_boundary(start) || !src.is_char_boundary(end) {
+            return Err(EditError {
+                span: self.span,
+                message: "The edit starts or ends inside a character.".to_string(),
+            });
+        }
+
+        let mut edited = String::with_capacity(src.len() + self.replacement.len());
+        edited.push_str(&src[..self.span.0]);
+        edited.push_str(&self.replacement);
+        edited.push_str(&src[self.span.1..]);
+        Ok(edited)
+    }
+
+    fn delta(&self) -> isize {
+        self.replacement.len() as isize - (self.span.1 - self.span.0) as isize
+    }
+}
+
+/// An edit that doesn't fit the source it's applied to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Edit Error at {}..{}:\n{}",
+            self.span.0, self.span.1, self.message
+        )
+    }
+}
+
+/// A parsed source file that can be kept up to date with `reparse` as the text is
+/// edited. Only the tokens around an edit are relexed, and only the top level
+/// statements whose tokens (or lookahead token) changed are parsed again; every
+/// other statement's subtree is reused from the previous tree.
+#[derive(Debug, Clone)]
+pub struct IncrementalParse {
+    src: String,
+    simplified: bool,
+    dialect: Dialect,
+    tokens: Vec<Token>,
+    stmts: Vec<TopLevelStmt>,
+    root: Node,
+    reused: usize,
+}
+
+impl IncrementalParse {
+    /// Parses `src` like `parse_with` does, keeping what `reparse` needs.
+    pub fn new(src: &str, simplified: bool, dialect: &Dialect) -> IncrementalParse {
+        let tokens = tokenize_with(src, false, dialect);
+        let stmts = top_level_stmts(
+            &tokens,
+            0,
+            src,
+            simplified,
+            dialect,
+            &mut Trace::disabled(),
+            |_| None,
+        );
+        let root = program(stmts.iter().map(|stmt| stmt.node.clone()), simplified);
+        IncrementalParse {
+            src: src.to_string(),
+            simplified,
+            dialect: *dialect,
+            tokens,
+            stmts,
+            root,
+            reused: 0,
+        }
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// How many top level statements the last `reparse` took from the previous
+    /// tree instead of parsing them again.
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+
+    /// Applies `edit` and brings the tree up to date, leaving everything as it was
+    /// if the edit doesn't fit the source.
+    pub fn reparse(&mut self, edit: &TextEdit) -> Result<&Node, EditError> {
+        let src = edit.apply(&self.src)?;
+        let delta = edit.delta();
+        let edit_end = edit.span.0 + edit.replacement.len();
+
+        // Relex from the last token that the edit can't have touched, until the
+        // lexer reaches a token that also starts a token of the old source.
+        let (restart, restart_offset) = match self
+            .tokens
+            .iter()
+            .rposition(|tok| tok.get_span().1 < edit.span.0)
+        {
+            Some(i) => (i, self.tokens[i].get_span().0),
+            None => (0, 0),
+        };
+        let old_tokens = &self.tokens;
+        let mut resync_index = old_tokens.len();
+        let (relexed, _) = tokenize_from(&src, restart_offset, false, &self.dialect, |offset| {
+            if offset < edit_end {
+                return false;
+            }
+            let old_offset = (offset as isize - delta) as usize;
+            match old_tokens[restart..].binary_search_by_key(&old_offset, |tok| tok.get_span().0) {
+                Ok(i) => {
+                    resync_index = restart + i;
+                    true
+                }
+                Err(_) => false,
+            }
+        });
+
+        // Tokens in `restart..changed_end` are new, everything after them is the
+        // old tail moved by `shift` indices and `delta` bytes.
+        let changed_end = restart + relexed.len();
+        let shift = changed_end as isize - resync_index as isize;
+        let mut tokens = self.tokens[..restart].to_vec();
+        tokens.extend(relexed);
+        tokens.extend(
+            self.tokens[resync_index..]
+                .iter()
+                .map(|tok| tok.with_span(shift_span(tok.get_span(), delta))),
+        );
+
+        // Whether the comment at the end is still open is checked again either way.
+        let old_stmts = match self.stmts.split_last() {
+            Some((last, rest)) if is_open_comment(last) => rest,
+            _ => &self.stmts[..],
+        };
+        // Statements whose lookahead token lies before the relexed tokens are kept as is.
+        let mut kept = old_stmts
+            .iter()
+            .take_while(|stmt| stmt.tokens.1 < restart)
+            .count();
+        // Tokens left over after the last statement are only checked after it.
+        if old_stmts[kept..].first().is_some_and(is_left_over) {
+            kept = kept.saturating_sub(1);
+        }
+        let mut stmts = old_stmts[..kept].to_vec();
+        let mut reused = kept;
+        if kept < old_stmts.len() {
+            let resume = if kept == 0 {
+                0
+            } else {
+                old_stmts[kept].tokens.0
+            };
+            let old_stmts = &old_stmts[kept..];
+            stmts.extend(top_level_stmts(
+                &tokens,
+                resume,
+                &src,
+                self.simplified,
+                &self.dialect,
+                &mut Trace::disabled(),
+                |first| {
+                    if first < changed_end {
+                        return None;
+                    }
+                    let old_first = (first as isize - shift) as usize;
+                    // The tokens left over after the last statement may start one now.
+                    let i = old_stmts
+                        .iter()
+                        .position(|stmt| stmt.tokens.0 == old_first && !is_left_over(stmt))?;
+                    reused += old_stmts.len() - i;
+                    Some(
+                        old_stmts[i..]
+                            .iter()
+                            .map(|stmt| {
+                                let mut node = stmt.node.clone();
+                                shift_node(&mut node, delta);
+                                TopLevelStmt {
+                                    tokens: (
+                                        (stmt.tokens.0 as isize + shift) as usize,
+                                        (stmt.tokens.1 as isize + shift) as usize,
+                                    ),
+                                    node,
+                                }
+                            })
+                            .collect(),
+                    )
+                },
+            ));
+        } else {
+            let mut holder = Node::new();
+            if unterminated_comment_error(&tokens, &mut holder, &src, &self.dialect) {
+                stmts.push(TopLevelStmt {
+                    tokens: (tokens.len(), tokens.len()),
+                    node: holder.children.pop().unwrap(),
+                });
+            }
+        }
+
+        self.root = program(stmts.iter().map(|stmt| stmt.node.clone()), self.simplified);
+        self.src = src;
+        self.tokens = tokens;
+        self.stmts = stmts;
+        self.reused = reused;
+        Ok(&self.root)
+    }
+}
+
+/// Whether `stmt` stands for the tokens left over after the last statement.
+fn is_left_over(stmt: &TopLevelStmt) -> bool {
+    matches!(
+        stmt.node.n_type,
+        NodeType::Error(ErrorType::UnexpectedToken, _)
+    )
+}
+
+/// Whether `stmt` stands for a comment left open at the end of the source.
+fn is_open_comment(stmt: &TopLevelStmt) -> bool {
+    matches!(
+        stmt.node.n_type,
+        NodeType::Error(ErrorType::LexicalError, _)
+    )
+}
+
+fn shift_span(span: Span, delta: isize) -> Span {
+    (
+        (span.0 as isize + delta) as usize,
+        (span.1 as isize + delta) as usize,
+    )
+}
+
+fn shift_node(node: &mut Node, delta: isize) {
+    // Nodes without any tokens under them (errors, mostly) keep the empty span.
+    if node.span.0 != usize::MAX {
+        node.span = shift_span(node.span, delta);
+    }
+    for child in node.children.iter_mut().chain(node.nextstmt.iter_mut()) {
+        shift_node(child, delta);
+    }
+}
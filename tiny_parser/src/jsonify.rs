@@ -0,0 +1,99 @@
+use crate::{Dialect, Node, NodeType};
+use json::{array, object, JsonValue};
+use tiny_lexer::lexer::Token;
+
+pub fn jsonify_node(node: &Node) -> JsonValue {
+    let mut node_arr: Vec<JsonValue> = vec![];
+    let mut nextnode_arr: Vec<JsonValue> = vec![];
+
+    for child in &node.children {
+        node_arr.push(jsonify_node(child));
+    }
+    for child in &node.nextstmt {
+        nextnode_arr.push(jsonify_node(child));
+    }
+
+    let mut json = object! {
+        "type" => type_name(&node.n_type),
+        "span" => array![node.span.0, node.span.1],
+        "children" => JsonValue::Array(node_arr),
+        "nextstmt" => JsonValue::Array(nextnode_arr),
+    };
+    if let NodeType::Number(value) = node.n_type {
+        json["value"] = value.into();
+    }
+    json
+}
+
+/// The node type as shown to front ends; numbers carry their value separately.
+fn type_name(n_type: &NodeType) -> String {
+    match n_type {
+        NodeType::Number(_) => "Number".to_string(),
+        n_type => format!("{:?}", n_type),
+    }
+}
+
+pub fn jsonify_tokens(tokens: &[Token], src: &str) -> JsonValue {
+    let mut token_arr: Vec<JsonValue> = vec![];
+
+    for tok in tokens {
+        let (i0, i1) = tok.get_span();
+        token_arr.push(object! {
+            "type" => tok.type_name(),
+            "span" => array![i0, i1],
+            "content" => &src[i0..i1],
+        });
+    }
+
+    JsonValue::Array(token_arr)
+}
+
+/// Converts a tree into the node structure drawn by the Treant.js viewer. Statements
+/// chained through `nextstmt` are drawn as siblings of the first one.
+pub fn treantify_node(node: &Node, src: &str) -> JsonValue {
+    treantify_node_with(node, src, &Dialect::default())
+}
+
+/// Same as `treantify_node`, for a tree parsed in the given dialect.
+pub fn treantify_node_with(node: &Node, src: &str, dialect: &Dialect) -> JsonValue {
+    let mut node_arr: Vec<JsonValue> = vec![];
+
+    for child in &node.children {
+        let mut curr = Some(child);
+        while let Some(stmt) = curr {
+            node_arr.push(treantify_node_with(stmt, src, dialect));
+            curr = stmt.nextstmt.first();
+        }
+    }
+
+    let mut text = object! {
+        "type" => type_name(&node.n_type),
+    };
+    let html_class = match &node.n_type {
+        NodeType::Stmt(_) | NodeType::Elsif => "stmt",
+        NodeType::Op(_) => {
+            text["name"] = node.op_symbol(src, dialect).into();
+            "op"
+        }
+        NodeType::Identifier
+        | NodeType::Number(_)
+        | NodeType::String
+        | NodeType::Keyword
+        | NodeType::Symbol => {
+            text["name"] = src[node.span.0..node.span.1].into();
+            "leaf"
+        }
+        NodeType::Error(_, message) => {
+            text["desc"] = message.as_str().into();
+            "error"
+        }
+        _ => "",
+    };
+
+    object! {
+        "text" => text,
+        "span" => array![node.span.0, node.span.1],
+        "HTMLclass" => html_class,
+        "children" => JsonValue::Array(node_arr),
+    }
+}
@@ -1,21 +1,44 @@
+pub mod ast;
+pub mod desugar;
+pub mod fold;
+pub mod incremental;
+pub mod jsonify;
+pub mod pretty;
+pub mod semantic;
+pub mod trace;
+
 use std::cmp::{max, min};
 use std::iter::Peekable;
 use std::slice::Iter;
-use std::usize;
-use tiny_lexer::lexer::{tokenize, Span, Token};
+pub use tiny_lexer::dialect::{Dialect, IntWidth};
+use tiny_lexer::lexer::{
+    number_value, string_value, tokenize_from, tokenize_with, unterminated_comment, LexError, Span,
+    Token,
+};
+use trace::{Rule, Trace};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NodeType {
     Program,
     StmtSeq,
     Stmt(StmtType),
+    Elsif,
+    Params,
+    Args,
+    Index,
     Op(OpType),
     Term,
+    UnaryExp,
     Factor,
+    BoolExp,
+    AndExp,
+    NotExp,
     Exp,
     SimplExp,
     Identifier,
-    Number,
+    /// A number literal with its value, its sign aside.
+    Number(u64),
+    String,
     Keyword,
     Error(ErrorType, String),
     Null,
@@ -24,38 +47,60 @@ pub enum NodeType {
     ClosingBrace,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum StmtType {
     IfStmt,
     RepeatStmt,
+    WhileStmt,
+    ProcDecl,
+    CallStmt,
+    ArrayDecl,
     WriteStmt,
     ReadStmt,
     AssignStmt,
     Illegal,
 }
 
-#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum OpType {
     MulOp,
     AddOp,
     CompOp,
+    OrOp,
+    AndOp,
+    NotOp,
+    UnaryOp,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ErrorType {
     IllegalStmt,
     UnexpectedEOF,
     MissingThenKeyword,
     MissingUntilKeyword,
+    MissingDoKeyword,
     MissingAssignOp,
+    MissingOpeningBracket,
     MissingClosingBracket,
     IllegalFactor,
     NonEndedIfStmt,
+    NonEndedWhileStmt,
+    NonEndedProcedure,
+    ExpectedArraySize,
     ExpectedFactor,
     ExpectedIdentifier,
+    LexicalError,
+    NumberOutOfRange,
+    UnexpectedToken,
+    NestedTooDeeply,
 }
 
-#[derive(Debug, Clone)]
+/// The parser's rules nest at most this deep, so that deeply nested statements and
+/// expressions are errors instead of running out of stack.
+pub(crate) const MAX_RULE_DEPTH: usize = 500;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Node {
     pub span: Span,
     pub n_type: NodeType,
@@ -63,7 +108,7 @@ pub struct Node {
     pub children: Vec<Node>,
 }
 
-impl<'a> Node {
+impl Node {
     fn new() -> Node {
         Node {
             span: (usize::MAX, 0),
@@ -73,7 +118,7 @@ impl<'a> Node {
         }
     }
 
-    fn set_nextstmt(&mut self, next: Node){
+    fn set_nextstmt(&mut self, next: Node) {
         self.nextstmt.push(next);
     }
 
@@ -83,112 +128,346 @@ impl<'a> Node {
         self.children.push(child);
     }
 
+    /// Moves this node down to be the only child of a fresh node in its place, so
+    /// that chained operators in the simplified tree nest to the left.
+    fn nest(&mut self) {
+        let lhs = std::mem::replace(self, Node::new());
+        self.add_child(lhs);
+    }
+
     fn reduce(&self) -> Node {
-        self.children.get(0).unwrap().clone()
+        self.children.first().unwrap().clone()
+    }
+
+    /// The operator token of an `Op` node in a tree parsed from `src` in `dialect`.
+    /// A full tree's op node spans just that token, a simplified one runs from it
+    /// to the end of its right operand.
+    pub fn op_symbol<'a>(&self, src: &'a str, dialect: &Dialect) -> &'a str {
+        // Lex just the one token the node starts with.
+        let start = self.span.0;
+        let (tokens, _) = tokenize_from(src, start, false, dialect, |offset| offset > start);
+        let (i0, i1) = tokens.first().map_or(self.span, Token::get_span);
+        &src[i0..i1]
+    }
+
+    fn is_error(&self) -> bool {
+        matches!(self.n_type, NodeType::Error(_, _))
+    }
+
+    /// Whether the node or anything under it is an error.
+    fn has_error(&self) -> bool {
+        self.is_error()
+            || self
+                .children
+                .iter()
+                .chain(self.nextstmt.iter())
+                .any(Node::has_error)
     }
 }
 
+/// Parses `src` as textbook TINY; `parse_with` takes the extensions.
 pub fn parse(src: &str, simplified: bool) -> Node {
-    let tokens: Vec<Token> = tokenize(src, false);
-    let mut token_iter = Box::new(tokens.iter()).peekable();
+    parse_with(src, simplified, &Dialect::default())
+}
+
+/// Same as `parse`, for the given dialect.
+pub fn parse_with(src: &str, simplified: bool, dialect: &Dialect) -> Node {
+    let tokens: Vec<Token> = tokenize_with(src, false, dialect);
+    let stmts = top_level_stmts(
+        &tokens,
+        0,
+        src,
+        simplified,
+        dialect,
+        &mut Trace::disabled(),
+        |_| None,
+    );
+    program(stmts.into_iter().map(|stmt| stmt.node), simplified)
+}
+
+/// A statement of the program's outermost statement sequence, along with the
+/// range of token indices it was parsed from: `tokens.0` is its first token and
+/// `tokens.1` the lookahead token that ended it.
+#[derive(Debug, Clone)]
+pub(crate) struct TopLevelStmt {
+    pub(crate) tokens: (usize, usize),
+    pub(crate) node: Node,
+}
+
+/// Parses the outermost statement sequence starting at token index `start`.
+/// Before each statement, `reuse` gets the chance to supply the rest of the
+/// sequence from a previous parse instead. The `program` and `stmt_seq` rules
+/// the statements make up are recorded in `trace`.
+pub(crate) fn top_level_stmts<F>(
+    tokens: &[Token],
+    start: usize,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+    mut reuse: F,
+) -> Vec<TopLevelStmt>
+where
+    F: FnMut(usize) -> Option<Vec<TopLevelStmt>>,
+{
+    let mut token_iter = Box::new(tokens[start..].iter()).peekable();
+    let mut stmts = vec![];
+    let mut reused = false;
+    trace.enter(Rule::Program, &token_iter);
+    trace.enter(Rule::StmtSeq, &token_iter);
+
+    loop {
+        let first = tokens.len() - token_iter.len();
+        if let Some(rest) = reuse(first) {
+            stmts.extend(rest);
+            reused = true;
+            break;
+        }
+
+        let mut holder = Node::new();
+        stmt(
+            &mut token_iter,
+            &mut holder,
+            src,
+            simplified,
+            dialect,
+            trace,
+        );
+        stmts.push(TopLevelStmt {
+            tokens: (first, tokens.len() - token_iter.len()),
+            node: holder.children.pop().unwrap(),
+        });
+
+        if match_tok(token_iter.peek(), ";", src) {
+            token_iter.next();
+        } else {
+            break;
+        }
+    }
+    trace.exit(&token_iter);
+    trace.exit(&token_iter);
+
+    // What's reused ends like the previous parse did, up to the comment that
+    // may or may not be closed now.
+    let first = tokens.len() - token_iter.len();
+    let mut holder = Node::new();
+    let last = stmts.last().map(|stmt| &stmt.node);
+    if !reused && unexpected_token(&mut token_iter, &mut holder, src, last) {
+        stmts.push(TopLevelStmt {
+            tokens: (first, tokens.len()),
+            node: holder.children.pop().unwrap(),
+        });
+    }
+    if unterminated_comment_error(tokens, &mut holder, src, dialect) {
+        stmts.push(TopLevelStmt {
+            tokens: (tokens.len(), tokens.len()),
+            node: holder.children.pop().unwrap(),
+        });
+    }
+    stmts
+}
+
+/// Adds an error to `parent_node` if any tokens are left after the program's
+/// statements, which is where a missing `;` between two statements ends it.
+/// After a `last` statement that failed, they're what it couldn't parse and
+/// already has an error for. Returns whether an error was added.
+pub(crate) fn unexpected_token(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    last: Option<&Node>,
+) -> bool {
+    let token = match token_iter.next() {
+        Some(token) => token,
+        None => return false,
+    };
+    token_iter.for_each(drop);
+    if last.is_some_and(Node::has_error) {
+        return false;
+    }
+    add_error(parent_node, ErrorType::UnexpectedToken, "Unexpected Token Error:\nExpected ';' or the end of the program after a statement. Found: '".to_string() + get_tok_content(token, src) + "'\nSuggested Fix:\tSeparate the statements with a semicolon(';').");
+    true
+}
+
+/// Adds an error to `parent_node` if the program ends inside a `{` comment, which
+/// would otherwise hide whatever it was meant to end before. Returns whether an
+/// error was added.
+pub(crate) fn unterminated_comment_error(
+    tokens: &[Token],
+    parent_node: &mut Node,
+    src: &str,
+    dialect: &Dialect,
+) -> bool {
+    match unterminated_comment(src, tokens, dialect) {
+        Some(err) => {
+            add_error(
+                parent_node,
+                ErrorType::LexicalError,
+                lexical_error_message(&err),
+            );
+            true
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn program<I>(stmts: I, simplified: bool) -> Node
+where
+    I: IntoIterator<Item = Node>,
+{
     let mut program_node = Node::new();
     program_node.n_type = NodeType::Program;
-    stmt_seq(&mut token_iter, &mut program_node, src, simplified);
+    attach_stmt_seq(&mut program_node, stmts, simplified);
     program_node
 }
 
-fn stmt_seq<'a>(
+fn stmt_seq(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
-    let mut stmt_seq_node = Node::new();
-    stmt_seq_node.n_type = NodeType::StmtSeq;
-    let mut next_parent_node = parent_node;
+    trace.enter(Rule::StmtSeq, token_iter);
+    let mut stmts = vec![];
+    let mut holder = Node::new();
 
-    if simplified {
-        stmt(token_iter, next_parent_node, src, simplified, true);
-        next_parent_node = next_parent_node.children.last_mut().unwrap();
-    } else {
-        stmt(token_iter, &mut stmt_seq_node, src, simplified, true);
+    stmt(token_iter, &mut holder, src, simplified, dialect, trace);
+    while match_tok(token_iter.peek(), ";", src) {
+        token_iter.next();
+        stmt(token_iter, &mut holder, src, simplified, dialect, trace);
     }
+    stmts.append(&mut holder.children);
 
-    loop {
-        if match_tok(token_iter.peek(), ";", src) {
-            token_iter.next();
-            if simplified {
-                stmt(token_iter, next_parent_node, src, simplified, false);
-                next_parent_node = next_parent_node.nextstmt.last_mut().unwrap();
+    attach_stmt_seq(parent_node, stmts, simplified);
+    trace.exit(token_iter);
+}
+
+/// Hangs a parsed statement sequence off `parent_node`. The full tree groups the
+/// statements under a `StmtSeq` node, while the simplified tree makes the first one
+/// a child of the parent and chains the rest through `nextstmt`. Statements that
+/// failed to parse are attached as children of the statement before them.
+fn attach_stmt_seq<I>(parent_node: &mut Node, stmts: I, simplified: bool)
+where
+    I: IntoIterator<Item = Node>,
+{
+    let mut stmts = stmts.into_iter();
+
+    if simplified {
+        let first = match stmts.next() {
+            Some(first) => first,
+            None => return,
+        };
+        parent_node.add_child(first);
+        let mut next_parent_node = parent_node.children.last_mut().unwrap();
+        for stmt_node in stmts {
+            if stmt_node.is_error() {
+                next_parent_node.add_child(stmt_node);
             } else {
-                stmt(token_iter, &mut stmt_seq_node, src, simplified, true);
-            }
-        } else {
-            if !simplified {
-                next_parent_node.add_child(stmt_seq_node);
+                next_parent_node.set_nextstmt(stmt_node);
+                next_parent_node = next_parent_node.nextstmt.last_mut().unwrap();
             }
-            break;
         }
+    } else {
+        let mut stmt_seq_node = Node::new();
+        stmt_seq_node.n_type = NodeType::StmtSeq;
+        for stmt_node in stmts {
+            stmt_seq_node.add_child(stmt_node);
+        }
+        parent_node.add_child(stmt_seq_node);
     }
 }
 
-fn stmt<'a>(
+fn stmt(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
-    child: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::Stmt, token_iter);
+    if trace.too_deep() {
+        skip_stmt(token_iter, src);
+        add_error(
+            parent_node,
+            ErrorType::NestedTooDeeply,
+            NESTED_TOO_DEEPLY.to_string(),
+        );
+        trace.exit(token_iter);
+        return;
+    }
     let mut stmt_node = Node::new();
     let mut err = false;
     if let Some(&token) = token_iter.peek() {
         let mut stmt_type = StmtType::Illegal;
-        match get_tok_content(token, src) {
+        let keyword = match token {
+            Token::RESERVED(_) => get_tok_content(token, src).to_ascii_lowercase(),
+            _ => String::new(),
+        };
+        match keyword.as_str() {
             "if" => {
                 stmt_type = StmtType::IfStmt;
-                if_stmt(token_iter, &mut stmt_node, src, simplified);
+                if_stmt(token_iter, &mut stmt_node, src, simplified, dialect, trace);
             }
             "repeat" => {
                 stmt_type = StmtType::RepeatStmt;
-                repeat_stmt(token_iter, &mut stmt_node, src, simplified);
+                repeat_stmt(token_iter, &mut stmt_node, src, simplified, dialect, trace);
+            }
+            "while" => {
+                stmt_type = StmtType::WhileStmt;
+                while_stmt(token_iter, &mut stmt_node, src, simplified, dialect, trace);
+            }
+            "procedure" => {
+                stmt_type = StmtType::ProcDecl;
+                proc_decl(token_iter, &mut stmt_node, src, simplified, dialect, trace);
+            }
+            "call" => {
+                stmt_type = StmtType::CallStmt;
+                call_stmt(token_iter, &mut stmt_node, src, simplified, dialect, trace);
+            }
+            "array" => {
+                stmt_type = StmtType::ArrayDecl;
+                array_decl(token_iter, &mut stmt_node, src, simplified, dialect, trace);
             }
             "read" => {
                 stmt_type = StmtType::ReadStmt;
-                read_stmt(token_iter, &mut stmt_node, src, simplified);
+                read_stmt(token_iter, &mut stmt_node, src, simplified, dialect, trace);
             }
             "write" => {
                 stmt_type = StmtType::WriteStmt;
-                write_stmt(token_iter, &mut stmt_node, src, simplified);
+                write_stmt(token_iter, &mut stmt_node, src, simplified, dialect, trace);
             }
             _ => {
                 if let Token::IDENTIFIER(_) = token {
                     stmt_type = StmtType::AssignStmt;
-                    assign_stmt(token_iter, &mut stmt_node, src, simplified);
+                    assign_stmt(token_iter, &mut stmt_node, src, simplified, dialect, trace);
                 } else {
                     err = true;
-                    add_error(parent_node, ErrorType::IllegalStmt, "Illegal Statement Error:\nExpected one of {'if', 'read', 'write', 'repeat', Identifier}. Found: '".to_string() + get_tok_content(token, src) + "'\nSuggested Fix:\tCheck if you have a semicolon(';') after your last statement.")
+                    add_error(parent_node, ErrorType::IllegalStmt, "Illegal Statement Error:\nExpected one of {'if', 'read', 'write', 'repeat', 'while', 'procedure', 'call', 'array', Identifier}. Found: '".to_string() + get_tok_content(token, src) + "'\nSuggested Fix:\tCheck if you have a semicolon(';') after your last statement.")
                 }
             }
         }
         if !err {
             stmt_node.n_type = NodeType::Stmt(stmt_type);
-            if !child {
-                parent_node.set_nextstmt(stmt_node);
-            } else {
-                parent_node.add_child(stmt_node);
-            }
+            parent_node.add_child(stmt_node);
         }
     } else {
         add_error(parent_node, ErrorType::IllegalStmt, "Illegal Statement Error:\nSuggested Fix:\tCheck if you have a semicolon(';') after your last statement.".to_string());
     }
+    trace.exit(token_iter);
 }
 
-fn if_stmt<'a>(
+fn if_stmt(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::IfStmt, token_iter);
     if !simplified {
         let mut if_node = Node::new();
         if_node.n_type = NodeType::Keyword;
@@ -198,7 +477,7 @@ fn if_stmt<'a>(
         token_iter.next();
     }
 
-    exp(token_iter, parent_node, src, simplified);
+    bool_exp(token_iter, parent_node, src, simplified, dialect, trace);
 
     if token_iter.peek().is_some() {
         if match_tok(token_iter.peek(), "then", src) {
@@ -217,21 +496,23 @@ fn if_stmt<'a>(
         add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected 'then' after the `if exp ->...<- stmtseq end`. Found: Early EOF".to_string());
     }
 
-    stmt_seq(token_iter, parent_node, src, simplified);
+    stmt_seq(token_iter, parent_node, src, simplified, dialect, trace);
 
-    if token_iter.peek().is_some() {
-        if match_tok(token_iter.peek(), "else", src) {
-            if !simplified {
-                let mut else_node = Node::new();
-                else_node.n_type = NodeType::Keyword;
-                else_node.span = token_iter.next().unwrap().get_span();
-                parent_node.add_child(else_node);
-            } else {
-                token_iter.next();
-            }
+    while is_elsif(token_iter.peek(), src) {
+        elsif_clause(token_iter, parent_node, src, simplified, dialect, trace);
+    }
 
-            stmt_seq(token_iter, parent_node, src, simplified);
+    if match_tok(token_iter.peek(), "else", src) {
+        if !simplified {
+            let mut else_node = Node::new();
+            else_node.n_type = NodeType::Keyword;
+            else_node.span = token_iter.next().unwrap().get_span();
+            parent_node.add_child(else_node);
+        } else {
+            token_iter.next();
         }
+
+        stmt_seq(token_iter, parent_node, src, simplified, dialect, trace);
     }
 
     if token_iter.peek().is_some() {
@@ -250,14 +531,64 @@ fn if_stmt<'a>(
     } else {
         add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected 'end' keyword to close the If Statement, Found: Early EOF\nSuggested Fix:\tAdd 'end' in its respective place.".to_string());
     }
+    trace.exit(token_iter);
 }
 
-fn repeat_stmt<'a>(
+/// `elsif exp then stmt_seq`, one clause of an if statement's else-if chain. Each
+/// clause becomes an `Elsif` node between the then part and the else part.
+fn elsif_clause(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::ElsifClause, token_iter);
+    let mut elsif_node = Node::new();
+    elsif_node.n_type = NodeType::Elsif;
+    if !simplified {
+        let mut keyword_node = Node::new();
+        keyword_node.n_type = NodeType::Keyword;
+        keyword_node.span = token_iter.next().unwrap().get_span();
+        elsif_node.add_child(keyword_node);
+    } else {
+        token_iter.next();
+    }
+
+    bool_exp(token_iter, &mut elsif_node, src, simplified, dialect, trace);
+
+    if token_iter.peek().is_some() {
+        if match_tok(token_iter.peek(), "then", src) {
+            if !simplified {
+                let mut then_node = Node::new();
+                then_node.n_type = NodeType::Keyword;
+                then_node.span = token_iter.next().unwrap().get_span();
+                elsif_node.add_child(then_node);
+            } else {
+                token_iter.next();
+            }
+        } else {
+            add_error(&mut elsif_node, ErrorType::MissingThenKeyword, "IllegalIfStatementSyntax:\nExpected 'then' after the `elsif exp ->...<- stmtseq`. Found: '".to_string() + get_tok_content(token_iter.peek().unwrap(), src) + "'\nSuggested Fix:\tAdd the missing 'then' keyword.");
+        }
+    } else {
+        add_error(&mut elsif_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected 'then' after the `elsif exp ->...<- stmtseq`. Found: Early EOF".to_string());
+    }
+
+    stmt_seq(token_iter, &mut elsif_node, src, simplified, dialect, trace);
+    parent_node.add_child(elsif_node);
+    trace.exit(token_iter);
+}
+
+fn repeat_stmt(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::RepeatStmt, token_iter);
     if !simplified {
         let mut repeat_node = Node::new();
         repeat_node.n_type = NodeType::Keyword;
@@ -267,7 +598,7 @@ fn repeat_stmt<'a>(
         token_iter.next();
     }
 
-    stmt_seq(token_iter, parent_node, src, simplified);
+    stmt_seq(token_iter, parent_node, src, simplified, dialect, trace);
 
     if token_iter.peek().is_some() {
         if match_tok(token_iter.peek(), "until", src) {
@@ -285,16 +616,325 @@ fn repeat_stmt<'a>(
     } else {
         add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected 'until' at `repeat stmtseq ->....<- exp, Found: Early EOF\nSuggested Fix:\tAdd 'until' at its respective place.".to_string());
     }
-    exp(token_iter, parent_node, src, simplified);
+    bool_exp(token_iter, parent_node, src, simplified, dialect, trace);
+    trace.exit(token_iter);
+}
+
+fn while_stmt(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::WhileStmt, token_iter);
+    if !simplified {
+        let mut while_node = Node::new();
+        while_node.n_type = NodeType::Keyword;
+        while_node.span = token_iter.next().unwrap().get_span();
+        parent_node.add_child(while_node);
+    } else {
+        token_iter.next();
+    }
+
+    bool_exp(token_iter, parent_node, src, simplified, dialect, trace);
+
+    if token_iter.peek().is_some() {
+        if match_tok(token_iter.peek(), "do", src) {
+            if !simplified {
+                let mut do_node = Node::new();
+                do_node.n_type = NodeType::Keyword;
+                do_node.span = token_iter.next().unwrap().get_span();
+                parent_node.add_child(do_node);
+            } else {
+                token_iter.next();
+            }
+        } else {
+            add_error(parent_node, ErrorType::MissingDoKeyword, "IllegalWhileStatementSyntax:\nExpected 'do' after the `while exp ->...<- stmtseq end`. Found: '".to_string() + get_tok_content(token_iter.peek().unwrap(), src) + "'\nSuggested Fix:\tAdd the missing 'do' keyword.");
+        }
+    } else {
+        add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected 'do' after the `while exp ->...<- stmtseq end`. Found: Early EOF".to_string());
+    }
+
+    stmt_seq(token_iter, parent_node, src, simplified, dialect, trace);
+
+    if token_iter.peek().is_some() {
+        if match_tok(token_iter.peek(), "end", src) {
+            if !simplified {
+                let mut end_node = Node::new();
+                end_node.n_type = NodeType::Keyword;
+                end_node.span = token_iter.next().unwrap().get_span();
+                parent_node.add_child(end_node);
+            } else {
+                token_iter.next();
+            }
+        } else {
+            add_error(parent_node, ErrorType::NonEndedWhileStmt, "NonEndedWhileStmt: All While Statements should be ended with the 'end'.\n\tSuggested Fix:\tAdd 'end' in its respective place.".to_string());
+        }
+    } else {
+        add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected 'end' keyword to close the While Statement, Found: Early EOF\nSuggested Fix:\tAdd 'end' in its respective place.".to_string());
+    }
+    trace.exit(token_iter);
+}
+
+/// `procedure identifier ( [identifier { , identifier }] ) stmt_seq end`. The
+/// simplified tree keeps the name, a `Params` node and the body.
+fn proc_decl(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::ProcDecl, token_iter);
+    if !simplified {
+        let mut procedure_node = Node::new();
+        procedure_node.n_type = NodeType::Keyword;
+        procedure_node.span = token_iter.next().unwrap().get_span();
+        parent_node.add_child(procedure_node);
+    } else {
+        token_iter.next();
+    }
+
+    if let Some(Token::IDENTIFIER(_)) = token_iter.peek() {
+        identifier(token_iter, parent_node);
+    } else {
+        add_error(parent_node, ErrorType::ExpectedIdentifier, "IllegalProcedureSyntax:\nExpected the procedure's name at `procedure ->....<- (params)`.\nSuggested Fix:\tName the procedure.".to_string());
+    }
+
+    let mut params_node = Node::new();
+    params_node.n_type = NodeType::Params;
+    parenthesized_list(
+        token_iter,
+        &mut params_node,
+        src,
+        simplified,
+        dialect,
+        trace,
+        |token_iter, node, src, _, _, _| param(token_iter, node, src),
+    );
+    parent_node.add_child(params_node);
+
+    stmt_seq(token_iter, parent_node, src, simplified, dialect, trace);
+
+    if token_iter.peek().is_some() {
+        if match_tok(token_iter.peek(), "end", src) {
+            if !simplified {
+                let mut end_node = Node::new();
+                end_node.n_type = NodeType::Keyword;
+                end_node.span = token_iter.next().unwrap().get_span();
+                parent_node.add_child(end_node);
+            } else {
+                token_iter.next();
+            }
+        } else {
+            add_error(parent_node, ErrorType::NonEndedProcedure, "NonEndedProcedure: All Procedures should be ended with the 'end'.\n\tSuggested Fix:\tAdd 'end' in its respective place.".to_string());
+        }
+    } else {
+        add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected 'end' keyword to close the Procedure, Found: Early EOF\nSuggested Fix:\tAdd 'end' in its respective place.".to_string());
+    }
+    trace.exit(token_iter);
+}
+
+fn param(token_iter: &mut Peekable<Box<Iter<Token>>>, parent_node: &mut Node, src: &str) {
+    match token_iter.peek() {
+        Some(Token::IDENTIFIER(_)) => identifier(token_iter, parent_node),
+        Some(tok) => {
+            add_error(parent_node, ErrorType::ExpectedIdentifier, "IllegalProcedureSyntax:\nExpected a parameter name. Found: '".to_string() + get_tok_content(tok, src) + "'\nSuggested Fix:\tParameters are identifiers separated by commas.");
+            // Skip it, unless it can end the parameter anyway.
+            if !match_tok(Some(tok), ",", src) && !match_tok(Some(tok), ")", src) {
+                token_iter.next();
+            }
+        }
+        None => add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected a parameter name. Found: EOF\nSuggested Fix:\tClose the parameter list with ')'.".to_string()),
+    }
+}
+
+/// `call identifier ( [bool_exp { , bool_exp }] )`. The simplified tree keeps the
+/// name and an `Args` node.
+fn call_stmt(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::CallStmt, token_iter);
+    if !simplified {
+        let mut call_node = Node::new();
+        call_node.n_type = NodeType::Keyword;
+        call_node.span = token_iter.next().unwrap().get_span();
+        parent_node.add_child(call_node);
+    } else {
+        token_iter.next();
+    }
+
+    if let Some(Token::IDENTIFIER(_)) = token_iter.peek() {
+        identifier(token_iter, parent_node);
+    } else {
+        add_error(parent_node, ErrorType::ExpectedIdentifier, "IllegalCallSyntax:\nExpected the procedure's name at `call ->....<- (args)`.\nSuggested Fix:\tAdd the name of the procedure to call.".to_string());
+    }
+
+    let mut args_node = Node::new();
+    args_node.n_type = NodeType::Args;
+    parenthesized_list(
+        token_iter,
+        &mut args_node,
+        src,
+        simplified,
+        dialect,
+        trace,
+        bool_exp,
+    );
+    parent_node.add_child(args_node);
+    trace.exit(token_iter);
+}
+
+/// Parses `( [item { , item }] )` into `list_node`, which keeps the brackets and
+/// commas only in the full tree.
+fn parenthesized_list<F>(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    list_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+    mut item: F,
+) where
+    F: FnMut(&mut Peekable<Box<Iter<Token>>>, &mut Node, &str, bool, &Dialect, &mut Trace),
+{
+    if !match_tok(token_iter.peek(), "(", src) {
+        add_error(list_node, ErrorType::MissingOpeningBracket, "MissingOpeningBracket:\nExpected '(' before the list of parameters or arguments.\n\tSuggested Fix: Add '()' even if the list is empty.".to_string());
+        return;
+    }
+    // The brackets give the list its span, even an empty one without them.
+    let open_span = symbol(token_iter, list_node, NodeType::OpeningBrace, simplified);
+    list_node.span.0 = min(list_node.span.0, open_span.0);
+    list_node.span.1 = max(list_node.span.1, open_span.1);
+
+    if !match_tok(token_iter.peek(), ")", src) {
+        item(token_iter, list_node, src, simplified, dialect, trace);
+        while match_tok(token_iter.peek(), ",", src) {
+            symbol(token_iter, list_node, NodeType::Symbol, simplified);
+            item(token_iter, list_node, src, simplified, dialect, trace);
+        }
+    }
+
+    if match_tok(token_iter.peek(), ")", src) {
+        let close_span = symbol(token_iter, list_node, NodeType::ClosingBrace, simplified);
+        list_node.span.1 = max(list_node.span.1, close_span.1);
+    } else {
+        add_error(list_node, ErrorType::MissingClosingBracket, "MissingClosingBracket:\nList of parameters or arguments preceded by an opening bracket '(' but not followed by a closing one ')'.\n\tSuggested Fix: Add a closing bracket ')' at the end of the list.".to_string());
+    }
+}
+
+/// Consumes a token that only shows up in the full tree, as an `n_type` node,
+/// returning its span.
+fn symbol(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    n_type: NodeType,
+    simplified: bool,
+) -> Span {
+    let span = token_iter.next().unwrap().get_span();
+    if !simplified {
+        let mut symbol_node = Node::new();
+        symbol_node.n_type = n_type;
+        symbol_node.span = span;
+        parent_node.add_child(symbol_node);
+    }
+    span
+}
+
+/// `array identifier [ number ]`
+fn array_decl(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::ArrayDecl, token_iter);
+    if !simplified {
+        let mut array_node = Node::new();
+        array_node.n_type = NodeType::Keyword;
+        array_node.span = token_iter.next().unwrap().get_span();
+        parent_node.add_child(array_node);
+    } else {
+        token_iter.next();
+    }
+
+    if let Some(Token::IDENTIFIER(_)) = token_iter.peek() {
+        identifier(token_iter, parent_node);
+    } else {
+        add_error(parent_node, ErrorType::ExpectedIdentifier, "IllegalArraySyntax:\nExpected the array's name at `array ->....<- [size]`.\nSuggested Fix:\tName the array.".to_string());
+    }
+
+    if let Some(Token::LBRACKET(_)) = token_iter.peek() {
+        let open_span = symbol(token_iter, parent_node, NodeType::Symbol, simplified);
+        parent_node.span.1 = max(parent_node.span.1, open_span.1);
+        if let Some(Token::NUMBER(_)) = token_iter.peek() {
+            number(token_iter, parent_node, src, dialect, false);
+        } else {
+            add_error(parent_node, ErrorType::ExpectedArraySize, "IllegalArraySyntax:\nExpected the array's size at `array name[->....<-]`.\nSuggested Fix:\tThe size has to be a number.".to_string());
+        }
+        if let Some(Token::RBRACKET(_)) = token_iter.peek() {
+            let close_span = symbol(token_iter, parent_node, NodeType::Symbol, simplified);
+            parent_node.span.1 = max(parent_node.span.1, close_span.1);
+        } else {
+            add_error(parent_node, ErrorType::MissingClosingBracket, "MissingClosingBracket:\nArray size preceded by an opening bracket '[' but not followed by a closing one ']'.\n\tSuggested Fix: Add a closing bracket ']' after the size.".to_string());
+        }
+    } else {
+        add_error(parent_node, ErrorType::ExpectedArraySize, "IllegalArraySyntax:\nExpected '[' and the array's size at `array name ->....<-`.\nSuggested Fix:\tAdd the size, as in `array a[10]`.".to_string());
+    }
+    trace.exit(token_iter);
+}
+
+/// `variable -> identifier [ '[' bool_exp ']' ]`. An indexed variable becomes an
+/// `Index` node holding the identifier and the index expression.
+fn variable(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::Variable, token_iter);
+    let mut index_node = Node::new();
+    identifier(token_iter, &mut index_node);
+
+    if let Some(Token::LBRACKET(_)) = token_iter.peek() {
+        index_node.n_type = NodeType::Index;
+        symbol(token_iter, &mut index_node, NodeType::Symbol, simplified);
+        bool_exp(token_iter, &mut index_node, src, simplified, dialect, trace);
+        if let Some(Token::RBRACKET(_)) = token_iter.peek() {
+            let close_span = symbol(token_iter, &mut index_node, NodeType::Symbol, simplified);
+            index_node.span.1 = max(index_node.span.1, close_span.1);
+        } else {
+            add_error(&mut index_node, ErrorType::MissingClosingBracket, "MissingClosingBracket:\nIndex preceded by an opening bracket '[' but not followed by a closing one ']'.\n\tSuggested Fix: Add a closing bracket ']' after the index.".to_string());
+        }
+        parent_node.add_child(index_node);
+    } else {
+        parent_node.add_child(index_node.reduce());
+    }
+    trace.exit(token_iter);
 }
 
-fn assign_stmt<'a>(
+fn assign_stmt(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
-    identifier(token_iter, parent_node, src);
+    trace.enter(Rule::AssignStmt, token_iter);
+    variable(token_iter, parent_node, src, simplified, dialect, trace);
 
     if token_iter.peek().is_some() {
         if match_tok(token_iter.peek(), ":=", src) {
@@ -313,15 +953,19 @@ fn assign_stmt<'a>(
         add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected AssignmentOperator ':=' at `identifier ->....<- exp`, Found: EOF\nSuggested Fix:\tRemove the dangling identifier at the end of the statement sequence.".to_string());
     }
 
-    exp(token_iter, parent_node, src, simplified);
+    bool_exp(token_iter, parent_node, src, simplified, dialect, trace);
+    trace.exit(token_iter);
 }
 
-fn read_stmt<'a>(
+fn read_stmt(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::ReadStmt, token_iter);
     if !simplified {
         let mut read_node = Node::new();
         read_node.n_type = NodeType::Keyword;
@@ -332,7 +976,7 @@ fn read_stmt<'a>(
     }
     if let Some(token) = token_iter.peek() {
         if let Token::IDENTIFIER(_) = token {
-            identifier(token_iter, parent_node, src);
+            variable(token_iter, parent_node, src, simplified, dialect, trace);
         } else {
             add_error(
                 parent_node,
@@ -346,14 +990,18 @@ fn read_stmt<'a>(
     } else {
         add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected an identifier at `read ->....<-`. Found: EOF\nSuggested Fix:\tRemove the dangling 'read' keyword.".to_string());
     }
+    trace.exit(token_iter);
 }
 
-fn write_stmt<'a>(
+fn write_stmt(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::WriteStmt, token_iter);
     if !simplified {
         let mut write_node = Node::new();
         write_node.n_type = NodeType::Keyword;
@@ -362,19 +1010,82 @@ fn write_stmt<'a>(
     } else {
         token_iter.next();
     }
-    if let Some(_) = token_iter.peek() {
-        exp(token_iter, parent_node, src, simplified);
-    } else {
-        add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected an expression at `write ->....<-`. Found: EOF\nSuggested Fix:\tRemove the dangling 'write' keyword.".to_string());
+    write_item(token_iter, parent_node, src, simplified, dialect, trace);
+    while dialect.strings && match_tok(token_iter.peek(), ",", src) {
+        symbol(token_iter, parent_node, NodeType::Symbol, simplified);
+        write_item(token_iter, parent_node, src, simplified, dialect, trace);
+    }
+    trace.exit(token_iter);
+}
+
+/// A string literal or an expression.
+fn write_item(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    match token_iter.peek() {
+        Some(Token::STRING(_)) => string(token_iter, parent_node, src),
+        Some(_) => bool_exp(token_iter, parent_node, src, simplified, dialect, trace),
+        None => add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected a string or an expression at `write ->....<-`. Found: EOF\nSuggested Fix:\tRemove the dangling 'write' keyword or ','.".to_string()),
+    }
+}
+
+fn or_op(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::OrOp, token_iter);
+    if match_tok(token_iter.peek(), "or", src) {
+        if !simplified {
+            let mut orop_node = Node::new();
+            orop_node.n_type = NodeType::Op(OpType::OrOp);
+            orop_node.span = token_iter.next().unwrap().get_span();
+            parent_node.add_child(orop_node);
+        } else {
+            parent_node.n_type = NodeType::Op(OpType::OrOp);
+            parent_node.span = token_iter.next().unwrap().get_span();
+        }
+    }
+    trace.exit(token_iter);
+}
+
+fn and_op(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::AndOp, token_iter);
+    if match_tok(token_iter.peek(), "and", src) {
+        if !simplified {
+            let mut andop_node = Node::new();
+            andop_node.n_type = NodeType::Op(OpType::AndOp);
+            andop_node.span = token_iter.next().unwrap().get_span();
+            parent_node.add_child(andop_node);
+        } else {
+            parent_node.n_type = NodeType::Op(OpType::AndOp);
+            parent_node.span = token_iter.next().unwrap().get_span();
+        }
     }
+    trace.exit(token_iter);
 }
 
-fn add_op<'a>(
+fn add_op(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::AddOp, token_iter);
     if token_iter.peek().is_some() {
         if match_tok(token_iter.peek(), "+", src) || match_tok(token_iter.peek(), "-", src) {
             if !simplified {
@@ -390,14 +1101,17 @@ fn add_op<'a>(
     } else {
         add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected AdditionOperator ( '+' , '-' ) at `term ->....<- term`. Found: EOF\nSuggested Fix:\tRemove the dangling term.".to_string());
     }
+    trace.exit(token_iter);
 }
 
-fn mulop<'a>(
+fn mulop(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::MulOp, token_iter);
     if token_iter.peek().is_some() {
         if match_tok(token_iter.peek(), "*", src) || match_tok(token_iter.peek(), "/", src) {
             if !simplified {
@@ -413,16 +1127,20 @@ fn mulop<'a>(
     } else {
         add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected MultiplicationOperator ( '*' , '/' ) at `factor ->....<- factor`. Found: EOF\nSuggested Fix:\tRemove the dangling factor.".to_string());
     }
+    trace.exit(token_iter);
 }
 
-fn comp_op<'a>(
+fn comp_op(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::CompOp, token_iter);
     if token_iter.peek().is_some() {
-        if match_tok(token_iter.peek(), "<", src) || match_tok(token_iter.peek(), "=", src) {
+        if is_comp_op(token_iter.peek(), src, dialect) {
             if !simplified {
                 let mut compop_node = Node::new();
                 compop_node.n_type = NodeType::Op(OpType::CompOp);
@@ -434,29 +1152,145 @@ fn comp_op<'a>(
             }
         }
     } else {
-        add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected ComparisonOperator ( '<' , '=' ) at `simple_exp ->....<- simple_exp`. Found: EOF\nSuggested Fix:\tRemove the dangling simple_exp.".to_string());
+        add_error(parent_node, ErrorType::UnexpectedEOF, "Unexpected EOF:\nExpected ComparisonOperator ( '<' , '<=' , '>' , '>=' , '=' , '<>' ) at `simple_exp ->....<- simple_exp`. Found: EOF\nSuggested Fix:\tRemove the dangling simple_exp.".to_string());
     }
+    trace.exit(token_iter);
 }
 
-fn exp<'a>(
+/// `bool_exp -> and_exp { or and_exp }`, the loosest binding expression.
+fn bool_exp(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::BoolExp, token_iter);
+    let mut bexp_node = Node::new();
+    let mut opped = false;
+
+    if !simplified {
+        bexp_node.n_type = NodeType::BoolExp;
+    }
+    and_exp(token_iter, &mut bexp_node, src, simplified, dialect, trace);
+
+    while match_tok(token_iter.peek(), "or", src) {
+        if opped && simplified {
+            bexp_node.nest();
+        }
+        opped = true;
+        or_op(token_iter, &mut bexp_node, src, simplified, trace);
+        and_exp(token_iter, &mut bexp_node, src, simplified, dialect, trace);
+    }
+
+    if !opped {
+        parent_node.add_child(bexp_node.reduce());
+    } else {
+        parent_node.add_child(bexp_node);
+    }
+    trace.exit(token_iter);
+}
+
+/// `and_exp -> not_exp { and not_exp }`
+fn and_exp(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::AndExp, token_iter);
+    let mut aexp_node = Node::new();
+    let mut opped = false;
+
+    if !simplified {
+        aexp_node.n_type = NodeType::AndExp;
+    }
+    not_exp(token_iter, &mut aexp_node, src, simplified, dialect, trace);
+
+    while match_tok(token_iter.peek(), "and", src) {
+        if opped && simplified {
+            aexp_node.nest();
+        }
+        opped = true;
+        and_op(token_iter, &mut aexp_node, src, simplified, trace);
+        not_exp(token_iter, &mut aexp_node, src, simplified, dialect, trace);
+    }
+
+    if !opped {
+        parent_node.add_child(aexp_node.reduce());
+    } else {
+        parent_node.add_child(aexp_node);
+    }
+    trace.exit(token_iter);
+}
+
+/// `not_exp -> not not_exp | exp`. The simplified tree turns every `not` into an
+/// `Op(NotOp)` node with the operand as its only child.
+fn not_exp(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::NotExp, token_iter);
+    if trace.too_deep() {
+        skip_operand(token_iter, src);
+        add_error(
+            parent_node,
+            ErrorType::NestedTooDeeply,
+            NESTED_TOO_DEEPLY.to_string(),
+        );
+    } else if match_tok(token_iter.peek(), "not", src) {
+        let mut nexp_node = Node::new();
+        let not_span = token_iter.next().unwrap().get_span();
+        if !simplified {
+            nexp_node.n_type = NodeType::NotExp;
+            let mut notop_node = Node::new();
+            notop_node.n_type = NodeType::Op(OpType::NotOp);
+            notop_node.span = not_span;
+            nexp_node.add_child(notop_node);
+        } else {
+            nexp_node.n_type = NodeType::Op(OpType::NotOp);
+            nexp_node.span = not_span;
+        }
+        not_exp(token_iter, &mut nexp_node, src, simplified, dialect, trace);
+        parent_node.add_child(nexp_node);
+    } else {
+        exp(token_iter, parent_node, src, simplified, dialect, trace);
+    }
+    trace.exit(token_iter);
+}
+
+fn exp(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+) {
+    trace.enter(Rule::Exp, token_iter);
     let mut exp_node = Node::new();
     let mut opped = false;
 
     if !simplified {
         exp_node.n_type = NodeType::Exp;
     }
-    simple_exp(token_iter, &mut exp_node, src, simplified);
+    simple_exp(token_iter, &mut exp_node, src, simplified, dialect, trace);
 
     loop {
-        if match_tok(token_iter.peek(), "<", src) || match_tok(token_iter.peek(), "=", src) {
+        if is_comp_op(token_iter.peek(), src, dialect) {
+            if opped && simplified {
+                exp_node.nest();
+            }
             opped = true;
-            comp_op(token_iter, &mut exp_node, src, simplified);
-            simple_exp(token_iter, &mut exp_node, src, simplified);
+            comp_op(token_iter, &mut exp_node, src, simplified, dialect, trace);
+            simple_exp(token_iter, &mut exp_node, src, simplified, dialect, trace);
         } else {
             break;
         }
@@ -467,14 +1301,18 @@ fn exp<'a>(
     } else {
         parent_node.add_child(exp_node);
     }
+    trace.exit(token_iter);
 }
 
-fn simple_exp<'a>(
+fn simple_exp(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::SimpleExp, token_iter);
     let mut sexp_node = Node::new();
     let mut opped = false;
 
@@ -482,13 +1320,16 @@ fn simple_exp<'a>(
         sexp_node.n_type = NodeType::SimplExp;
     }
 
-    term(token_iter, &mut sexp_node, src, simplified);
+    term(token_iter, &mut sexp_node, src, simplified, dialect, trace);
 
     loop {
         if match_tok(token_iter.peek(), "+", src) || match_tok(token_iter.peek(), "-", src) {
+            if opped && simplified {
+                sexp_node.nest();
+            }
             opped = true;
-            add_op(token_iter, &mut sexp_node, src, simplified);
-            term(token_iter, &mut sexp_node, src, simplified);
+            add_op(token_iter, &mut sexp_node, src, simplified, trace);
+            term(token_iter, &mut sexp_node, src, simplified, dialect, trace);
         } else {
             break;
         }
@@ -499,14 +1340,18 @@ fn simple_exp<'a>(
     } else {
         parent_node.add_child(sexp_node);
     }
+    trace.exit(token_iter);
 }
 
-fn term<'a>(
+fn term(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
 ) {
+    trace.enter(Rule::Term, token_iter);
     let mut term_node = Node::new();
     let mut opped = false;
 
@@ -514,33 +1359,114 @@ fn term<'a>(
         term_node.n_type = NodeType::Term;
     }
 
-    factor(token_iter, &mut term_node, src, simplified);
+    unary(
+        token_iter,
+        &mut term_node,
+        src,
+        simplified,
+        dialect,
+        trace,
+        false,
+    );
 
     loop {
         if match_tok(token_iter.peek(), "*", src) || match_tok(token_iter.peek(), "/", src) {
+            if opped && simplified {
+                term_node.nest();
+            }
             opped = true;
-            mulop(token_iter, &mut term_node, src, simplified);
-            factor(token_iter, &mut term_node, src, simplified);
+            mulop(token_iter, &mut term_node, src, simplified, trace);
+            unary(
+                token_iter,
+                &mut term_node,
+                src,
+                simplified,
+                dialect,
+                trace,
+                false,
+            );
         } else {
             break;
         }
     }
-    if !simplified {
-//        term_node.value = term_node.get_content(src);
-    }
     if !opped {
         parent_node.add_child(term_node.reduce());
     } else {
         parent_node.add_child(term_node);
     }
+    trace.exit(token_iter);
+}
+
+/// `unary -> ( - | + ) unary | factor`. Like `not`, a sign turns into an
+/// `Op(UnaryOp)` node with the operand as its only child in the simplified tree.
+/// `negated` is whether the unary follows a `-` directly.
+fn unary(
+    token_iter: &mut Peekable<Box<Iter<Token>>>,
+    parent_node: &mut Node,
+    src: &str,
+    simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+    negated: bool,
+) {
+    trace.enter(Rule::Unary, token_iter);
+    if trace.too_deep() {
+        skip_operand(token_iter, src);
+        add_error(
+            parent_node,
+            ErrorType::NestedTooDeeply,
+            NESTED_TOO_DEEPLY.to_string(),
+        );
+    } else if dialect.unary_ops
+        && (match_tok(token_iter.peek(), "-", src) || match_tok(token_iter.peek(), "+", src))
+    {
+        let mut unary_node = Node::new();
+        let negates = match_tok(token_iter.peek(), "-", src);
+        let sign_span = token_iter.next().unwrap().get_span();
+        if !simplified {
+            unary_node.n_type = NodeType::UnaryExp;
+            let mut sign_node = Node::new();
+            sign_node.n_type = NodeType::Op(OpType::UnaryOp);
+            sign_node.span = sign_span;
+            unary_node.add_child(sign_node);
+        } else {
+            unary_node.n_type = NodeType::Op(OpType::UnaryOp);
+            unary_node.span = sign_span;
+        }
+        unary(
+            token_iter,
+            &mut unary_node,
+            src,
+            simplified,
+            dialect,
+            trace,
+            negates,
+        );
+        parent_node.add_child(unary_node);
+    } else {
+        factor(
+            token_iter,
+            parent_node,
+            src,
+            simplified,
+            dialect,
+            trace,
+            negated,
+        );
+    }
+    trace.exit(token_iter);
 }
 
-fn factor<'a>(
+fn factor(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
     simplified: bool,
+    dialect: &Dialect,
+    trace: &mut Trace,
+    negated: bool,
 ) {
+    trace.enter(Rule::Factor, token_iter);
     let mut factor_node = Node::new();
     factor_node.n_type = NodeType::Factor;
 
@@ -548,16 +1474,23 @@ fn factor<'a>(
         match tok {
             Token::NUMBER(_) => {
                 if !simplified {
-                    number(token_iter, &mut factor_node, src);
+                    number(token_iter, &mut factor_node, src, dialect, negated);
                 } else {
-                    number(token_iter, parent_node, src);
+                    number(token_iter, parent_node, src, dialect, negated);
                 }
             }
             Token::IDENTIFIER(_) => {
                 if !simplified {
-                    identifier(token_iter, &mut factor_node, src);
+                    variable(
+                        token_iter,
+                        &mut factor_node,
+                        src,
+                        simplified,
+                        dialect,
+                        trace,
+                    );
                 } else {
-                    identifier(token_iter, parent_node, src);
+                    variable(token_iter, parent_node, src, simplified, dialect, trace);
                 }
             }
             Token::SYMBOL(_) => {
@@ -568,7 +1501,14 @@ fn factor<'a>(
                         open_brace_node.span = token_iter.next().unwrap().get_span();
                         factor_node.add_child(open_brace_node);
 
-                        exp(token_iter, &mut factor_node, src, simplified);
+                        bool_exp(
+                            token_iter,
+                            &mut factor_node,
+                            src,
+                            simplified,
+                            dialect,
+                            trace,
+                        );
                         if match_tok(token_iter.peek(), ")", src) {
                             let mut close_brace_node = Node::new();
                             close_brace_node.n_type = NodeType::ClosingBrace;
@@ -578,7 +1518,13 @@ fn factor<'a>(
                             add_error(parent_node, ErrorType::MissingClosingBracket, "MissingClosingBracket:\nExpression preceded by an opening bracket '(' but not followed by a closing one ')'.\n\tSuggested Fix: Add a closing bracket ')' at the end of the expression.".to_string());
                         }
                     } else {
-                        exp(token_iter, parent_node, src, simplified);
+                        token_iter.next();
+                        bool_exp(token_iter, parent_node, src, simplified, dialect, trace);
+                        if match_tok(token_iter.peek(), ")", src) {
+                            token_iter.next();
+                        } else {
+                            add_error(parent_node, ErrorType::MissingClosingBracket, "MissingClosingBracket:\nExpression preceded by an opening bracket '(' but not followed by a closing one ')'.\n\tSuggested Fix: Add a closing bracket ')' at the end of the expression.".to_string());
+                        }
                     }
                 } else {
                     add_error(parent_node, ErrorType::IllegalFactor, "Illegal Factor: Expected one of {identifier, number, (expression)}, Found none of the above.".to_string());
@@ -595,49 +1541,169 @@ fn factor<'a>(
     if !simplified {
         parent_node.add_child(factor_node);
     }
+    trace.exit(token_iter);
 }
 
-fn number<'a>(
+/// A number literal, or the error for a malformed one or one too large for the
+/// dialect's integers.
+/// A `negated` literal may be one larger, as the most negative integer has no
+/// positive counterpart.
+fn number(
     token_iter: &mut Peekable<Box<Iter<Token>>>,
     parent_node: &mut Node,
-    src: &'a str,
+    src: &str,
+    dialect: &Dialect,
+    negated: bool,
 ) {
+    let span = token_iter.next().unwrap().get_span();
+    let width = dialect.int_width;
+    let limit = if negated {
+        width.min().unsigned_abs()
+    } else {
+        width.max() as u64
+    };
+
     let mut number_node = Node::new();
-    number_node.n_type = NodeType::Number;
-    number_node.span = token_iter.next().unwrap().get_span();
+    match number_value(src, span) {
+        Ok(value) if value <= limit => number_node.n_type = NodeType::Number(value),
+        Err(err) => {
+            number_node.n_type =
+                NodeType::Error(ErrorType::LexicalError, lexical_error_message(&err));
+        }
+        Ok(_) => {
+            number_node.n_type = NodeType::Error(
+                ErrorType::NumberOutOfRange,
+                format!(
+                    "Number Out Of Range:\n'{}' doesn't fit in a {}-bit integer, which holds {}..{}.\nSuggested Fix:\tUse a number in that range.",
+                    &src[span.0..span.1],
+                    width.bits(),
+                    width.min(),
+                    width.max()
+                ),
+            );
+        }
+    }
+    number_node.span = span;
     parent_node.add_child(number_node);
 }
 
-fn identifier<'a>(
-    token_iter: &mut Peekable<Box<Iter<Token>>>,
-    parent_node: &mut Node,
-    src: &'a str,
-) {
+/// A string literal, or the lexical error that makes it invalid.
+fn string(token_iter: &mut Peekable<Box<Iter<Token>>>, parent_node: &mut Node, src: &str) {
+    let span = token_iter.next().unwrap().get_span();
+    let mut string_node = Node::new();
+    match string_value(src, span) {
+        Ok(_) => string_node.n_type = NodeType::String,
+        Err(err) => {
+            string_node.n_type =
+                NodeType::Error(ErrorType::LexicalError, lexical_error_message(&err));
+        }
+    }
+    string_node.span = span;
+    parent_node.add_child(string_node);
+}
+
+fn identifier(token_iter: &mut Peekable<Box<Iter<Token>>>, parent_node: &mut Node) {
     let mut id_node = Node::new();
     id_node.n_type = NodeType::Identifier;
     id_node.span = token_iter.next().unwrap().get_span();
     parent_node.add_child(id_node);
 }
 
-fn match_tok(opt_tok: Option<&&Token>, res: &str, src: &str) -> bool {
-    if let Some(tok) = opt_tok {
-        if get_tok_content(tok, src) == res {
-            return true;
+const NESTED_TOO_DEEPLY: &str = "Nesting Error:\nThe statement or expression is nested too deeply to be parsed.\nSuggested Fix:\tBreak it up, with variables holding the values of the inner expressions.";
+
+/// Skips a statement nested too deeply to parse, along with the statements nested
+/// in it, up to the `;` or keyword that ends it.
+fn skip_stmt(token_iter: &mut Peekable<Box<Iter<Token>>>, src: &str) {
+    let mut open = 0;
+    while let Some(&token) = token_iter.peek() {
+        let keyword = match token {
+            Token::RESERVED(_) => get_tok_content(token, src).to_ascii_lowercase(),
+            _ => String::new(),
+        };
+        match keyword.as_str() {
+            "if" | "repeat" | "while" | "procedure" => open += 1,
+            "end" | "until" | "else" | "elsif" | "elseif" if open == 0 => break,
+            "end" | "until" => open -= 1,
+            _ if open == 0 && match_tok(Some(&token), ";", src) => break,
+            _ => {}
         }
-        false
+        token_iter.next();
+    }
+}
+
+/// Skips an operand nested too deeply to parse: its prefix operators, then an
+/// expression in parentheses, an identifier with its index or a single literal.
+fn skip_operand(token_iter: &mut Peekable<Box<Iter<Token>>>, src: &str) {
+    while ["-", "+", "not"]
+        .iter()
+        .any(|op| match_tok(token_iter.peek(), op, src))
+    {
+        token_iter.next();
+    }
+    match token_iter.peek() {
+        Some(Token::IDENTIFIER(_)) => {
+            token_iter.next();
+            if let Some(Token::LBRACKET(_)) = token_iter.peek() {
+                skip_brackets(token_iter, src);
+            }
+        }
+        Some(Token::NUMBER(_)) | Some(Token::STRING(_)) => {
+            token_iter.next();
+        }
+        opening if match_tok(opening, "(", src) => skip_brackets(token_iter, src),
+        _ => {}
+    }
+}
+
+/// Skips from an opening bracket or parenthesis to the one closing it.
+fn skip_brackets(token_iter: &mut Peekable<Box<Iter<Token>>>, src: &str) {
+    let mut open = 0;
+    for token in token_iter {
+        match token {
+            Token::LBRACKET(_) => open += 1,
+            Token::RBRACKET(_) => open -= 1,
+            _ if match_tok(Some(&token), "(", src) => open += 1,
+            _ if match_tok(Some(&token), ")", src) => open -= 1,
+            _ => {}
+        }
+        if open == 0 {
+            break;
+        }
+    }
+}
+
+fn is_elsif(opt_tok: Option<&&Token>, src: &str) -> bool {
+    match_tok(opt_tok, "elsif", src) || match_tok(opt_tok, "elseif", src)
+}
+
+fn is_comp_op(opt_tok: Option<&&Token>, src: &str, dialect: &Dialect) -> bool {
+    let ops: &[&str] = if dialect.comparison_ops {
+        &["<", "<=", ">", ">=", "=", "<>"]
     } else {
-        false
+        &["<", "="]
+    };
+    ops.iter().any(|op| match_tok(opt_tok, op, src))
+}
+
+/// Whether the token is the symbol or keyword `res`. Keywords only match reserved
+/// tokens, in any case if the dialect made them reserved regardless of case.
+fn match_tok(opt_tok: Option<&&Token>, res: &str, src: &str) -> bool {
+    match opt_tok {
+        Some(tok @ Token::RESERVED(_)) => get_tok_content(tok, src).eq_ignore_ascii_case(res),
+        Some(Token::IDENTIFIER(_)) | None => false,
+        Some(tok) => get_tok_content(tok, src) == res,
     }
 }
 
 fn get_tok_content<'a>(tok: &Token, src: &'a str) -> &'a str {
-    match *tok {
-        Token::RESERVED((i0, i1))
-        | Token::IDENTIFIER((i0, i1))
-        | Token::NUMBER((i0, i1))
-        | Token::COMMENT((i0, i1))
-        | Token::SYMBOL((i0, i1)) => &src[i0..i1],
-    }
+    let (i0, i1) = tok.get_span();
+    &src[i0..i1]
+}
+
+/// The message of an error node for `err`. Like the other error messages it leaves
+/// out the position, which is on the node and changes as `incremental` shifts it.
+fn lexical_error_message(err: &LexError) -> String {
+    format!("Lexical Error:\n{}", err.message)
 }
 
 fn add_error(parent_node: &mut Node, error_type: ErrorType, err_mess: String) {
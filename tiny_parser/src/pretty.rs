@@ -0,0 +1,165 @@
+//! Prints the typed tree back as TINY source, one statement per line, with only
+//! the brackets the grammar needs. Parsing the result gives the same program
+//! again, spans aside and with `-5` a literal even where it was `-` applied to `5`.
+
+use crate::ast::{BinOp, ElsifPart, Exp, Program, Stmt, UnOp, WriteItem};
+
+const INDENT: &str = "  ";
+
+pub fn to_source(program: &Program) -> String {
+    let mut src = String::new();
+    stmts(&program.body, 0, &mut src);
+    src
+}
+
+pub fn exp_to_source(exp: &Exp) -> String {
+    let mut src = String::new();
+    write_exp(exp, 0, &mut src);
+    src
+}
+
+fn stmts(stmts: &[Stmt], depth: usize, src: &mut String) {
+    for (i, s) in stmts.iter().enumerate() {
+        src.push_str(&INDENT.repeat(depth));
+        stmt(s, depth, src);
+        if i + 1 < stmts.len() {
+            src.push(';');
+        }
+        src.push('\n');
+    }
+}
+
+fn stmt(stmt: &Stmt, depth: usize, src: &mut String) {
+    let indent = INDENT.repeat(depth);
+    match stmt {
+        Stmt::If {
+            cond,
+            then_part,
+            elsif_parts,
+            else_part,
+            ..
+        } => {
+            *src += &format!("if {} then\n", exp_to_source(cond));
+            stmts(then_part, depth + 1, src);
+            for ElsifPart { cond, body, .. } in elsif_parts {
+                *src += &format!("{}elsif {} then\n", indent, exp_to_source(cond));
+                stmts(body, depth + 1, src);
+            }
+            if !else_part.is_empty() {
+                *src += &format!("{}else\n", indent);
+                stmts(else_part, depth + 1, src);
+            }
+            *src += &format!("{}end", indent);
+        }
+        Stmt::Repeat { body, cond, .. } => {
+            src.push_str("repeat\n");
+            stmts(body, depth + 1, src);
+            *src += &format!("{}until {}", indent, exp_to_source(cond));
+        }
+        Stmt::While { cond, body, .. } => {
+            *src += &format!("while {} do\n", exp_to_source(cond));
+            stmts(body, depth + 1, src);
+            *src += &format!("{}end", indent);
+        }
+        Stmt::Procedure {
+            name, params, body, ..
+        } => {
+            *src += &format!("procedure {}({})\n", name, params.join(", "));
+            stmts(body, depth + 1, src);
+            *src += &format!("{}end", indent);
+        }
+        Stmt::Call { name, args, .. } => {
+            let args: Vec<String> = args.iter().map(exp_to_source).collect();
+            *src += &format!("call {}({})", name, args.join(", "));
+        }
+        Stmt::Array { name, size, .. } => *src += &format!("array {}[{}]", name, size),
+        Stmt::Assign {
+            name, index, value, ..
+        } => *src += &format!("{} := {}", variable(name, index), exp_to_source(value)),
+        Stmt::Read { name, index, .. } => *src += &format!("read {}", variable(name, index)),
+        Stmt::Write { items, .. } => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| match item {
+                    WriteItem::String { value, .. } => string_literal(value),
+                    WriteItem::Exp(exp) => exp_to_source(exp),
+                })
+                .collect();
+            *src += &format!("write {}", items.join(", "));
+        }
+    }
+}
+
+fn variable(name: &str, index: &Option<Exp>) -> String {
+    match index {
+        Some(index) => format!("{}[{}]", name, exp_to_source(index)),
+        None => name.to_string(),
+    }
+}
+
+/// `value` as a string literal, quotes and escapes added.
+pub fn string_literal(value: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// How tightly an expression binds, following the grammar from `bool_exp` down
+/// to `factor`.
+fn precedence(exp: &Exp) -> u8 {
+    match exp {
+        Exp::BinOp { op, .. } => match op {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge | BinOp::Eq | BinOp::Ne => 4,
+            BinOp::Add | BinOp::Sub => 5,
+            BinOp::Mul | BinOp::Div => 6,
+        },
+        Exp::UnOp { op: UnOp::Not, .. } => 3,
+        Exp::UnOp { op: UnOp::Neg, .. } => 7,
+        Exp::Number { value, .. } if *value < 0 => 7,
+        Exp::Number { .. } | Exp::Identifier { .. } | Exp::Index { .. } => 8,
+    }
+}
+
+/// Writes `exp`, in brackets unless it binds at least as tightly as `min`.
+fn write_exp(exp: &Exp, min: u8, src: &mut String) {
+    let prec = precedence(exp);
+    if prec < min {
+        src.push('(');
+        write_exp(exp, 0, src);
+        src.push(')');
+        return;
+    }
+    match exp {
+        Exp::Number { value, .. } => *src += &value.to_string(),
+        Exp::Identifier { name, .. } => src.push_str(name),
+        Exp::Index { name, index, .. } => {
+            *src += &format!("{}[{}]", name, exp_to_source(index));
+        }
+        Exp::UnOp { op, operand, .. } => {
+            src.push_str(op.symbol());
+            if *op == UnOp::Not {
+                src.push(' ');
+            }
+            write_exp(operand, prec, src);
+        }
+        Exp::BinOp { op, lhs, rhs, .. } => {
+            // Comparisons don't chain, so neither side may be another one; the
+            // other operators associate to the left.
+            let lhs_min = if prec == 4 { prec + 1 } else { prec };
+            write_exp(lhs, lhs_min, src);
+            *src += &format!(" {} ", op.symbol());
+            write_exp(rhs, prec + 1, src);
+        }
+    }
+}
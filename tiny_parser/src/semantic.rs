@@ -0,0 +1,316 @@
+//! Checks on the typed tree that the grammar can't express: procedure calls and
+//! array accesses have to match their declarations, and arrays can't have more
+//! than `MAX_ARRAY_SIZE` elements.
+//!
+//! Scoping is deliberately simple: a procedure's parameters are its only locals
+//! and are passed by value; every other name, inside a procedure or not, refers
+//! to the global variable of that name. Procedures and arrays are declared at the
+//! top level only and exist from the start of the program, so a procedure may be
+//! called before its declaration, recursively too. Arrays are always global, and
+//! their names can only be used with an index unless a parameter shadows them.
+
+use crate::ast::{Exp, Program, Stmt, WriteItem};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use tiny_lexer::lexer::Span;
+
+/// Declaring a bigger array is an error, so that running a program can't take up
+/// all the memory.
+pub const MAX_ARRAY_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SemanticError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Semantic Error at {}..{}:\n{}",
+            self.span.0, self.span.1, self.message
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcInfo {
+    pub params: Vec<String>,
+    /// The global scalar variables the body uses.
+    pub globals: BTreeSet<String>,
+    pub span: Span,
+}
+
+/// What the checked program declares.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Symbols {
+    pub procedures: BTreeMap<String, ProcInfo>,
+    /// Array names and their sizes.
+    pub arrays: BTreeMap<String, usize>,
+    /// Every global scalar variable the program uses, in procedures or not.
+    pub globals: BTreeSet<String>,
+}
+
+/// Procedures (name to arity) and arrays (name to size) that exist before a
+/// program starts, because programs run earlier declared them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Declarations {
+    pub procedures: BTreeMap<String, usize>,
+    pub arrays: BTreeMap<String, usize>,
+}
+
+pub fn check(program: &Program) -> Result<Symbols, Vec<SemanticError>> {
+    check_with(program, &Declarations::default())
+}
+
+/// Like `check`, with everything in `known` declared beforehand.
+pub fn check_with(program: &Program, known: &Declarations) -> Result<Symbols, Vec<SemanticError>> {
+    let mut checker = Checker {
+        arities: known.procedures.clone(),
+        sizes: known.arrays.clone(),
+        symbols: Symbols::default(),
+        errors: vec![],
+    };
+
+    for stmt in &program.body {
+        checker.declare(stmt);
+    }
+
+    for stmt in &program.body {
+        match stmt {
+            Stmt::Procedure {
+                name, params, body, ..
+            } => {
+                let mut scope = Scope {
+                    locals: params,
+                    globals: BTreeSet::new(),
+                };
+                checker.stmts(body, &mut scope);
+                checker
+                    .symbols
+                    .globals
+                    .extend(scope.globals.iter().cloned());
+                checker.symbols.procedures.get_mut(name).unwrap().globals = scope.globals;
+            }
+            Stmt::Array { .. } => {}
+            stmt => {
+                let mut scope = Scope {
+                    locals: &[],
+                    globals: BTreeSet::new(),
+                };
+                checker.stmt(stmt, &mut scope);
+                checker.symbols.globals.extend(scope.globals);
+            }
+        }
+    }
+
+    if checker.errors.is_empty() {
+        Ok(checker.symbols)
+    } else {
+        Err(checker.errors)
+    }
+}
+
+struct Checker {
+    arities: BTreeMap<String, usize>,
+    sizes: BTreeMap<String, usize>,
+    symbols: Symbols,
+    errors: Vec<SemanticError>,
+}
+
+struct Scope<'a> {
+    locals: &'a [String],
+    globals: BTreeSet<String>,
+}
+
+impl Scope<'_> {
+    fn is_local(&self, name: &str) -> bool {
+        self.locals.iter().any(|local| local == name)
+    }
+
+    fn name(&mut self, name: &str) {
+        if !self.is_local(name) {
+            self.globals.insert(name.to_string());
+        }
+    }
+}
+
+impl Checker {
+    fn error(&mut self, span: Span, message: String) {
+        self.errors.push(SemanticError { span, message });
+    }
+
+    /// Records a top level declaration.
+    fn declare(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Procedure {
+                name, params, span, ..
+            } => {
+                if self.symbols.procedures.contains_key(name) {
+                    self.error(*span, format!("Procedure '{}' is already declared.", name));
+                }
+                for (i, param) in params.iter().enumerate() {
+                    if params[..i].contains(param) {
+                        self.error(
+                            *span,
+                            format!("Parameter '{}' of '{}' is declared twice.", param, name),
+                        );
+                    }
+                }
+                self.arities.insert(name.clone(), params.len());
+                self.symbols.procedures.insert(
+                    name.clone(),
+                    ProcInfo {
+                        params: params.clone(),
+                        globals: BTreeSet::new(),
+                        span: *span,
+                    },
+                );
+            }
+            Stmt::Array { name, size, span } => {
+                if self.symbols.arrays.contains_key(name) {
+                    self.error(*span, format!("Array '{}' is already declared.", name));
+                }
+                if *size == 0 {
+                    self.error(
+                        *span,
+                        format!("Array '{}' needs at least one element.", name),
+                    );
+                } else if *size > MAX_ARRAY_SIZE {
+                    self.error(
+                        *span,
+                        format!(
+                            "Array '{}' of {} elements is bigger than the limit of {}.",
+                            name, size, MAX_ARRAY_SIZE
+                        ),
+                    );
+                }
+                self.sizes.insert(name.clone(), *size);
+                self.symbols.arrays.insert(name.clone(), *size);
+            }
+            _ => {}
+        }
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt], scope: &mut Scope) {
+        for stmt in stmts {
+            self.stmt(stmt, scope);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, scope: &mut Scope) {
+        match stmt {
+            Stmt::If {
+                cond,
+                then_part,
+                elsif_parts,
+                else_part,
+                ..
+            } => {
+                self.exp(cond, scope);
+                self.stmts(then_part, scope);
+                for part in elsif_parts {
+                    self.exp(&part.cond, scope);
+                    self.stmts(&part.body, scope);
+                }
+                self.stmts(else_part, scope);
+            }
+            Stmt::Repeat { body, cond, .. } | Stmt::While { cond, body, .. } => {
+                self.stmts(body, scope);
+                self.exp(cond, scope);
+            }
+            Stmt::Procedure { name, span, .. } => self.error(
+                *span,
+                format!(
+                    "Procedure '{}' is declared inside another statement.\nSuggested Fix:\tDeclare procedures at the top level.",
+                    name
+                ),
+            ),
+            Stmt::Array { name, span, .. } => self.error(
+                *span,
+                format!(
+                    "Array '{}' is declared inside another statement.\nSuggested Fix:\tDeclare arrays at the top level.",
+                    name
+                ),
+            ),
+            Stmt::Call { name, args, span } => {
+                match self.arities.get(name) {
+                    None => self.error(*span, format!("Call to undeclared procedure '{}'.", name)),
+                    Some(&arity) if arity != args.len() => self.error(
+                        *span,
+                        format!(
+                            "Procedure '{}' takes {} argument(s) but {} were given.",
+                            name,
+                            arity,
+                            args.len()
+                        ),
+                    ),
+                    Some(_) => {}
+                }
+                for arg in args {
+                    self.exp(arg, scope);
+                }
+            }
+            Stmt::Assign {
+                name,
+                index,
+                value,
+                span,
+            } => {
+                self.variable(name, index.as_ref(), *span, scope);
+                self.exp(value, scope);
+            }
+            Stmt::Read { name, index, span } => self.variable(name, index.as_ref(), *span, scope),
+            Stmt::Write { items, .. } => {
+                for item in items {
+                    if let WriteItem::Exp(exp) = item {
+                        self.exp(exp, scope);
+                    }
+                }
+            }
+        }
+    }
+
+    fn exp(&mut self, exp: &Exp, scope: &mut Scope) {
+        match exp {
+            Exp::Number { .. } => {}
+            Exp::Identifier { name, span } => self.variable(name, None, *span, scope),
+            Exp::Index { name, index, span } => self.variable(name, Some(index), *span, scope),
+            Exp::BinOp { lhs, rhs, .. } => {
+                self.exp(lhs, scope);
+                self.exp(rhs, scope);
+            }
+            Exp::UnOp { operand, .. } => self.exp(operand, scope),
+        }
+    }
+
+    /// Checks a use of `name`, or of `name[index]`.
+    fn variable(&mut self, name: &str, index: Option<&Exp>, span: Span, scope: &mut Scope) {
+        let size = match self.sizes.get(name) {
+            Some(&size) if !scope.is_local(name) => Some(size),
+            _ => None,
+        };
+        match (index, size) {
+            (None, None) => scope.name(name),
+            (None, Some(_)) => {
+                self.error(span, format!("Array '{}' is used without an index.", name))
+            }
+            (Some(_), None) => self.error(span, format!("'{}' is not an array.", name)),
+            (Some(index), Some(size)) => {
+                if let Exp::Number { value, span } = index {
+                    if *value < 0 || *value as u64 >= size as u64 {
+                        self.error(
+                            *span,
+                            format!(
+                                "Index {} is out of bounds for array '{}' of size {}.",
+                                value, name, size
+                            ),
+                        );
+                    }
+                }
+                self.exp(index, scope);
+            }
+        }
+    }
+}
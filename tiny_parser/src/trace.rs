@@ -0,0 +1,375 @@
+//! A record of the parser at work: every entry into and exit out of a grammar
+//! rule, with the lookahead token it started on and the source it consumed.
+//! It can be printed as an indented trace, a JSON event stream or the leftmost
+//! derivation the parse amounts to.
+
+use crate::{program, top_level_stmts, Dialect, Node, MAX_RULE_DEPTH};
+use json::{object, JsonValue};
+use std::iter::Peekable;
+use std::slice::Iter;
+use tiny_lexer::lexer::{tokenize_with, Span, Token};
+
+/// The grammar rules, one per parsing function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    Program,
+    StmtSeq,
+    Stmt,
+    IfStmt,
+    ElsifClause,
+    RepeatStmt,
+    WhileStmt,
+    ProcDecl,
+    CallStmt,
+    ArrayDecl,
+    AssignStmt,
+    Variable,
+    ReadStmt,
+    WriteStmt,
+    BoolExp,
+    OrOp,
+    AndExp,
+    AndOp,
+    NotExp,
+    Exp,
+    CompOp,
+    SimpleExp,
+    AddOp,
+    Term,
+    MulOp,
+    Unary,
+    Factor,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    /// `rule` was entered with `lookahead` (the span of token index `token`) as
+    /// the next token, `None` at the end of the input.
+    Enter {
+        rule: Rule,
+        depth: usize,
+        token: usize,
+        lookahead: Option<Span>,
+    },
+    /// `rule` returned after consuming the token indices `tokens.0..tokens.1`,
+    /// which cover `consumed` in the source.
+    Exit {
+        rule: Rule,
+        depth: usize,
+        tokens: (usize, usize),
+        consumed: Option<Span>,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    enabled: bool,
+    tokens: Vec<Span>,
+    events: Vec<TraceEvent>,
+    stack: Vec<(Rule, usize)>,
+    /// How many rules are being parsed, counted whether or not they're recorded.
+    depth: usize,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Program => "program",
+            Rule::StmtSeq => "stmt_seq",
+            Rule::Stmt => "stmt",
+            Rule::IfStmt => "if_stmt",
+            Rule::ElsifClause => "elsif_clause",
+            Rule::RepeatStmt => "repeat_stmt",
+            Rule::WhileStmt => "while_stmt",
+            Rule::ProcDecl => "proc_decl",
+            Rule::CallStmt => "call_stmt",
+            Rule::ArrayDecl => "array_decl",
+            Rule::AssignStmt => "assign_stmt",
+            Rule::Variable => "variable",
+            Rule::ReadStmt => "read_stmt",
+            Rule::WriteStmt => "write_stmt",
+            Rule::BoolExp => "bool_exp",
+            Rule::OrOp => "or_op",
+            Rule::AndExp => "and_exp",
+            Rule::AndOp => "and_op",
+            Rule::NotExp => "not_exp",
+            Rule::Exp => "exp",
+            Rule::CompOp => "comp_op",
+            Rule::SimpleExp => "simple_exp",
+            Rule::AddOp => "add_op",
+            Rule::Term => "term",
+            Rule::MulOp => "mulop",
+            Rule::Unary => "unary",
+            Rule::Factor => "factor",
+        }
+    }
+}
+
+/// Parses `src` like `parse_with` does while tracing every rule.
+pub fn parse_traced(src: &str, simplified: bool, dialect: &Dialect) -> (Node, Trace) {
+    let tokens = tokenize_with(src, false, dialect);
+    let mut trace = Trace::new(&tokens);
+    let stmts = top_level_stmts(&tokens, 0, src, simplified, dialect, &mut trace, |_| None);
+    let root = program(stmts.into_iter().map(|stmt| stmt.node), simplified);
+    (root, trace)
+}
+
+impl Trace {
+    pub(crate) fn new(tokens: &[Token]) -> Trace {
+        Trace {
+            enabled: true,
+            tokens: tokens.iter().map(Token::get_span).collect(),
+            ..Trace::default()
+        }
+    }
+
+    /// A trace that records nothing, for the usual untraced parse.
+    pub(crate) fn disabled() -> Trace {
+        Trace::default()
+    }
+
+    pub(crate) fn enter(&mut self, rule: Rule, token_iter: &Peekable<Box<Iter<Token>>>) {
+        self.depth += 1;
+        if !self.enabled {
+            return;
+        }
+        let token = self.tokens.len() - token_iter.len();
+        self.events.push(TraceEvent::Enter {
+            rule,
+            depth: self.stack.len(),
+            token,
+            lookahead: self.tokens.get(token).copied(),
+        });
+        self.stack.push((rule, token));
+    }
+
+    pub(crate) fn exit(&mut self, token_iter: &Peekable<Box<Iter<Token>>>) {
+        self.depth -= 1;
+        if !self.enabled {
+            return;
+        }
+        let (rule, start) = self.stack.pop().unwrap();
+        let end = self.tokens.len() - token_iter.len();
+        self.events.push(TraceEvent::Exit {
+            rule,
+            depth: self.stack.len(),
+            tokens: (start, end),
+            consumed: self.consumed(start, end),
+        });
+    }
+
+    /// Whether the rules being parsed nest deeper than `MAX_RULE_DEPTH`.
+    pub(crate) fn too_deep(&self) -> bool {
+        self.depth > MAX_RULE_DEPTH
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    fn consumed(&self, start: usize, end: usize) -> Option<Span> {
+        if start < end {
+            Some((self.tokens[start].0, self.tokens[end - 1].1))
+        } else {
+            None
+        }
+    }
+
+    /// One line per event, indented by depth:
+    /// `-> rule  [lookahead]` on entry and `<- rule  'consumed' (start..end)` on exit.
+    pub fn to_text(&self, src: &str) -> String {
+        let mut text = String::new();
+        for event in &self.events {
+            let line = match event {
+                TraceEvent::Enter {
+                    rule,
+                    depth,
+                    lookahead,
+                    ..
+                } => format!(
+                    "{}-> {}  [{}]",
+                    "  ".repeat(*depth),
+                    rule.name(),
+                    match lookahead {
+                        Some(span) => &src[span.0..span.1],
+                        None => "EOF",
+                    }
+                ),
+                TraceEvent::Exit {
+                    rule,
+                    depth,
+                    consumed,
+                    ..
+                } => format!(
+                    "{}<- {}  {}",
+                    "  ".repeat(*depth),
+                    rule.name(),
+                    match consumed {
+                        Some(span) =>
+                            format!("'{}' ({}..{})", &src[span.0..span.1], span.0, span.1),
+                        None => "consumed nothing".to_string(),
+                    }
+                ),
+            };
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The events as a JSON array of `{event: "enter"|"exit", rule, depth, ...}`
+    /// objects, entries carrying their lookahead and exits what they consumed.
+    pub fn to_json(&self, src: &str) -> JsonValue {
+        let span_json = |span: &Option<Span>| match span {
+            Some(span) => object! {
+                "span" => vec![span.0, span.1],
+                "content" => &src[span.0..span.1],
+            },
+            None => JsonValue::Null,
+        };
+        let events: Vec<JsonValue> = self
+            .events
+            .iter()
+            .map(|event| match event {
+                TraceEvent::Enter {
+                    rule,
+                    depth,
+                    token,
+                    lookahead,
+                } => object! {
+                    "event" => "enter",
+                    "rule" => rule.name(),
+                    "depth" => *depth,
+                    "token" => *token,
+                    "lookahead" => span_json(lookahead),
+                },
+                TraceEvent::Exit {
+                    rule,
+                    depth,
+                    tokens,
+                    consumed,
+                } => object! {
+                    "event" => "exit",
+                    "rule" => rule.name(),
+                    "depth" => *depth,
+                    "tokens" => vec![tokens.0, tokens.1],
+                    "consumed" => span_json(consumed),
+                },
+            })
+            .collect();
+        JsonValue::from(events)
+    }
+
+    /// The leftmost derivation of the traced parse, one sentential form per
+    /// line, starting from `<program>`. A rule's right-hand side is made of the
+    /// rules it called and the tokens it consumed itself, in source order.
+    pub fn derivation(&self, src: &str) -> Vec<String> {
+        let calls = match self.calls() {
+            Some(calls) => calls,
+            None => return vec![],
+        };
+
+        let mut form = vec![Symbol::Rule(0)];
+        let mut lines = vec![self.render(&form, &calls, src)];
+        while let Some(i) = form.iter().position(|sym| matches!(sym, Symbol::Rule(_))) {
+            let call = match form[i] {
+                Symbol::Rule(call) => &calls[call],
+                Symbol::Token(_) => unreachable!(),
+            };
+            form.splice(i..=i, call.rhs());
+            lines.push(self.render(&form, &calls, src));
+        }
+        lines
+    }
+
+    /// Rebuilds the tree of rule invocations from the events, the root first.
+    fn calls(&self) -> Option<Vec<Call>> {
+        let mut calls: Vec<Call> = vec![];
+        let mut open = vec![];
+        for event in &self.events {
+            match event {
+                TraceEvent::Enter { rule, token, .. } => {
+                    let id = calls.len();
+                    if let Some(&parent) = open.last() {
+                        let parent: &mut Call = &mut calls[parent];
+                        parent.children.push(id);
+                    }
+                    calls.push(Call {
+                        rule: *rule,
+                        tokens: (*token, *token),
+                        children: vec![],
+                        child_tokens: vec![],
+                    });
+                    open.push(id);
+                }
+                TraceEvent::Exit { tokens, .. } => {
+                    let id = open.pop()?;
+                    calls[id].tokens = *tokens;
+                    if let Some(&parent) = open.last() {
+                        calls[parent].child_tokens.push(*tokens);
+                    }
+                }
+            }
+        }
+        if calls.is_empty() {
+            None
+        } else {
+            Some(calls)
+        }
+    }
+
+    fn render(&self, form: &[Symbol], calls: &[Call], src: &str) -> String {
+        if form.is_empty() {
+            return "ε".to_string();
+        }
+        let symbols: Vec<String> = form
+            .iter()
+            .map(|sym| match sym {
+                Symbol::Rule(call) => format!("<{}>", calls[*call].rule.name()),
+                Symbol::Token(token) => {
+                    let span = self.tokens[*token];
+                    src[span.0..span.1].to_string()
+                }
+            })
+            .collect();
+        symbols.join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Symbol {
+    Rule(usize),
+    Token(usize),
+}
+
+/// A single invocation of a rule: the calls it made and the token ranges they
+/// consumed, in order.
+struct Call {
+    rule: Rule,
+    tokens: (usize, usize),
+    children: Vec<usize>,
+    child_tokens: Vec<(usize, usize)>,
+}
+
+impl Call {
+    fn rhs(&self) -> Vec<Symbol> {
+        let mut rhs = vec![];
+        let mut children = self.children.iter().zip(&self.child_tokens).peekable();
+        let mut token = self.tokens.0;
+        loop {
+            match children.peek() {
+                Some((&child, &(start, end))) if start <= token => {
+                    rhs.push(Symbol::Rule(child));
+                    token = token.max(end);
+                    children.next();
+                }
+                _ if token < self.tokens.1 => {
+                    rhs.push(Symbol::Token(token));
+                    token += 1;
+                }
+                _ => break,
+            }
+        }
+        rhs
+    }
+}
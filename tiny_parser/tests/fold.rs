@@ -0,0 +1,112 @@
+use tiny_parser::ast::{lower_with, parse_program, Exp, Stmt, WriteItem};
+use tiny_parser::fold::fold;
+use tiny_parser::pretty::to_source;
+use tiny_parser::{parse_with, Dialect};
+
+fn folded(src: &str) -> String {
+    to_source(&fold(&parse_program(src).unwrap()).unwrap())
+}
+
+#[test]
+fn folds_constant_subexpressions() {
+    assert_eq!(folded("x := 2 * 3 + y "), "x := 6 + y\n");
+    assert_eq!(folded("x := y + 2 * 3 "), "x := y + 6\n");
+    assert_eq!(
+        folded("if 1 < 2 and not 0 then write 7 / 2, -(3 - 5) end "),
+        "if 1 then\n  write 3, 2\nend\n"
+    );
+    // The left operand is folded on its own: `y + 1 + 2` is `(y + 1) + 2`.
+    assert_eq!(folded("x := y + 1 + 2 "), "x := y + 1 + 2\n");
+}
+
+#[test]
+fn folding_wraps_like_the_interpreter() {
+    assert_eq!(
+        folded("x := 9223372036854775807 + 1 "),
+        "x := -9223372036854775808\n"
+    );
+    assert_eq!(
+        folded("x := -9223372036854775808 / -1 "),
+        "x := -9223372036854775808\n"
+    );
+}
+
+#[test]
+fn identities_are_dropped() {
+    assert_eq!(
+        folded("x := y * 1 + 0; z := 1 * (0 + y) - 0; w := y / 1; v := - -y "),
+        "x := y;\nz := y;\nw := y;\nv := y\n"
+    );
+    assert_eq!(folded("x := 0 and y; z := 2 or y "), "x := 0;\nz := 1\n");
+}
+
+#[test]
+fn run_time_errors_are_kept() {
+    // `a[y]` may be out of bounds and `1 / y` may divide by zero.
+    assert_eq!(
+        folded("array a[2]; x := a[y] * 0 + (1 / y) * 0 "),
+        "array a[2];\nx := a[y] * 0 + 1 / y * 0\n"
+    );
+}
+
+#[test]
+fn division_by_a_constant_zero_is_an_error() {
+    let src = "x := 1; y := x / (2 - 2) ";
+    let errors = fold(&parse_program(src).unwrap()).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span, (13, 23));
+    assert!(errors[0].message.starts_with("Division by zero."));
+}
+
+#[test]
+fn folded_expressions_keep_their_span() {
+    let src = "write (1 + 2) * 3 ";
+    let program = fold(&parse_program(src).unwrap()).unwrap();
+    match &program.body[0] {
+        Stmt::Write { items, .. } => assert_eq!(
+            items[0],
+            WriteItem::Exp(Exp::Number {
+                value: 9,
+                span: (7, 17)
+            })
+        ),
+        stmt => panic!("expected a write, found {:?}", stmt),
+    }
+}
+
+#[test]
+fn printed_source_parses_back_into_the_same_program() {
+    let src = "{ all of it } procedure p(a, b) write a, \"\\\"quoted\\\"\\n\" end;
+array arr[3];
+read x; read arr[x - 1];
+if x < 1 then write 1 elsif x = 2 then write 2 else call p(x, -x) end;
+repeat x := x - 1 until x <= 0 or not (arr[0] <> 1);
+while (a - (b - c)) * (d + e) > -(a + b) do a := a / 2 end ";
+    let printed = to_source(&parse_program(src).unwrap());
+    assert_eq!(
+        printed,
+        "procedure p(a, b)
+  write a, \"\\\"quoted\\\"\\n\"
+end;
+array arr[3];
+read x;
+read arr[x - 1];
+if x < 1 then
+  write 1
+elsif x = 2 then
+  write 2
+else
+  call p(x, -x)
+end;
+repeat
+  x := x - 1
+until x <= 0 or not arr[0] <> 1;
+while (a - (b - c)) * (d + e) > -(a + b) do
+  a := a / 2
+end
+"
+    );
+    let dialect = Dialect::extended();
+    let reparsed = lower_with(&parse_with(&printed, true, &dialect), &printed, &dialect).unwrap();
+    assert_eq!(to_source(&reparsed), printed);
+}
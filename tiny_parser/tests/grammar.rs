@@ -0,0 +1,673 @@
+use tiny_parser::desugar::desugar_elsif;
+use tiny_parser::{
+    parse, parse_with, Dialect, ErrorType, IntWidth, Node, NodeType, OpType, StmtType,
+};
+
+/// Parses `src` in the extended dialect, which most of these tests are about.
+fn parse_extended(src: &str, simplified: bool) -> Node {
+    parse_with(src, simplified, &Dialect::extended())
+}
+
+fn types(nodes: &[Node]) -> Vec<NodeType> {
+    nodes.iter().map(|node| node.n_type.clone()).collect()
+}
+
+fn first_stmt(src: &str, simplified: bool) -> Node {
+    let root = parse_extended(src, simplified);
+    if simplified {
+        root.children[0].clone()
+    } else {
+        root.children[0].children[0].clone()
+    }
+}
+
+fn errors(node: &Node, found: &mut Vec<ErrorType>) {
+    if let NodeType::Error(error_type, _) = &node.n_type {
+        found.push(error_type.clone());
+    }
+    for child in node.children.iter().chain(node.nextstmt.iter()) {
+        errors(child, found);
+    }
+}
+
+fn error_types(src: &str) -> Vec<ErrorType> {
+    let mut found = vec![];
+    errors(&parse_extended(src, true), &mut found);
+    found
+}
+
+#[test]
+fn deep_nesting_is_an_error_instead_of_a_stack_overflow() {
+    let nested = |open: &str, inner: &str, close: &str, levels| {
+        format!("{}{}{}", open.repeat(levels), inner, close.repeat(levels))
+    };
+    let sources = [
+        format!("x := {} ", nested("(", "1", ")", 100_000)),
+        format!("x := {} ", nested("not ", "1", "", 100_000)),
+        format!("x := {} ", nested("-", "1", "", 100_000)),
+        format!("x := {} ", nested("a[", "1", "]", 100_000)),
+        format!(
+            "{}; write x ",
+            nested("if x then ", "x := 1", " end", 100_000)
+        ),
+        format!(
+            "{}; write x ",
+            nested("repeat ", "x := 1", " until x", 100_000)
+        ),
+    ];
+    for src in &sources {
+        for &simplified in &[true, false] {
+            let mut found = vec![];
+            errors(&parse_extended(src, simplified), &mut found);
+            assert!(!found.is_empty(), "{}", &src[..20]);
+            assert!(
+                found
+                    .iter()
+                    .all(|found| *found == ErrorType::NestedTooDeeply),
+                "{}",
+                &src[..20]
+            );
+        }
+    }
+
+    let src = format!("x := {} ", nested("(", "1", ")", 50));
+    assert_eq!(error_types(&src), vec![]);
+}
+
+#[test]
+fn while_loops_in_the_simplified_tree() {
+    let src = "while x < 3 do x := x + 1; write x end ";
+    let stmt = first_stmt(src, true);
+    assert_eq!(stmt.n_type, NodeType::Stmt(StmtType::WhileStmt));
+    assert_eq!(stmt.children.len(), 2);
+    assert_eq!(stmt.children[0].op_symbol(src, &Dialect::extended()), "<");
+    assert_eq!(
+        stmt.children[1].n_type,
+        NodeType::Stmt(StmtType::AssignStmt)
+    );
+    assert_eq!(
+        stmt.children[1].nextstmt[0].n_type,
+        NodeType::Stmt(StmtType::WriteStmt)
+    );
+}
+
+#[test]
+fn while_loops_in_the_full_tree() {
+    let src = "while x do read x end ";
+    let stmt = first_stmt(src, false);
+    assert_eq!(stmt.n_type, NodeType::Stmt(StmtType::WhileStmt));
+    assert_eq!(
+        types(&stmt.children),
+        vec![
+            NodeType::Keyword,
+            NodeType::Factor,
+            NodeType::Keyword,
+            NodeType::StmtSeq,
+            NodeType::Keyword,
+        ]
+    );
+    let keywords: Vec<&str> = stmt
+        .children
+        .iter()
+        .filter(|node| node.n_type == NodeType::Keyword)
+        .map(|node| &src[node.span.0..node.span.1])
+        .collect();
+    assert_eq!(keywords, vec!["while", "do", "end"]);
+}
+
+#[test]
+fn statements_need_semicolons_between_them() {
+    assert_eq!(
+        error_types("x := 1 write x "),
+        vec![ErrorType::UnexpectedToken]
+    );
+    let mut found = vec![];
+    errors(
+        &parse_extended("read x; x := 1 write x ", false),
+        &mut found,
+    );
+    assert_eq!(found, vec![ErrorType::UnexpectedToken]);
+    // Nothing is reported twice after a statement that failed.
+    assert_eq!(
+        error_types("if x then write 1 write 2 "),
+        vec![ErrorType::NonEndedIfStmt]
+    );
+    assert_eq!(error_types("x := 1; write x "), vec![]);
+}
+
+#[test]
+fn programs_need_no_trailing_whitespace() {
+    for src in ["write 5", "x := 5", "read x", "x := 1; write x // done"] {
+        assert_eq!(error_types(src), vec![], "{:?}", src);
+    }
+    assert_eq!(first_stmt("read x", false).children[1].span, (5, 6));
+}
+
+#[test]
+fn unterminated_comments_are_lexical_errors() {
+    assert_eq!(error_types("x := 1 { one"), vec![ErrorType::LexicalError]);
+    let mut found = vec![];
+    errors(&parse_extended("x := 1; write x {", false), &mut found);
+    assert_eq!(found, vec![ErrorType::LexicalError]);
+    assert_eq!(error_types("x := 1 { one } "), vec![]);
+}
+
+#[test]
+fn while_loop_errors() {
+    assert_eq!(
+        error_types("while x read x end "),
+        vec![ErrorType::MissingDoKeyword]
+    );
+    assert_eq!(
+        error_types("while x do read x; write x "),
+        vec![ErrorType::UnexpectedEOF]
+    );
+    assert_eq!(
+        error_types("while x do read x until "),
+        vec![ErrorType::NonEndedWhileStmt]
+    );
+}
+
+#[test]
+fn comparison_operators() {
+    for op in &["<", "<=", ">", ">=", "=", "<>"] {
+        let src = format!("write a {} b ", op);
+        let stmt = first_stmt(&src, true);
+        let cmp = &stmt.children[0];
+        assert_eq!(cmp.n_type, NodeType::Op(OpType::CompOp));
+        assert_eq!(cmp.op_symbol(&src, &Dialect::extended()), *op);
+        assert_eq!(types(&cmp.children), vec![NodeType::Identifier; 2]);
+
+        let stmt = first_stmt(&src, false);
+        let exp = &stmt.children[1];
+        assert_eq!(exp.children[1].op_symbol(&src, &Dialect::extended()), *op);
+    }
+    // `a <= b` and `a < = b` are different programs.
+    assert_eq!(error_types("write a <= b "), vec![]);
+    assert_eq!(error_types("write a < = b ").len(), 1);
+}
+
+#[test]
+fn boolean_operators_bind_looser_than_comparisons() {
+    // not > and > or: `not a < b or c and not d` is `(not (a < b)) or (c and (not d))`.
+    let src = "if not a < b or c and not d then write 1 end ";
+    let cond = &first_stmt(src, true).children[0];
+    assert_eq!(cond.n_type, NodeType::Op(OpType::OrOp));
+    assert_eq!(cond.op_symbol(src, &Dialect::extended()), "or");
+
+    let (lhs, rhs) = (&cond.children[0], &cond.children[1]);
+    assert_eq!(lhs.n_type, NodeType::Op(OpType::NotOp));
+    assert_eq!(lhs.children.len(), 1);
+    assert_eq!(lhs.children[0].n_type, NodeType::Op(OpType::CompOp));
+    assert_eq!(rhs.n_type, NodeType::Op(OpType::AndOp));
+    assert_eq!(
+        types(&rhs.children),
+        vec![NodeType::Identifier, NodeType::Op(OpType::NotOp)]
+    );
+
+    let cond = &first_stmt(src, false).children[1];
+    assert_eq!(cond.n_type, NodeType::BoolExp);
+    assert_eq!(
+        types(&cond.children),
+        vec![
+            NodeType::NotExp,
+            NodeType::Op(OpType::OrOp),
+            NodeType::AndExp
+        ]
+    );
+}
+
+#[test]
+fn boolean_operators_chain_to_the_left() {
+    let src = "repeat x := 1 until a and b and (c or d) ";
+    let cond = &first_stmt(src, true).children[1];
+    assert_eq!(cond.n_type, NodeType::Op(OpType::AndOp));
+    assert_eq!(cond.children[0].n_type, NodeType::Op(OpType::AndOp));
+    assert_eq!(cond.children[1].n_type, NodeType::Op(OpType::OrOp));
+    assert_eq!(error_types("write a and "), vec![ErrorType::UnexpectedEOF]);
+}
+
+#[test]
+fn unary_signs_bind_tighter_than_multiplication() {
+    let src = "x := -a * -(b) ";
+    let value = &first_stmt(src, true).children[1];
+    assert_eq!(value.n_type, NodeType::Op(OpType::MulOp));
+    for operand in &value.children {
+        assert_eq!(operand.n_type, NodeType::Op(OpType::UnaryOp));
+        assert_eq!(operand.op_symbol(src, &Dialect::extended()), "-");
+        assert_eq!(operand.children.len(), 1);
+    }
+
+    let term = &first_stmt(src, false).children[2];
+    assert_eq!(
+        types(&term.children),
+        vec![
+            NodeType::UnaryExp,
+            NodeType::Op(OpType::MulOp),
+            NodeType::UnaryExp
+        ]
+    );
+    assert_eq!(
+        types(&term.children[0].children),
+        vec![NodeType::Op(OpType::UnaryOp), NodeType::Factor]
+    );
+}
+
+#[test]
+fn minus_after_an_operand_still_subtracts() {
+    let src = "write 3-1; write 3 - -1; write + - 2 ";
+    let stmt = first_stmt(src, true);
+    assert_eq!(stmt.children[0].n_type, NodeType::Op(OpType::AddOp));
+    assert_eq!(
+        types(&stmt.children[0].children),
+        vec![NodeType::Number(3), NodeType::Number(1)]
+    );
+
+    let stmt = &stmt.nextstmt[0];
+    assert_eq!(stmt.children[0].n_type, NodeType::Op(OpType::AddOp));
+    assert_eq!(
+        stmt.children[0].children[1].n_type,
+        NodeType::Op(OpType::UnaryOp)
+    );
+    assert_eq!(error_types(src), vec![]);
+    assert_eq!(error_types("write - "), vec![ErrorType::UnexpectedEOF]);
+}
+
+/// The node types of a tree, with `nextstmt` chains, but without spans.
+fn shape(node: &Node) -> String {
+    let children: Vec<String> = node.children.iter().map(shape).collect();
+    let next: Vec<String> = node.nextstmt.iter().map(shape).collect();
+    format!("{:?}[{}]{}", node.n_type, children.join(","), next.join(""))
+}
+
+#[test]
+fn elsif_chains_stay_flat() {
+    let src = "if a then x := 1 elsif b then x := 2 elseif c then x := 3 else x := 4 end ";
+    let stmt = first_stmt(src, true);
+    assert_eq!(
+        types(&stmt.children),
+        vec![
+            NodeType::Identifier,
+            NodeType::Stmt(StmtType::AssignStmt),
+            NodeType::Elsif,
+            NodeType::Elsif,
+            NodeType::Stmt(StmtType::AssignStmt),
+        ]
+    );
+    assert_eq!(
+        types(&stmt.children[3].children),
+        vec![NodeType::Identifier, NodeType::Stmt(StmtType::AssignStmt)]
+    );
+
+    let stmt = first_stmt(src, false);
+    assert_eq!(stmt.children[4].n_type, NodeType::Elsif);
+    assert_eq!(
+        types(&stmt.children[4].children),
+        vec![
+            NodeType::Keyword,
+            NodeType::Factor,
+            NodeType::Keyword,
+            NodeType::StmtSeq
+        ]
+    );
+    assert_eq!(
+        error_types("if a then x := 1 elsif b x := 2 end "),
+        vec![ErrorType::MissingThenKeyword]
+    );
+}
+
+#[test]
+fn elsif_chains_desugar_to_nested_ifs() {
+    let chained =
+        "if a then x := 1 elsif b then x := 2 elsif c then x := 3 else x := 4 end; write x ";
+    let nested = "if a then x := 1 else if b then x := 2 else if c then x := 3 else x := 4 end end end; write x ";
+    let desugared = desugar_elsif(&parse_extended(chained, true));
+    assert_eq!(shape(&desugared), shape(&parse_extended(nested, true)));
+
+    let inner = &desugared.children[0].children[2];
+    assert_eq!(
+        &chained[inner.span.0..inner.span.1],
+        "b then x := 2 elsif c then x := 3 else x := 4"
+    );
+
+    let plain = "if a then x := 1 end ";
+    assert_eq!(
+        desugar_elsif(&parse_extended(plain, true)),
+        parse_extended(plain, true)
+    );
+}
+
+#[test]
+#[should_panic(expected = "desugar_elsif takes the simplified tree")]
+fn only_the_simplified_tree_desugars() {
+    desugar_elsif(&parse_extended(
+        "if a then x := 1 elsif b then x := 2 end ",
+        false,
+    ));
+}
+
+#[test]
+fn procedure_declarations_and_calls() {
+    let src = "procedure add(a, b) sum := a + b end; call add(1, x * 2); call nothing() ";
+    let decl = first_stmt(src, true);
+    assert_eq!(decl.n_type, NodeType::Stmt(StmtType::ProcDecl));
+    assert_eq!(
+        types(&decl.children),
+        vec![
+            NodeType::Identifier,
+            NodeType::Params,
+            NodeType::Stmt(StmtType::AssignStmt)
+        ]
+    );
+    assert_eq!(
+        types(&decl.children[1].children),
+        vec![NodeType::Identifier; 2]
+    );
+
+    let call = &decl.nextstmt[0];
+    assert_eq!(call.n_type, NodeType::Stmt(StmtType::CallStmt));
+    let args = &call.children[1];
+    assert_eq!(args.n_type, NodeType::Args);
+    assert_eq!(
+        types(&args.children),
+        vec![NodeType::Number(1), NodeType::Op(OpType::MulOp)]
+    );
+    let empty = &call.nextstmt[0].children[1];
+    assert!(empty.children.is_empty());
+    assert_eq!(&src[empty.span.0..empty.span.1], "()");
+
+    let decl = first_stmt(src, false);
+    assert_eq!(
+        types(&decl.children),
+        vec![
+            NodeType::Keyword,
+            NodeType::Identifier,
+            NodeType::Params,
+            NodeType::StmtSeq,
+            NodeType::Keyword,
+        ]
+    );
+    assert_eq!(
+        types(&decl.children[2].children),
+        vec![
+            NodeType::OpeningBrace,
+            NodeType::Identifier,
+            NodeType::Symbol,
+            NodeType::Identifier,
+            NodeType::ClosingBrace,
+        ]
+    );
+}
+
+#[test]
+fn procedure_syntax_errors() {
+    assert_eq!(
+        error_types("procedure p x := 1 end "),
+        vec![ErrorType::MissingOpeningBracket]
+    );
+    assert_eq!(
+        error_types("call p(1, 2 "),
+        vec![ErrorType::MissingClosingBracket]
+    );
+    assert_eq!(
+        error_types("procedure p(1) x := 1 end "),
+        vec![ErrorType::ExpectedIdentifier]
+    );
+    assert_eq!(
+        error_types("procedure p() x := 1 until "),
+        vec![ErrorType::NonEndedProcedure]
+    );
+}
+
+#[test]
+fn arrays_and_index_expressions() {
+    let src = "array a[10]; a[i] := a[i - 1] + 1; read a[0]; write a[3] ";
+    let decl = first_stmt(src, true);
+    assert_eq!(decl.n_type, NodeType::Stmt(StmtType::ArrayDecl));
+    assert_eq!(
+        types(&decl.children),
+        vec![NodeType::Identifier, NodeType::Number(10)]
+    );
+    assert_eq!(&src[decl.span.0..decl.span.1], "a[10]");
+
+    let assign = &decl.nextstmt[0];
+    let target = &assign.children[0];
+    assert_eq!(target.n_type, NodeType::Index);
+    assert_eq!(&src[target.span.0..target.span.1], "a[i]");
+    assert_eq!(
+        types(&target.children),
+        vec![NodeType::Identifier, NodeType::Identifier]
+    );
+    let value = &assign.children[1];
+    assert_eq!(value.children[0].n_type, NodeType::Index);
+    assert_eq!(
+        value.children[0].children[1].n_type,
+        NodeType::Op(OpType::AddOp)
+    );
+
+    let read = &assign.nextstmt[0];
+    assert_eq!(read.children[0].n_type, NodeType::Index);
+
+    let decl = first_stmt(src, false);
+    assert_eq!(
+        types(&decl.children),
+        vec![
+            NodeType::Keyword,
+            NodeType::Identifier,
+            NodeType::Symbol,
+            NodeType::Number(10),
+            NodeType::Symbol,
+        ]
+    );
+    let full = parse_extended(src, false);
+    let target = &full.children[0].children[1].children[0];
+    assert_eq!(
+        types(&target.children),
+        vec![
+            NodeType::Identifier,
+            NodeType::Symbol,
+            NodeType::Factor,
+            NodeType::Symbol
+        ]
+    );
+}
+
+#[test]
+fn array_syntax_errors() {
+    assert_eq!(error_types("array a "), vec![ErrorType::ExpectedArraySize]);
+    assert_eq!(
+        error_types("array a[n] "),
+        vec![
+            ErrorType::ExpectedArraySize,
+            ErrorType::MissingClosingBracket
+        ]
+    );
+    assert_eq!(
+        error_types("write a[1 "),
+        vec![ErrorType::MissingClosingBracket]
+    );
+}
+
+#[test]
+fn write_takes_a_list_of_strings_and_expressions() {
+    let src = r#"write "x = ", x + 1, "!" "#;
+    let write = first_stmt(src, true);
+    assert_eq!(
+        types(&write.children),
+        vec![
+            NodeType::String,
+            NodeType::Op(OpType::AddOp),
+            NodeType::String
+        ]
+    );
+    assert_eq!(write.span, (6, 24));
+
+    let write = first_stmt(src, false);
+    assert_eq!(
+        types(&write.children),
+        vec![
+            NodeType::Keyword,
+            NodeType::String,
+            NodeType::Symbol,
+            NodeType::SimplExp,
+            NodeType::Symbol,
+            NodeType::String
+        ]
+    );
+}
+
+#[test]
+fn malformed_strings_are_lexical_errors() {
+    assert_eq!(
+        error_types("write \"abc\n; x := 1 "),
+        vec![ErrorType::LexicalError]
+    );
+    assert_eq!(error_types(r#"write "\q" "#), vec![ErrorType::LexicalError]);
+    assert_eq!(error_types("write 1, "), vec![ErrorType::UnexpectedEOF]);
+    assert_eq!(error_types(r#"x := "a" "#), vec![ErrorType::ExpectedFactor]);
+}
+
+fn textbook_error_types(src: &str) -> Vec<ErrorType> {
+    let mut found = vec![];
+    errors(&parse_with(src, true, &Dialect::textbook()), &mut found);
+    found
+}
+
+#[test]
+fn textbook_dialect_parses_textbook_programs() {
+    let src = "read x; if 0 < x then fact := 1; repeat fact := fact * x; x := x - 1 until x = 0; write fact end ";
+    assert_eq!(
+        parse_with(src, true, &Dialect::textbook()),
+        parse_extended(src, true)
+    );
+    assert!(textbook_error_types(src).is_empty());
+}
+
+#[test]
+fn textbook_dialect_rejects_the_extensions() {
+    assert_eq!(
+        textbook_error_types("while x do x := 0 end "),
+        vec![ErrorType::MissingAssignOp]
+    );
+    assert!(!textbook_error_types("if x > 1 then write x end ").is_empty());
+    assert!(!textbook_error_types("x := -1 ").is_empty());
+    assert_eq!(
+        textbook_error_types("repeat write 1, 2 until x ")[0],
+        ErrorType::MissingUntilKeyword
+    );
+    assert!(!textbook_error_types("write \"a\" ").is_empty());
+    assert!(!textbook_error_types("a[1] := 1 ").is_empty());
+    // Without the extension, its keywords are plain identifiers.
+    assert!(textbook_error_types("array := 1; and := array ").is_empty());
+}
+
+#[test]
+fn the_default_dialect_is_the_textbook() {
+    // The extended dialect reserves these, see `Dialect`.
+    let src = "do := 1; IF := do ";
+    assert!(!error_types(src).is_empty());
+    assert_eq!(
+        parse(src, true),
+        parse_with(src, true, &Dialect::textbook())
+    );
+    assert_eq!(
+        parse(src, false),
+        parse_with(src, false, &Dialect::textbook())
+    );
+}
+
+#[test]
+fn op_symbols_are_lexed_in_the_tree_dialect() {
+    let src = "write a <= b ";
+    let textbook = Dialect::textbook();
+    let root = parse_with(src, false, &textbook);
+    let exp = &root.children[0].children[0].children[1];
+    assert_eq!(exp.children[1].op_symbol(src, &textbook), "<");
+    let root = parse_extended(src, false);
+    let exp = &root.children[0].children[0].children[1];
+    assert_eq!(exp.children[1].op_symbol(src, &Dialect::extended()), "<=");
+}
+
+#[test]
+fn keywords_are_case_insensitive_in_the_extended_dialect() {
+    let stmt = first_stmt("IF x Then write 1 ELSE write 2 End ", true);
+    assert_eq!(stmt.n_type, NodeType::Stmt(StmtType::IfStmt));
+    assert!(error_types("WHILE x AND NOT y DO x := 0 END ").is_empty());
+}
+
+fn with_width(int_width: IntWidth) -> Dialect {
+    Dialect {
+        int_width,
+        ..Dialect::extended()
+    }
+}
+
+#[test]
+fn number_nodes_carry_their_value() {
+    let write = first_stmt("write 007 + 18446744073709551615 ", true);
+    assert_eq!(write.children[0].children[0].n_type, NodeType::Number(7));
+    let err = &write.children[0].children[1];
+    assert!(matches!(
+        err.n_type,
+        NodeType::Error(ErrorType::NumberOutOfRange, _)
+    ));
+    assert_eq!(err.span, (12, 32));
+}
+
+#[test]
+fn literals_have_to_fit_the_integer_width() {
+    let errors_in = |src: &str, width| {
+        let mut found = vec![];
+        errors(&parse_with(src, true, &with_width(width)), &mut found);
+        found
+    };
+    assert!(errors_in("x := 32767; y := -32768 ", IntWidth::Bits16).is_empty());
+    assert_eq!(
+        errors_in("x := 32768 ", IntWidth::Bits16),
+        vec![ErrorType::NumberOutOfRange]
+    );
+    // Only a literal right after the minus gets the extra value.
+    assert_eq!(
+        errors_in("x := -(32768); y := - + 32768 ", IntWidth::Bits16),
+        vec![ErrorType::NumberOutOfRange; 2]
+    );
+    assert!(errors_in("x := - -2147483648 ", IntWidth::Bits32).is_empty());
+    assert!(errors_in("x := 2147483647 ", IntWidth::Bits32).is_empty());
+    assert!(!errors_in("array a[2147483648] ", IntWidth::Bits32).is_empty());
+    assert!(errors_in("x := -9223372036854775808 ", IntWidth::Bits64).is_empty());
+    assert!(!errors_in("x := 9223372036854775808 ", IntWidth::Bits64).is_empty());
+
+    let root = parse_with("x := 99999 ", true, &with_width(IntWidth::Bits16));
+    if let NodeType::Error(_, message) = &root.children[0].children[1].n_type {
+        assert_eq!(
+            message.lines().take(2).collect::<Vec<_>>(),
+            vec![
+                "Number Out Of Range:",
+                "'99999' doesn't fit in a 16-bit integer, which holds -32768..32767."
+            ]
+        );
+    } else {
+        panic!("expected an error node");
+    }
+}
+
+#[test]
+fn hex_and_binary_literals_carry_their_decoded_value() {
+    let write = first_stmt("write 0xFF + 0b1_0000 ", true);
+    assert_eq!(write.children[0].children[0].n_type, NodeType::Number(255));
+    assert_eq!(write.children[0].children[1].n_type, NodeType::Number(16));
+
+    assert_eq!(error_types("write 0b2 "), vec![ErrorType::LexicalError]);
+    assert_eq!(error_types("x := 0x "), vec![ErrorType::LexicalError]);
+
+    let errors_in = |src: &str| {
+        let mut found = vec![];
+        errors(
+            &parse_with(src, true, &with_width(IntWidth::Bits16)),
+            &mut found,
+        );
+        found
+    };
+    assert!(errors_in("x := -0x8000 ").is_empty());
+    assert_eq!(errors_in("x := 0x8000 "), vec![ErrorType::NumberOutOfRange]);
+}
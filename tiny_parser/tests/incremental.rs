@@ -0,0 +1,177 @@
+use tiny_parser::incremental::{IncrementalParse, TextEdit};
+use tiny_parser::{parse, parse_with, Dialect};
+
+const SAMPLE: &str = "{ Sample program in TINY language computes factorial }
+read x; {input an integer }
+if 0 < x then { don't compute if x <= 0 }
+  fact := 1;
+  repeat
+    fact := fact * x;
+    x := x - 1
+  until x = 0;
+  write fact { output factorial of x }
+end;
+y := (fact + 2) * 3;
+write y;
+read z;
+z := z * z - y / 2;
+repeat z := z - 1 until z < 1;
+write z;
+if z = 0 then write 1 else write 0 end;
+x := (x);
+write x
+";
+
+const SNIPPETS: &[&str] = &[
+    "",
+    " ",
+    ";",
+    "x",
+    "fact",
+    "1",
+    "42",
+    "0x1F",
+    "0b",
+    "_",
+    ":=",
+    ":",
+    "=",
+    "<",
+    ">",
+    "<=",
+    ">=",
+    "<>",
+    "and",
+    "or",
+    "not",
+    "+",
+    "-",
+    "*",
+    "/",
+    "(",
+    "[",
+    "]",
+    "array",
+    ",",
+    "\"",
+    "\"x = \\\"\"",
+    "\\",
+    ")",
+    "{",
+    "//",
+    "\n",
+    "}",
+    "if",
+    "then",
+    "else",
+    "elsif",
+    "end",
+    "repeat",
+    "until",
+    "while",
+    "do",
+    "read",
+    "write",
+    "\n",
+    "z := 3;",
+    "if x < 1 then y := 2 end;",
+    "while 0 < z do z := z - 1 end;",
+];
+
+/// xorshift64*, so the edit sequences are random but reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn random_edit(rng: &mut Rng, src: &str) -> TextEdit {
+    let start = rng.below(src.len() + 1);
+    let end = if rng.below(3) == 0 {
+        start
+    } else {
+        start + rng.below(src.len() - start + 1).min(8)
+    };
+    TextEdit::new((start, end), SNIPPETS[rng.below(SNIPPETS.len())])
+}
+
+fn check_random_edits(simplified: bool, dialect: &Dialect) {
+    for seed in 1..=300 {
+        let mut rng = Rng(seed);
+        let mut doc = IncrementalParse::new(SAMPLE, simplified, dialect);
+        for _ in 0..25 {
+            let edit = random_edit(&mut rng, doc.src());
+            let before = doc.src().to_string();
+            doc.reparse(&edit).unwrap();
+            assert_eq!(
+                doc.root(),
+                &parse_with(doc.src(), simplified, dialect),
+                "seed {} diverged after {:?} on {:?}",
+                seed,
+                edit,
+                before
+            );
+        }
+    }
+}
+
+#[test]
+fn random_edits_match_full_reparse() {
+    check_random_edits(false, &Dialect::extended());
+    check_random_edits(false, &Dialect::textbook());
+}
+
+#[test]
+fn random_edits_match_full_simplified_reparse() {
+    check_random_edits(true, &Dialect::extended());
+    check_random_edits(true, &Dialect::textbook());
+}
+
+#[test]
+fn unchanged_statements_are_shifted() {
+    let mut doc = IncrementalParse::new("read x; write x; write 1 ", true, &Dialect::default());
+    doc.reparse(&TextEdit::new((5, 6), "abc")).unwrap();
+    assert_eq!(doc.src(), "read abc; write x; write 1 ");
+    assert_eq!(doc.root(), &parse(doc.src(), true));
+}
+
+#[test]
+fn only_edited_statements_are_parsed_again() {
+    let mut doc = IncrementalParse::new(SAMPLE, false, &Dialect::default());
+    let stmts = parse(SAMPLE, false).children[0].children.len();
+    let y = SAMPLE.find("y := (fact").unwrap();
+
+    // The statements before the edit are kept, the ones after it shifted.
+    doc.reparse(&TextEdit::new((y + 6, y + 10), "result"))
+        .unwrap();
+    assert_eq!(doc.root(), &parse(doc.src(), false));
+    assert_eq!(doc.reused(), stmts - 1);
+
+    // A new statement in the middle changes the lookahead of the one before it
+    // only.
+    doc.reparse(&TextEdit::new((y, y), "write 0; ")).unwrap();
+    assert_eq!(doc.root(), &parse(doc.src(), false));
+    assert_eq!(doc.reused(), stmts - 1);
+    assert_eq!(doc.root().children[0].children.len(), stmts + 1);
+}
+
+#[test]
+fn edits_that_dont_fit_are_rejected() {
+    let src = "write \"é\" ";
+    let mut doc = IncrementalParse::new(src, true, &Dialect::default());
+    for span in [(3, 2), (0, src.len() + 1), (8, 8)] {
+        let err = doc.reparse(&TextEdit::new(span, "x")).unwrap_err();
+        assert_eq!(err.span, span);
+    }
+    assert_eq!(doc.src(), src);
+    assert_eq!(doc.root(), &parse(src, true));
+}
@@ -0,0 +1,114 @@
+use tiny_parser::ast::{lower_with, parse_program, Program};
+use tiny_parser::semantic::{check, check_with, Declarations, MAX_ARRAY_SIZE};
+use tiny_parser::{parse_with, Dialect};
+
+/// The program in `src` before the semantic checks.
+fn lowered(src: &str) -> Program {
+    let dialect = Dialect::extended();
+    lower_with(&parse_with(src, true, &dialect), src, &dialect).unwrap()
+}
+
+fn errors(src: &str) -> Vec<String> {
+    let program = lowered(src);
+    match check(&program) {
+        Ok(_) => vec![],
+        Err(errors) => errors.into_iter().map(|err| err.message).collect(),
+    }
+}
+
+#[test]
+fn parameters_are_locals_and_everything_else_is_global() {
+    let src = "procedure inc(n) total := total + n; n := 0 end;
+read total; call inc(step); write total ";
+    let program = lowered(src);
+    let symbols = check(&program).unwrap();
+
+    let inc = &symbols.procedures["inc"];
+    assert_eq!(inc.params, vec!["n"]);
+    assert_eq!(inc.globals.iter().collect::<Vec<_>>(), vec!["total"]);
+    assert_eq!(
+        symbols.globals.iter().collect::<Vec<_>>(),
+        vec!["step", "total"]
+    );
+}
+
+#[test]
+fn calls_are_checked_against_declarations() {
+    assert!(errors("call later(1); procedure later(x) write x end ").is_empty());
+    assert!(errors("procedure loop(x) call loop(x - 1) end ").is_empty());
+    assert_eq!(
+        errors("procedure p(a, b) write a end; call p(1) "),
+        vec!["Procedure 'p' takes 2 argument(s) but 1 were given."]
+    );
+    assert_eq!(
+        errors("call q() "),
+        vec!["Call to undeclared procedure 'q'."]
+    );
+}
+
+#[test]
+fn declarations_are_checked() {
+    assert_eq!(
+        errors("procedure p() x := 1 end; procedure p() x := 2 end "),
+        vec!["Procedure 'p' is already declared."]
+    );
+    assert_eq!(
+        errors("procedure p(a, a) x := a end "),
+        vec!["Parameter 'a' of 'p' is declared twice."]
+    );
+    assert_eq!(errors("if 1 then procedure p() x := 1 end end ").len(), 1);
+}
+
+#[test]
+fn known_procedures_can_be_called() {
+    let src = "call p(1) ";
+    let program = lowered(src);
+    let known = Declarations {
+        procedures: vec![("p".to_string(), 1)].into_iter().collect(),
+        ..Declarations::default()
+    };
+    assert!(check_with(&program, &known).is_ok());
+    assert!(parse_program(src).unwrap_err()[0].contains("undeclared procedure 'p'"));
+}
+
+#[test]
+fn arrays_are_indexed_within_bounds() {
+    assert!(errors("array a[3]; a[2] := 1; write a[a[0]] ").is_empty());
+    assert!(errors("procedure p(a) write a end; array a[2]; write a[1] ").is_empty());
+    assert_eq!(
+        errors("array a[3]; write a; x := 1; write x[0] "),
+        vec![
+            "Array 'a' is used without an index.",
+            "'x' is not an array."
+        ]
+    );
+    assert_eq!(
+        errors("array a[3]; a[3] := 1; array b[0] "),
+        vec![
+            "Array 'b' needs at least one element.",
+            "Index 3 is out of bounds for array 'a' of size 3."
+        ]
+    );
+
+    let src = format!(
+        "array a[{}]; array b[{}] ",
+        MAX_ARRAY_SIZE,
+        MAX_ARRAY_SIZE + 1
+    );
+    assert_eq!(
+        errors(&src),
+        vec![format!(
+            "Array 'b' of {} elements is bigger than the limit of {}.",
+            MAX_ARRAY_SIZE + 1,
+            MAX_ARRAY_SIZE
+        )]
+    );
+
+    let src = "array a[3]; array b[4] ";
+    let program = lowered(src);
+    let arrays = check(&program).unwrap().arrays;
+    assert_eq!(
+        arrays.into_iter().collect::<Vec<_>>(),
+        vec![("a".to_string(), 3), ("b".to_string(), 4)]
+    );
+}
@@ -0,0 +1,90 @@
+use tiny_parser::trace::{parse_traced, Rule, TraceEvent};
+use tiny_parser::{parse, parse_with, Dialect};
+
+const SAMPLE: &str = "read x; if 0 < x then repeat x := x - 1 until x = 0 else write (x) end; y 2 ";
+
+#[test]
+fn traced_parse_builds_the_same_tree() {
+    for &simplified in &[true, false] {
+        for src in &[
+            SAMPLE,
+            "",
+            "x := ",
+            "write 1 + 2 * (3 - 4) ",
+            "x := 1 write x",
+            "write 1 { one",
+        ] {
+            let (root, _) = parse_traced(src, simplified, &Dialect::default());
+            assert_eq!(root, parse(src, simplified), "{:?}", src);
+        }
+    }
+}
+
+#[test]
+fn traced_parse_reports_the_same_errors() {
+    for dialect in &[Dialect::textbook(), Dialect::extended()] {
+        for &simplified in &[true, false] {
+            for src in &[
+                "x := 1 y := 2; write y",
+                "if x then write 1 else",
+                "repeat x := x - 1 until",
+                "while x do x := 0 end end",
+                "x := (1 + ; write 2 }",
+                "procedure p(a) write a end; call p(1, ",
+                "array a[3]; a[1 := 2 { not closed",
+                "x := 1 $ 2; y := @",
+            ] {
+                let (root, _) = parse_traced(src, simplified, dialect);
+                assert_eq!(root, parse_with(src, simplified, dialect), "{:?}", src);
+            }
+        }
+    }
+}
+
+#[test]
+fn events_are_balanced_and_record_consumed_spans() {
+    let src = "x := 1 + 2 ";
+    let (_, trace) = parse_traced(src, true, &Dialect::default());
+    let events = trace.events();
+    assert_eq!(
+        events.first(),
+        Some(&TraceEvent::Enter {
+            rule: Rule::Program,
+            depth: 0,
+            token: 0,
+            lookahead: Some((0, 1)),
+        })
+    );
+    assert_eq!(
+        events.last(),
+        Some(&TraceEvent::Exit {
+            rule: Rule::Program,
+            depth: 0,
+            tokens: (0, 5),
+            consumed: Some((0, 10)),
+        })
+    );
+
+    let enters = events
+        .iter()
+        .filter(|event| matches!(event, TraceEvent::Enter { .. }))
+        .count();
+    assert_eq!(enters * 2, events.len());
+
+    let text = trace.to_text(src);
+    assert!(text
+        .contains("\n                  -> add_op  [+]\n                  <- add_op  '+' (7..8)\n"));
+    assert_eq!(trace.to_json(src)[1]["rule"], "stmt_seq");
+}
+
+#[test]
+fn derivation_expands_the_leftmost_rule() {
+    let src = "read x; write (x) ";
+    let (_, trace) = parse_traced(src, true, &Dialect::default());
+    let derivation = trace.derivation(src);
+    assert_eq!(derivation[0], "<program>");
+    assert_eq!(derivation[2], "<stmt> ; <stmt>");
+    assert_eq!(derivation[4], "read <variable> ; <stmt>");
+    assert_eq!(derivation[5], "read x ; <stmt>");
+    assert_eq!(derivation.last().unwrap(), "read x ; write ( x )");
+}
@@ -0,0 +1,319 @@
+//! A small HTTP service around the lexer, parser and interpreter, serving the
+//! Treant tree viewer as well. Every request is handled in memory, so any number
+//! of users can parse at the same time.
+//!
+//! * `POST /tokenize`: the body is TINY source, replies with the token array.
+//! * `POST /parse?simplified=true&format=treant&dialect=textbook`: replies with the
+//!   tree; `format` is `treant` for the viewer's node structure, otherwise the plain
+//!   tree JSON, and `dialect=textbook` parses without the extensions. The root has
+//!   the lexer's `warnings` too, each a `message` and `span`, and so do the `errors`
+//!   replies of the other requests.
+//! * `POST /fold`: folds the program's constants, replies with the result as TINY
+//!   `source` and as its `tree` for the viewer.
+//! * `POST /cfg?format=dot&dominators=true`: replies with the control-flow graphs
+//!   of the procedures and the main program, as Graphviz with `format=dot` (with
+//!   the dominator trees as dashed edges if asked for), otherwise as JSON.
+//! * `POST /lint`: replies with the `warnings` about assignments that are never
+//!   used and variables read before they're assigned, each a `message` and `span`.
+//! * `POST /run?input=5,3`: runs the program with the given `read` values and
+//!   replies with its output and final variables and arrays.
+//!
+//! Other than with `dialect=textbook`, sources are read in the extended dialect,
+//! see `Dialect::extended`. Bodies over `MAX_BODY_SIZE` are refused with a 413.
+
+use json::{object, JsonValue};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use tiny_http::{Header, Request, Response, Server};
+use tiny_interpreter::Interpreter;
+use tiny_ir::cfg::{self, Cfg};
+use tiny_ir::lint;
+use tiny_lexer::lexer::{self, tokenize_with, Span};
+use tiny_parser::ast::parse_program;
+use tiny_parser::fold::fold;
+use tiny_parser::jsonify::{jsonify_node, jsonify_tokens, treantify_node_with};
+use tiny_parser::pretty::to_source;
+use tiny_parser::{parse_with, Dialect};
+
+/// Programs sent to `/run` are stopped after this many statements.
+pub const STEP_LIMIT: usize = 1_000_000;
+
+/// Request bodies bigger than this are turned away unread.
+pub const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// The stack of each worker thread, with room to spare for the nesting the parser
+/// accepts.
+const WORKER_STACK_SIZE: usize = 16 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(status: u16, body: JsonValue) -> Reply {
+        Reply {
+            status,
+            content_type: "application/json",
+            body: body.dump().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Reply {
+        Reply::json(status, object! { "error" => message })
+    }
+}
+
+/// Starts `workers` threads answering requests from `server`.
+pub fn spawn_workers(
+    server: Arc<Server>,
+    static_dir: PathBuf,
+    workers: usize,
+) -> Vec<JoinHandle<()>> {
+    (0..workers)
+        .map(|_| {
+            let server = server.clone();
+            let static_dir = static_dir.clone();
+            thread::Builder::new()
+                .stack_size(WORKER_STACK_SIZE)
+                .spawn(move || {
+                    for request in server.incoming_requests() {
+                        respond(request, &static_dir);
+                    }
+                })
+                .unwrap()
+        })
+        .collect()
+}
+
+fn respond(mut request: Request, static_dir: &Path) {
+    let mut body = String::new();
+    // One byte over the limit is enough for `handle` to turn the request away.
+    let mut reader = request.as_reader().take(MAX_BODY_SIZE as u64 + 1);
+    let reply = match reader.read_to_string(&mut body) {
+        Ok(_) => handle(request.method().as_str(), request.url(), &body, static_dir),
+        Err(_) => Reply::error(400, "The request body has to be UTF-8 text."),
+    };
+    let content_type = Header::from_bytes("Content-Type", reply.content_type).unwrap();
+    let response = Response::from_data(reply.body)
+        .with_status_code(reply.status)
+        .with_header(content_type);
+    // The client may have hung up already, there is no one left to tell.
+    let _ = request.respond(response);
+}
+
+/// Answers a single request.
+pub fn handle(method: &str, url: &str, body: &str, static_dir: &Path) -> Reply {
+    if body.len() > MAX_BODY_SIZE {
+        return Reply::error(
+            413,
+            &format!(
+                "The request body is bigger than the limit of {} bytes.",
+                MAX_BODY_SIZE
+            ),
+        );
+    }
+    let (path, query) = match url.find('?') {
+        Some(i) => (&url[..i], &url[i + 1..]),
+        None => (url, ""),
+    };
+
+    match (method, path) {
+        ("POST", "/tokenize") => Reply::json(
+            200,
+            jsonify_tokens(&tokenize_with(body, false, &Dialect::extended()), body),
+        ),
+        ("POST", "/parse") => {
+            let simplified = query_param(query, "simplified") != Some("false");
+            let dialect = if query_param(query, "dialect") == Some("textbook") {
+                Dialect::textbook()
+            } else {
+                Dialect::extended()
+            };
+            let root = parse_with(body, simplified, &dialect);
+            let mut tree = if query_param(query, "format") == Some("treant") {
+                treantify_node_with(&root, body, &dialect)
+            } else {
+                jsonify_node(&root)
+            };
+            tree["warnings"] = lex_warnings(body, &dialect);
+            Reply::json(200, tree)
+        }
+        ("POST", "/fold") => fold_constants(body),
+        ("POST", "/cfg") => control_flow(
+            body,
+            query_param(query, "format") == Some("dot"),
+            query_param(query, "dominators") == Some("true"),
+        ),
+        ("POST", "/lint") => warnings(body),
+        ("POST", "/run") => run(body, query_param(query, "input").unwrap_or("")),
+        ("GET", "/") => static_file(static_dir, "html/editor.html"),
+        ("GET", "/tree") => static_file(static_dir, "html/tree.html"),
+        ("GET", _) if path.starts_with("/stuff/") || path.starts_with("/ace/") => {
+            static_file(static_dir, &path[1..])
+        }
+        _ => Reply::error(404, "Not Found"),
+    }
+}
+
+fn fold_constants(src: &str) -> Reply {
+    let folded = parse_program(src).and_then(|program| {
+        fold(&program).map_err(|errors| errors.iter().map(ToString::to_string).collect())
+    });
+    match folded {
+        Ok(program) => {
+            let source = to_source(&program);
+            let dialect = Dialect::extended();
+            let tree = treantify_node_with(&parse_with(&source, true, &dialect), &source, &dialect);
+            Reply::json(200, object! { "source" => source, "tree" => tree })
+        }
+        Err(errors) => errors_reply(src, errors),
+    }
+}
+
+fn control_flow(src: &str, dot: bool, dominators: bool) -> Reply {
+    let program = match parse_program(src) {
+        Ok(program) => tiny_ir::lower(&program),
+        Err(errors) => return errors_reply(src, errors),
+    };
+    let cfgs: Vec<Cfg> = program.functions().map(Cfg::new).collect();
+    if dot {
+        Reply {
+            status: 200,
+            content_type: "text/vnd.graphviz",
+            body: cfg::to_dot(&cfgs, dominators).into_bytes(),
+        }
+    } else {
+        Reply::json(200, cfg::to_json(&cfgs))
+    }
+}
+
+fn warnings(src: &str) -> Reply {
+    let program = match parse_program(src) {
+        Ok(program) => tiny_ir::lower(&program),
+        Err(errors) => return errors_reply(src, errors),
+    };
+    let warnings: Vec<JsonValue> = lint::warnings(&program)
+        .into_iter()
+        .map(|warning| warning_json(warning.message, warning.span))
+        .collect();
+    Reply::json(200, object! { "warnings" => warnings })
+}
+
+/// The lexer's warnings about `src`, like keywords in the wrong case, which often
+/// explain the errors that come with them.
+fn lex_warnings(src: &str, dialect: &Dialect) -> JsonValue {
+    let warnings: Vec<JsonValue> =
+        lexer::warnings(src, &tokenize_with(src, false, dialect), dialect)
+            .into_iter()
+            .map(|warning| warning_json(warning.message, warning.span))
+            .collect();
+    warnings.into()
+}
+
+fn warning_json(message: String, span: Span) -> JsonValue {
+    object! {
+        "message" => message,
+        "span" => vec![span.0, span.1],
+    }
+}
+
+fn errors_reply(src: &str, errors: Vec<String>) -> Reply {
+    let errors: Vec<JsonValue> = errors.into_iter().map(JsonValue::from).collect();
+    Reply::json(
+        422,
+        object! { "errors" => errors, "warnings" => lex_warnings(src, &Dialect::extended()) },
+    )
+}
+
+fn run(src: &str, input: &str) -> Reply {
+    let input: Result<Vec<i64>, _> = input
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::parse)
+        .collect();
+    let input = match input {
+        Ok(input) => input,
+        Err(_) => {
+            return Reply::error(400, "'input' has to be a comma separated list of integers.")
+        }
+    };
+
+    let program = match parse_program(src) {
+        Ok(program) => program,
+        Err(errors) => return errors_reply(src, errors),
+    };
+
+    let mut interpreter = Interpreter::with_step_limit(STEP_LIMIT);
+    let mut output = vec![];
+    let result = interpreter.run(&program, &mut input.into_iter(), &mut output);
+
+    let mut vars = JsonValue::new_object();
+    for (name, value) in interpreter.vars() {
+        vars[name.as_str()] = (*value).into();
+    }
+    let mut arrays = JsonValue::new_object();
+    for (name, values) in interpreter.arrays() {
+        arrays[name.as_str()] = values.clone().into();
+    }
+    let error = match result {
+        Ok(()) => JsonValue::Null,
+        Err(err) => object! {
+            "message" => err.message,
+            "span" => vec![err.span.0, err.span.1],
+        },
+    };
+    Reply::json(
+        200,
+        object! {
+            "output" => String::from_utf8_lossy(&output).into_owned(),
+            "vars" => vars,
+            "arrays" => arrays,
+            "error" => error,
+        },
+    )
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            Some((parts.next()?, parts.next().unwrap_or("")))
+        })
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn static_file(static_dir: &Path, path: &str) -> Reply {
+    let path = Path::new(path);
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Reply::error(404, "Not Found");
+    }
+
+    let content_type = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "application/javascript",
+        Some("css") => "text/css",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    };
+    match fs::read(static_dir.join(path)) {
+        Ok(body) => Reply {
+            status: 200,
+            content_type,
+            body,
+        },
+        Err(_) => Reply::error(404, "Not Found"),
+    }
+}
@@ -0,0 +1,27 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use tiny_http::Server;
+use tiny_server::spawn_workers;
+
+const WORKERS: usize = 4;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:3000".to_string());
+    let static_dir = PathBuf::from(args.next().unwrap_or_else(|| "treant".to_string()));
+
+    let server = match Server::http(&addr) {
+        Ok(server) => Arc::new(server),
+        Err(err) => {
+            eprintln!("Couldn't listen on {}: {}", addr, err);
+            process::exit(1);
+        }
+    };
+    println!("Listening on http://{}", addr);
+
+    for worker in spawn_workers(server, static_dir, WORKERS) {
+        worker.join().unwrap();
+    }
+}
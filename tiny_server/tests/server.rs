@@ -0,0 +1,216 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use tiny_http::Server;
+use tiny_server::{handle, spawn_workers, MAX_BODY_SIZE};
+
+fn static_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../treant")
+}
+
+fn body_json(method: &str, url: &str, body: &str) -> (u16, json::JsonValue) {
+    let reply = handle(method, url, body, &static_dir());
+    let text = String::from_utf8(reply.body).unwrap();
+    (reply.status, json::parse(&text).unwrap())
+}
+
+#[test]
+fn tokenizes_and_parses() {
+    let (status, tokens) = body_json("POST", "/tokenize", "read x ");
+    assert_eq!(status, 200);
+    assert_eq!(tokens[1]["content"], "x");
+
+    let (_, tree) = body_json("POST", "/parse?simplified=true", "read x ");
+    assert_eq!(tree["children"][0]["type"], "Stmt(ReadStmt)");
+
+    let (_, tree) = body_json("POST", "/parse?simplified=false", "read x ");
+    assert_eq!(tree["children"][0]["type"], "StmtSeq");
+
+    let (_, tree) = body_json("POST", "/parse?format=treant", "x := 1; write x ");
+    assert_eq!(tree["children"][1]["text"]["type"], "Stmt(WriteStmt)");
+
+    let src = "procedure p(a) write a end; call p(2) ";
+    let (_, tree) = body_json("POST", "/parse?format=treant", src);
+    let decl = &tree["children"][0];
+    assert_eq!(decl["HTMLclass"], "stmt");
+    assert_eq!(decl["children"][1]["text"]["type"], "Params");
+    assert_eq!(decl["children"][1]["children"][0]["text"]["name"], "a");
+    assert_eq!(tree["children"][1]["text"]["type"], "Stmt(CallStmt)");
+}
+
+#[test]
+fn parse_replies_have_the_lexer_warnings() {
+    let src = "x := 1; IF x = 1 THEN write x END ";
+    let (_, tree) = body_json("POST", "/parse?dialect=textbook", src);
+    assert_eq!(tree["warnings"].len(), 3);
+    assert_eq!(tree["warnings"][0]["span"], json::array![8, 10]);
+    assert!(tree["warnings"][0]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Did you mean the keyword `if`?"));
+
+    let (_, tree) = body_json("POST", "/parse?format=treant", src);
+    assert!(tree["warnings"].is_array());
+    assert!(tree["warnings"].is_empty());
+
+    let (_, result) = body_json("POST", "/run", "x 1 ");
+    assert!(result["warnings"].is_array());
+}
+
+#[test]
+fn runs_programs() {
+    let src = "read x; read y; write x * y ";
+    let (status, result) = body_json("POST", "/run?input=6,7", src);
+    assert_eq!(status, 200);
+    assert_eq!(result["output"], "42\n");
+    assert_eq!(result["vars"]["y"], 7);
+    assert!(result["error"].is_null());
+
+    let (_, result) = body_json("POST", "/run", src);
+    assert_eq!(result["error"]["span"][0], 5);
+
+    let (status, result) = body_json("POST", "/run", "call p(1) ");
+    assert_eq!(status, 422);
+    assert_eq!(result["errors"].len(), 1);
+
+    let (status, result) = body_json("POST", "/run", "x 1 ");
+    assert_eq!(status, 422);
+    assert_eq!(result["errors"].len(), 1);
+}
+
+#[test]
+fn folds_constants() {
+    let (status, result) = body_json("POST", "/fold", "x := 2 * 3 + y * 1 ");
+    assert_eq!(status, 200);
+    assert_eq!(result["source"], "x := 6 + y\n");
+    let assign = &result["tree"]["children"][0];
+    assert_eq!(assign["text"]["type"], "Stmt(AssignStmt)");
+    assert_eq!(assign["children"][1]["children"][0]["text"]["name"], "6");
+
+    let (status, result) = body_json("POST", "/fold", "write 1 / 0 ");
+    assert_eq!(status, 422);
+    assert_eq!(result["errors"].len(), 1);
+}
+
+#[test]
+fn builds_control_flow_graphs() {
+    let src = "procedure p(n) write n end; read x; while x > 0 do call p(x); x := x - 1 end ";
+    let (status, cfgs) = body_json("POST", "/cfg", src);
+    assert_eq!(status, 200);
+    assert_eq!(cfgs.len(), 2);
+    assert_eq!(cfgs[0]["name"], "p");
+    let main = &cfgs[1];
+    assert_eq!(main["blocks"][0]["instrs"][0]["text"], "read x");
+    assert_eq!(main["blocks"][0]["instrs"][0]["span"][0], 33);
+    // The loop header is entered from before the loop and from its body.
+    assert_eq!(main["blocks"][1]["preds"], json::array![0, 2]);
+    assert_eq!(main["blocks"][2]["idom"], 1);
+    assert!(main["blocks"][0]["idom"].is_null());
+
+    let reply = handle(
+        "POST",
+        "/cfg?format=dot&dominators=true",
+        src,
+        &static_dir(),
+    );
+    assert_eq!(reply.content_type, "text/vnd.graphviz");
+    let dot = String::from_utf8(reply.body).unwrap();
+    assert!(dot.starts_with("digraph cfg {"));
+    assert!(dot.contains("\"main.B1\" -> \"main.B2\" [style = dashed"));
+
+    let (status, result) = body_json("POST", "/cfg", "call p(1) ");
+    assert_eq!(status, 422);
+    assert_eq!(result["errors"].len(), 1);
+}
+
+#[test]
+fn warns_about_dead_stores_and_unassigned_reads() {
+    let (status, result) = body_json("POST", "/lint", "x := 1; x := 2; write x + y ");
+    assert_eq!(status, 200);
+    let warnings = &result["warnings"];
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0]["span"], json::array![5, 6]);
+    assert!(warnings[0]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("The value assigned to 'x' here is never used."));
+    assert_eq!(warnings[1]["span"], json::array![22, 27]);
+
+    let (status, _) = body_json("POST", "/lint", "call p(1) ");
+    assert_eq!(status, 422);
+}
+
+#[test]
+fn serves_the_viewer_but_nothing_outside_it() {
+    let reply = handle("GET", "/tree", "", &static_dir());
+    assert_eq!(
+        (reply.status, reply.content_type),
+        (200, "text/html; charset=utf-8")
+    );
+    assert_eq!(
+        handle("GET", "/stuff/../index.js", "", &static_dir()).status,
+        404
+    );
+}
+
+/// Sends a request to the server at `addr`, returning the reply's body.
+fn post(addr: SocketAddr, url: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "POST {} HTTP/1.0\r\nContent-Length: {}\r\n\r\n{}",
+        url,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response[response.find("\r\n\r\n").unwrap() + 4..].to_string()
+}
+
+#[test]
+fn handles_concurrent_requests() {
+    let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+    let addr = server.server_addr().to_ip().unwrap();
+    spawn_workers(server, static_dir(), 4);
+
+    let clients: Vec<_> = (0..16)
+        .map(|i| {
+            thread::spawn(move || {
+                let body = post(addr, "/run", &format!("write {} * 2 ", i));
+                assert_eq!(
+                    json::parse(&body).unwrap()["output"],
+                    format!("{}\n", i * 2).as_str()
+                );
+            })
+        })
+        .collect();
+    for client in clients {
+        client.join().unwrap();
+    }
+}
+
+#[test]
+fn survives_deeply_nested_and_oversized_requests() {
+    let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+    let addr = server.server_addr().to_ip().unwrap();
+    spawn_workers(server, static_dir(), 1);
+
+    let nested = format!("x := {}1{} ", "(".repeat(200_000), ")".repeat(200_000));
+    for url in &["/parse", "/parse?simplified=false", "/run"] {
+        assert!(
+            post(addr, url, &nested).contains("nested too deeply"),
+            "{}",
+            url
+        );
+    }
+    let (status, reply) = body_json("POST", "/parse", &" ".repeat(MAX_BODY_SIZE + 1));
+    assert_eq!(status, 413);
+    assert!(reply["error"].as_str().unwrap().contains("limit"));
+
+    let body = post(addr, "/run", "write 6 * 7 ");
+    assert_eq!(json::parse(&body).unwrap()["output"], "42\n");
+}
@@ -0,0 +1,73 @@
+//! WebAssembly bindings for the lexer and parser, so the front ends can parse in
+//! process instead of writing the code to a file and spawning `tiny_parser`, and
+//! for the control-flow graphs shown next to the tree. Sources are read in the
+//! extended dialect, see `Dialect::extended`.
+//!
+//! `npm run build` in this directory produces a Node/Electron package in `pkg/`:
+//!
+//! ```js
+//! const tiny = require('./pkg/tiny_wasm');
+//! const tree = tiny.parse(editor.getValue(), true);
+//! ```
+
+use js_sys::JSON;
+use tiny_ir::cfg::{self, Cfg};
+use tiny_lexer::lexer;
+use tiny_parser::ast::parse_program;
+use tiny_parser::jsonify::{jsonify_node, jsonify_tokens};
+use tiny_parser::{parse_with, Dialect};
+use wasm_bindgen::prelude::*;
+
+/// Tokenizes `src`, returning an array of `{ type, span, content }` objects.
+#[wasm_bindgen]
+pub fn tokenize(src: &str, comments_allowed: bool) -> Result<JsValue, JsValue> {
+    JSON::parse(&tokenize_json(src, comments_allowed))
+}
+
+/// Parses `src`, returning the tree as nested `{ type, span, children, nextstmt }` objects.
+#[wasm_bindgen]
+pub fn parse(src: &str, simplified: bool) -> Result<JsValue, JsValue> {
+    JSON::parse(&parse_json(src, simplified))
+}
+
+/// Same as `tokenize`, but returns the JSON text.
+#[wasm_bindgen(js_name = tokenizeJson)]
+pub fn tokenize_json(src: &str, comments_allowed: bool) -> String {
+    jsonify_tokens(
+        &lexer::tokenize_with(src, comments_allowed, &Dialect::extended()),
+        src,
+    )
+    .dump()
+}
+
+/// Same as `parse`, but returns the JSON text.
+#[wasm_bindgen(js_name = parseJson)]
+pub fn parse_json(src: &str, simplified: bool) -> String {
+    jsonify_node(&parse_with(src, simplified, &Dialect::extended())).dump()
+}
+
+/// The control-flow graphs of the procedures and the main program, as an array of
+/// `{ name, params, entry, exit, blocks }` objects. Throws the program's errors,
+/// one per line, if it has any.
+#[wasm_bindgen]
+pub fn cfg(src: &str) -> Result<JsValue, JsValue> {
+    JSON::parse(&cfg_json(src)?)
+}
+
+/// Same as `cfg`, but returns the JSON text.
+#[wasm_bindgen(js_name = cfgJson)]
+pub fn cfg_json(src: &str) -> Result<String, JsValue> {
+    Ok(cfg::to_json(&cfgs(src)?).dump())
+}
+
+/// The control-flow graphs as Graphviz, with the dominator trees as dashed edges
+/// if `dominators` is set.
+#[wasm_bindgen(js_name = cfgDot)]
+pub fn cfg_dot(src: &str, dominators: bool) -> Result<String, JsValue> {
+    Ok(cfg::to_dot(&cfgs(src)?, dominators))
+}
+
+fn cfgs(src: &str) -> Result<Vec<Cfg>, JsValue> {
+    let program = parse_program(src).map_err(|errors| JsValue::from_str(&errors.join("\n")))?;
+    Ok(tiny_ir::lower(&program).functions().map(Cfg::new).collect())
+}
@@ -0,0 +1,53 @@
+//! Run under Node with `npm test` (or `cargo test -p tiny_wasm --target wasm32-unknown-unknown`).
+#![cfg(target_arch = "wasm32")]
+
+use js_sys::{Array, Reflect};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const SRC: &str = "read x; if 0 < x then write x end ";
+
+fn get(value: &JsValue, key: &str) -> JsValue {
+    Reflect::get(value, &JsValue::from_str(key)).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn tokenize_returns_token_objects() {
+    let tokens = Array::from(&tiny_wasm::tokenize(SRC, false).unwrap());
+    assert_eq!(tokens.length(), 11);
+    assert_eq!(get(&tokens.get(0), "type"), "RESERVED");
+    assert_eq!(get(&tokens.get(1), "content"), "x");
+    assert_eq!(get(&tokens.get(2), "content"), ";");
+}
+
+#[wasm_bindgen_test]
+fn parse_returns_tree_objects() {
+    let tree = tiny_wasm::parse(SRC, true).unwrap();
+    assert_eq!(get(&tree, "type"), "Program");
+
+    let read = Array::from(&get(&tree, "children")).get(0);
+    assert_eq!(get(&read, "type"), "Stmt(ReadStmt)");
+    let next = Array::from(&get(&read, "nextstmt")).get(0);
+    assert_eq!(get(&next, "type"), "Stmt(IfStmt)");
+}
+
+#[wasm_bindgen_test]
+fn json_matches_native_parser() {
+    let expected = tiny_parser::jsonify::jsonify_node(&tiny_parser::parse(SRC, false)).dump();
+    assert_eq!(tiny_wasm::parse_json(SRC, false), expected);
+}
+
+#[wasm_bindgen_test]
+fn cfg_returns_a_graph_per_function() {
+    let cfgs = Array::from(&tiny_wasm::cfg(SRC).unwrap());
+    assert_eq!(cfgs.length(), 1);
+    let main = cfgs.get(0);
+    assert_eq!(get(&main, "name"), "main");
+    let entry = Array::from(&get(&main, "blocks")).get(0);
+    assert_eq!(Array::from(&get(&entry, "succs")).length(), 2);
+
+    assert!(tiny_wasm::cfg("call p(1) ").is_err());
+    assert!(tiny_wasm::cfg_dot(SRC, true)
+        .unwrap()
+        .starts_with("digraph cfg {"));
+}
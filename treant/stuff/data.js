@@ -1,10 +1,15 @@
 var datajson ;
 
-$.getJSON("http://localhost:3000/tree/get",function( data ) {
-    //console.log(data);   
-    datajson = data;
-    //console.log(datajson);
-    do_dat_shit();
+$.ajax({
+    url: '/parse?format=treant&simplified=' + sessionStorage.getItem('simplified'),
+    type: 'POST',
+    data: sessionStorage.getItem('code') || '',
+    contentType: 'text/plain; charset=utf-8',
+    dataType: 'json',
+    success: function( data ) {
+      datajson = data;
+      do_dat_shit();
+    }
   });
 
 function do_dat_shit(){